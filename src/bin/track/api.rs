@@ -0,0 +1,245 @@
+use crate::config::Config;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub enum ApiError {
+    // Nothing answered, worth trying again later
+    Unreachable(String),
+    // The server answered with an error, sending it again will not help
+    Failed(String),
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_connect() || e.is_timeout() || e.is_request() {
+            ApiError::Unreachable(e.to_string())
+        } else {
+            ApiError::Failed(e.to_string())
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Unreachable(e) => write!(f, "server unreachable: {}", e),
+            ApiError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskBody<'a> {
+    pub name: &'a str,
+    pub user: Option<&'a str>,
+    pub initial_time: String,
+    pub end_time: String,
+    pub project: Option<&'a str>,
+}
+
+impl TaskBody<'_> {
+    pub fn time(time: DateTime<Utc>) -> String {
+        time.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Task {
+    pub _id: String,
+    pub name: String,
+    pub user: Option<String>,
+    pub initial_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub project: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TaskWritten {
+    _id: String,
+}
+
+#[derive(Deserialize)]
+struct ClientProjects {
+    projects: Vec<Project>,
+}
+
+#[derive(Deserialize)]
+struct Project {
+    _id: String,
+    name: String,
+    #[serde(default)]
+    subprojects: Vec<Project>,
+}
+
+fn flatten(projects: Vec<Project>, all: &mut Vec<(String, String)>) {
+    for project in projects {
+        all.push((project._id, project.name));
+        flatten(project.subprojects, all);
+    }
+}
+
+/// Body putting `task` (as read from the server) back with only its end moved.
+fn ended(task: &Value, end_time: String) -> Value {
+    json!({
+        "name": task["name"],
+        "user": task["user"],
+        "initial_time": task["initial_time"],
+        "end_time": end_time,
+        "project": task["project"],
+        "activity": task["activity"],
+        "description": task["description"],
+        "tags": task["tags"],
+    })
+}
+
+pub fn is_object_id(id: &str) -> bool {
+    id.len() == 24 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub struct Api {
+    client: reqwest::Client,
+    url: String,
+    pub user: Option<String>,
+}
+
+impl Api {
+    pub fn new(config: &Config) -> Result<Api, String> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &config.token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|_| "the token can not be sent as a header".to_string())?;
+            headers.insert(AUTHORIZATION, value);
+        }
+        if let Some(workspace) = &config.workspace {
+            let value = HeaderValue::from_str(workspace)
+                .map_err(|_| "the workspace can not be sent as a header".to_string())?;
+            headers.insert("x-workspace", value);
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Api {
+            client,
+            url: config.api_url(),
+            user: config.user(),
+        })
+    }
+
+    async fn send<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, ApiError> {
+        let response = request.send().await?;
+        let status = response.status();
+
+        if status.is_success() {
+            Ok(response.json().await?)
+        } else {
+            // Errors come as `{ "message": "..." }`
+            let message = response
+                .json::<Value>()
+                .await
+                .ok()
+                .and_then(|body| body["message"].as_str().map(str::to_string))
+                .unwrap_or_else(|| status.to_string());
+            Err(ApiError::Failed(message))
+        }
+    }
+
+    /// Every project as (id, name), subprojects included.
+    pub async fn projects(&self) -> Result<Vec<(String, String)>, ApiError> {
+        let groups: Vec<ClientProjects> = self
+            .send(self.client.get(format!("{}/projects", self.url)))
+            .await?;
+
+        let mut projects = vec![];
+        for group in groups {
+            flatten(group.projects, &mut projects);
+        }
+
+        Ok(projects)
+    }
+
+    /// Id of the project named `project` (ignoring case), ids are kept as is.
+    pub async fn project_id(&self, project: &str) -> Result<Option<String>, ApiError> {
+        if is_object_id(project) {
+            return Ok(Some(project.to_string()));
+        }
+
+        Ok(self
+            .projects()
+            .await?
+            .into_iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(project))
+            .map(|(id, _)| id))
+    }
+
+    /// Tasks of the user (of everyone without one) ending on the UTC day
+    /// `from` or later.
+    pub async fn tasks(&self, from: NaiveDate) -> Result<Vec<Task>, ApiError> {
+        let mut query = vec![("from", from.format("%Y-%m-%d").to_string())];
+        if let Some(user) = &self.user {
+            query.push(("user", user.clone()));
+        }
+
+        self.send(self.client.get(format!("{}/tasks", self.url)).query(&query))
+            .await
+    }
+
+    pub async fn create_task(&self, task: &TaskBody<'_>) -> Result<String, ApiError> {
+        let written: TaskWritten = self
+            .send(self.client.post(format!("{}/tasks", self.url)).json(task))
+            .await?;
+
+        Ok(written._id)
+    }
+
+    /// Ends the task at `end_time`, keeping what was edited on it meanwhile
+    /// (tags, activity, description...).
+    pub async fn end_task(&self, id: &str, end_time: String) -> Result<(), ApiError> {
+        let url = format!("{}/tasks/{}", self.url, id);
+        let task: Value = self.send(self.client.get(&url)).await?;
+
+        let _: Value = self
+            .send(self.client.put(&url).json(&ended(&task, end_time)))
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ending_a_task_keeps_the_rest() {
+        let task = json!({
+            "_id": "6170f2d5e6a1b2c3d4e5f601",
+            "name": "Support",
+            "user": "ana",
+            "initial_time": "2021-10-20T09:00:00Z",
+            "end_time": "2021-10-20T09:00:00Z",
+            "project": null,
+            "activity": "6170f2d5e6a1b2c3d4e5f602",
+            "description": "Printer",
+            "tags": ["6170f2d5e6a1b2c3d4e5f603"],
+            "duration": { "hours": 0, "minutes": 0 },
+        });
+
+        let body = ended(&task, "2021-10-20T10:30:00Z".to_string());
+        assert_eq!(body["end_time"], "2021-10-20T10:30:00Z");
+        assert_eq!(body["initial_time"], "2021-10-20T09:00:00Z");
+        assert_eq!(body["activity"], task["activity"]);
+        assert_eq!(body["description"], "Printer");
+        assert_eq!(body["tags"], task["tags"]);
+        assert!(body.get("duration").is_none());
+    }
+}
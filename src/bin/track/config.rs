@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+
+const DEFAULT_SERVER: &str = "http://localhost:5000";
+
+/// `config.toml` of the client, every key is optional and unknown keys are
+/// ignored:
+///
+/// ```toml
+/// server = "http://localhost:5000"
+/// token = "secret"      # sent as `Authorization: Bearer secret`
+/// user = "ana"          # defaults to $USER
+/// workspace = "default"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Config {
+    pub server: Option<String>,
+    // For servers behind an authenticating proxy
+    pub token: Option<String>,
+    pub user: Option<String>,
+    pub workspace: Option<String>,
+}
+
+impl Config {
+    /// Reads `config.toml` of `dir`, a missing file gives the defaults.
+    pub fn load(dir: &std::path::Path) -> Result<Config, String> {
+        let path = dir.join("config.toml");
+
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Base URL of the versioned API, e.g. `http://localhost:5000/api/v1`.
+    pub fn api_url(&self) -> String {
+        let server = self.server.as_deref().unwrap_or(DEFAULT_SERVER);
+        format!("{}/api/v1", server.trim_end_matches('/'))
+    }
+
+    pub fn user(&self) -> Option<String> {
+        self.user.clone().or_else(|| std::env::var("USER").ok())
+    }
+}
+
+/// `$TRACK_DIR`, else `$XDG_CONFIG_HOME/track` or `~/.config/track`. Holds the
+/// config and the state (running timer and queued events).
+pub fn dir() -> Result<PathBuf, String> {
+    if let Ok(dir) = std::env::var("TRACK_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Ok(config_home) = std::env::var("XDG_CONFIG_HOME") {
+        return Ok(PathBuf::from(config_home).join("track"));
+    }
+
+    std::env::var("HOME")
+        .map(|home| PathBuf::from(home).join(".config").join("track"))
+        .map_err(|_| "set TRACK_DIR or HOME to locate the config".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_partial_configs() {
+        let config: Config = toml::from_str("server = \"https://time.example.com/\"").unwrap();
+        assert_eq!(config.api_url(), "https://time.example.com/api/v1");
+        assert_eq!(config.user, None);
+        assert_eq!(config.token, None);
+
+        assert_eq!(Config::default().api_url(), "http://localhost:5000/api/v1");
+        // Configs of newer versions still load
+        let config: Config = toml::from_str("token = \"secret\"\ntheme = \"dark\"").unwrap();
+        assert_eq!(config.token.as_deref(), Some("secret"));
+    }
+}
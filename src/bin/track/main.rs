@@ -0,0 +1,533 @@
+mod api;
+mod config;
+mod state;
+
+use api::{Api, ApiError, Task, TaskBody};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+use clap::{Parser, Subcommand};
+use config::Config;
+use state::{Event, State, Timer};
+use std::collections::HashMap;
+
+/// Tracks time from the terminal against the time tracking API. Reads the
+/// server, user and workspace from `config.toml` in `$TRACK_DIR` (by default
+/// `~/.config/track`). Starts and stops made offline are sent later.
+#[derive(Debug, Parser)]
+#[command(name = "track", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Start a task, stopping the running one
+    Start {
+        name: String,
+        /// Project name or id
+        #[arg(short, long)]
+        project: Option<String>,
+    },
+    /// Stop the running task
+    Stop,
+    /// Show the running task and what waits for the server
+    Status,
+    /// List tasks, of the last 7 days by default
+    Log {
+        #[arg(long)]
+        today: bool,
+    },
+    /// Total time per project, of this week by default
+    Report {
+        #[arg(long, conflicts_with = "month")]
+        week: bool,
+        #[arg(long)]
+        month: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    if let Err(e) = run(cli.command).await {
+        eprintln!("track: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(command: Command) -> Result<(), String> {
+    let dir = config::dir()?;
+    let api = Api::new(&Config::load(&dir)?)?;
+    let mut state = State::load(&dir)?;
+
+    sync(&api, &mut state).await;
+    let result = match command {
+        Command::Start { name, project } => start(&api, &mut state, name, project).await,
+        Command::Stop => match state.running.take() {
+            Some(timer) => stop(&api, &mut state, timer, Utc::now()).await,
+            None => Err("no task is running".to_string()),
+        },
+        Command::Status => {
+            status(&state);
+            Ok(())
+        }
+        Command::Log { today } => {
+            let days = if today { 1 } else { 7 };
+            let from = Local::now().date_naive() - Duration::days(days - 1);
+            log(&api, &state, from).await
+        }
+        Command::Report { month, .. } => {
+            let today = Local::now().date_naive();
+            let from = if month {
+                today.with_day(1).unwrap()
+            } else {
+                today - Duration::days(today.weekday().num_days_from_monday() as i64)
+            };
+            report(&api, &state, from).await
+        }
+    };
+    state.save(&dir)?;
+
+    result
+}
+
+fn body<'a>(
+    api: &'a Api,
+    timer: &'a Timer,
+    project: Option<&'a str>,
+    end: DateTime<Utc>,
+) -> TaskBody<'a> {
+    TaskBody {
+        name: &timer.name,
+        user: api.user.as_deref(),
+        initial_time: TaskBody::time(timer.started_at),
+        end_time: TaskBody::time(end),
+        project,
+    }
+}
+
+// Projects queued by name may no longer exist, the time is kept without one
+async fn project_id(api: &Api, timer: &Timer) -> Result<Option<String>, ApiError> {
+    let project = match &timer.project {
+        Some(project) => project,
+        None => return Ok(None),
+    };
+
+    let id = api.project_id(project).await?;
+    if id.is_none() {
+        eprintln!(
+            "track: no project named {}, {} is kept without one",
+            project, timer.name
+        );
+    }
+
+    Ok(id)
+}
+
+// The task is created as soon as it starts, ending where it starts
+async fn send_start(api: &Api, timer: &Timer) -> Result<String, ApiError> {
+    let project = project_id(api, timer).await?;
+
+    api.create_task(&body(api, timer, project.as_deref(), timer.started_at))
+        .await
+}
+
+// Only the end of a started task is moved, what was set on it through the
+// API while it ran is kept
+async fn send_stop(api: &Api, timer: &Timer, at: DateTime<Utc>) -> Result<(), ApiError> {
+    let id = match &timer.task {
+        Some(id) => Some(id.clone()),
+        None => created_task(api, timer).await?,
+    };
+    match id {
+        Some(id) => api.end_task(&id, TaskBody::time(at)).await,
+        // Its start never made it to the server
+        None => {
+            let project = project_id(api, timer).await?;
+            api.create_task(&body(api, timer, project.as_deref(), at))
+                .await
+                .map(|_| ())
+        }
+    }
+}
+
+/// Task of `timer` among `tasks`: same name and start, to the second sent.
+fn started_task(tasks: Vec<Task>, timer: &Timer) -> Option<String> {
+    tasks
+        .into_iter()
+        .find(|task| {
+            task.name == timer.name && task.initial_time.timestamp() == timer.started_at.timestamp()
+        })
+        .map(|task| task._id)
+}
+
+// A start that timed out may still have reached the server, its task is
+// looked up before it is created again
+async fn created_task(api: &Api, timer: &Timer) -> Result<Option<String>, ApiError> {
+    let tasks = api.tasks(timer.started_at.date_naive()).await?;
+
+    Ok(started_task(tasks, timer))
+}
+
+async fn resend_start(api: &Api, timer: &Timer) -> Result<String, ApiError> {
+    match created_task(api, timer).await? {
+        Some(task) => Ok(task),
+        None => send_start(api, timer).await,
+    }
+}
+
+/// Sends the queued events in order, until the server is unreachable again.
+/// Events the server refuses are dropped, sending them again would not help.
+async fn sync(api: &Api, state: &mut State) {
+    while let Some(event) = state.queue.first().cloned() {
+        let result = match &event {
+            Event::Start { timer } => resend_start(api, timer)
+                .await
+                .map(|task| state.task_created(&timer.id, &task)),
+            Event::Stop { timer, at } => send_stop(api, timer, *at).await,
+        };
+
+        match result {
+            Ok(()) => {}
+            Err(ApiError::Unreachable(_)) => return,
+            Err(ApiError::Failed(e)) => {
+                let (action, timer) = match &event {
+                    Event::Start { timer } => ("start", timer),
+                    Event::Stop { timer, .. } => ("stop", timer),
+                };
+                eprintln!(
+                    "track: dropped the queued {} of {}: {}",
+                    action, timer.name, e
+                );
+            }
+        }
+        state.queue.remove(0);
+    }
+}
+
+async fn start(
+    api: &Api,
+    state: &mut State,
+    name: String,
+    project: Option<String>,
+) -> Result<(), String> {
+    let now = Utc::now();
+    if let Some(timer) = state.running.take() {
+        stop(api, state, timer, now).await?;
+    }
+
+    let mut timer = Timer::new(name, project, now);
+    // Queued events go first, so the server sees them in order
+    let sent = if state.queue.is_empty() {
+        start_online(api, &timer).await
+    } else {
+        Err(ApiError::Unreachable("events are queued".to_string()))
+    };
+    match sent {
+        Ok(task) => timer.task = Some(task),
+        Err(ApiError::Unreachable(_)) => {
+            println!("Server unreachable, the start is sent later");
+            state.queue.push(Event::Start {
+                timer: timer.clone(),
+            });
+        }
+        Err(ApiError::Failed(e)) => return Err(e),
+    }
+
+    println!(
+        "Started {}{} at {}",
+        timer.name,
+        on_project(&timer.project),
+        timer.started_at.with_timezone(&Local).format("%H:%M")
+    );
+    state.running = Some(timer);
+
+    Ok(())
+}
+
+// Unlike queued starts, an unknown project is refused right away
+async fn start_online(api: &Api, timer: &Timer) -> Result<String, ApiError> {
+    if let Some(project) = &timer.project {
+        if api.project_id(project).await?.is_none() {
+            return Err(ApiError::Failed(format!("no project named {}", project)));
+        }
+    }
+
+    send_start(api, timer).await
+}
+
+async fn stop(api: &Api, state: &mut State, timer: Timer, at: DateTime<Utc>) -> Result<(), String> {
+    let sent = if state.queue.is_empty() {
+        send_stop(api, &timer, at).await
+    } else {
+        Err(ApiError::Unreachable("events are queued".to_string()))
+    };
+    match sent {
+        Ok(()) => {}
+        Err(ApiError::Unreachable(_)) => {
+            println!("Server unreachable, the stop is sent later");
+            state.queue.push(Event::Stop {
+                timer: timer.clone(),
+                at,
+            });
+        }
+        Err(ApiError::Failed(e)) => {
+            // Still running, so it can be stopped once the conflict is solved
+            state.running = Some(timer);
+            return Err(e);
+        }
+    }
+
+    println!(
+        "Stopped {} after {}",
+        timer.name,
+        format_duration(at - timer.started_at)
+    );
+
+    Ok(())
+}
+
+fn status(state: &State) {
+    match &state.running {
+        Some(timer) => println!(
+            "Tracking {}{} since {} ({})",
+            timer.name,
+            on_project(&timer.project),
+            timer.started_at.with_timezone(&Local).format("%H:%M"),
+            format_duration(Utc::now() - timer.started_at)
+        ),
+        None => println!("Not tracking"),
+    }
+
+    if !state.queue.is_empty() {
+        println!("{} events wait for the server", state.queue.len());
+    }
+}
+
+fn on_project(project: &Option<String>) -> String {
+    project
+        .as_ref()
+        .map(|project| format!(" on {}", project))
+        .unwrap_or_default()
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+fn midnight(day: NaiveDate) -> DateTime<Utc> {
+    Local
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .unwrap_or_else(|| {
+            day.and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+                .with_timezone(&Local)
+        })
+        .with_timezone(&Utc)
+}
+
+/// Tasks of the user starting on `from` or later, oldest first. The running
+/// task ends now.
+fn tasks_since(tasks: Vec<Task>, state: &State, user: Option<&str>, from: NaiveDate) -> Vec<Task> {
+    let from = midnight(from);
+    let running = state
+        .running
+        .as_ref()
+        .and_then(|timer| timer.task.as_deref());
+
+    let mut tasks: Vec<Task> = tasks
+        .into_iter()
+        .filter(|task| user.is_none() || task.user.as_deref() == user)
+        .filter(|task| task.initial_time >= from)
+        .map(|mut task| {
+            if Some(task._id.as_str()) == running {
+                task.end_time = Utc::now();
+            }
+            task
+        })
+        .collect();
+    tasks.sort_by_key(|task| task.initial_time);
+
+    tasks
+}
+
+async fn log(api: &Api, state: &State, from: NaiveDate) -> Result<(), String> {
+    let projects: HashMap<String, String> = api
+        .projects()
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+    let tasks = api
+        .tasks(midnight(from).date_naive())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut day = None;
+    for task in tasks_since(tasks, state, api.user.as_deref(), from) {
+        let start = task.initial_time.with_timezone(&Local);
+        if day != Some(start.date_naive()) {
+            day = Some(start.date_naive());
+            println!("{}", start.format("%a %e %b"));
+        }
+
+        let project = task.project.as_ref().and_then(|id| projects.get(id));
+        println!(
+            "  {}-{}  {}  {}{}",
+            start.format("%H:%M"),
+            task.end_time.with_timezone(&Local).format("%H:%M"),
+            format_duration(task.end_time - task.initial_time),
+            task.name,
+            project
+                .map(|name| format!(" ({})", name))
+                .unwrap_or_default()
+        );
+    }
+    if day.is_none() {
+        println!("Nothing tracked");
+    }
+
+    Ok(())
+}
+
+async fn report(api: &Api, state: &State, from: NaiveDate) -> Result<(), String> {
+    let projects: HashMap<String, String> = api
+        .projects()
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+    let tasks = api
+        .tasks(midnight(from).date_naive())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut totals: HashMap<String, Duration> = HashMap::new();
+    for task in tasks_since(tasks, state, api.user.as_deref(), from) {
+        let project = task
+            .project
+            .as_ref()
+            .and_then(|id| projects.get(id))
+            .cloned()
+            .unwrap_or_else(|| "(no project)".to_string());
+        *totals.entry(project).or_insert_with(Duration::zero) += task.end_time - task.initial_time;
+    }
+
+    let mut totals: Vec<(String, Duration)> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    println!("Since {}", from.format("%a %e %b"));
+    for (project, duration) in &totals {
+        println!("  {:<30} {}", project, format_duration(*duration));
+    }
+    let total = totals.iter().fold(Duration::zero(), |sum, (_, d)| sum + *d);
+    println!("  {:<30} {}", "Total", format_duration(total));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, user: &str, start: &str, end: &str) -> Task {
+        Task {
+            _id: id.to_string(),
+            name: id.to_string(),
+            user: Some(user.to_string()),
+            initial_time: start.parse().unwrap(),
+            end_time: end.parse().unwrap(),
+            project: None,
+        }
+    }
+
+    #[test]
+    fn durations_read_as_clock_time() {
+        assert_eq!(format_duration(Duration::seconds(5400)), "01:30:00");
+        assert_eq!(format_duration(Duration::seconds(-5)), "00:00:00");
+    }
+
+    #[test]
+    fn lists_recent_tasks_of_the_user_in_order() {
+        let tasks = vec![
+            task(
+                "late",
+                "ana",
+                "2021-10-19T14:00:00Z",
+                "2021-10-19T15:00:00Z",
+            ),
+            task(
+                "early",
+                "ana",
+                "2021-10-19T09:00:00Z",
+                "2021-10-19T10:00:00Z",
+            ),
+            task(
+                "other",
+                "bo",
+                "2021-10-19T09:00:00Z",
+                "2021-10-19T10:00:00Z",
+            ),
+            task("old", "ana", "2021-10-10T09:00:00Z", "2021-10-10T10:00:00Z"),
+        ];
+        let from = NaiveDate::from_ymd_opt(2021, 10, 18).unwrap();
+
+        let names: Vec<String> = tasks_since(tasks, &State::default(), Some("ana"), from)
+            .into_iter()
+            .map(|task| task.name)
+            .collect();
+        assert_eq!(names, ["early", "late"]);
+    }
+
+    #[test]
+    fn finds_the_task_of_a_timed_out_start() {
+        let started_at = "2021-10-19T09:00:00.250Z".parse().unwrap();
+        let timer = Timer::new("early".to_string(), None, started_at);
+        let tasks = vec![
+            task(
+                "other",
+                "ana",
+                "2021-10-19T09:00:00Z",
+                "2021-10-19T09:00:00Z",
+            ),
+            task(
+                "early",
+                "ana",
+                "2021-10-19T09:00:00Z",
+                "2021-10-19T09:00:00Z",
+            ),
+        ];
+
+        assert_eq!(
+            started_task(tasks.clone(), &timer).as_deref(),
+            Some("early")
+        );
+
+        let later = Timer::new(
+            "early".to_string(),
+            None,
+            "2021-10-19T09:00:01Z".parse().unwrap(),
+        );
+        assert_eq!(started_task(tasks, &later), None);
+    }
+
+    #[test]
+    fn parses_the_commands() {
+        let cli =
+            Cli::try_parse_from(["track", "start", "Design", "--project", "Website"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Start { ref name, project: Some(ref project) } if name == "Design" && project == "Website"
+        ));
+        assert!(Cli::try_parse_from(["track", "report", "--week", "--month"]).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A started task. `task` is its id on the server, unknown until the start
+/// reached it.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Timer {
+    // Local id, ties the queued start and stop of a timer together
+    pub id: String,
+    pub task: Option<String>,
+    pub name: String,
+    // Project id, or a name still to be looked up
+    pub project: Option<String>,
+    pub started_at: DateTime<Utc>,
+}
+
+impl Timer {
+    pub fn new(name: String, project: Option<String>, started_at: DateTime<Utc>) -> Timer {
+        Timer {
+            id: started_at
+                .timestamp_nanos_opt()
+                .unwrap_or_default()
+                .to_string(),
+            task: None,
+            name,
+            project,
+            started_at,
+        }
+    }
+}
+
+/// Starts and stops made while the server was unreachable, sent in order once
+/// it answers again.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Start { timer: Timer },
+    Stop { timer: Timer, at: DateTime<Utc> },
+}
+
+/// `state.json` next to the config.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct State {
+    pub running: Option<Timer>,
+    #[serde(default)]
+    pub queue: Vec<Event>,
+}
+
+impl State {
+    pub fn load(dir: &Path) -> Result<State, String> {
+        let path = dir.join("state.json");
+
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(State::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        let path = dir.join("state.json");
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+
+        std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&path, json))
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Records the server id of timer `id`, on the running timer and on its
+    /// queued stop.
+    pub fn task_created(&mut self, id: &str, task: &str) {
+        let timers =
+            self.running
+                .iter_mut()
+                .chain(self.queue.iter_mut().map(|event| match event {
+                    Event::Start { timer } | Event::Stop { timer, .. } => timer,
+                }));
+
+        for timer in timers.filter(|timer| timer.id == id) {
+            timer.task = Some(task.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn created_tasks_reach_queued_stops() {
+        let started_at = "2021-10-18T09:00:00Z".parse().unwrap();
+        let timer = Timer::new("Design".to_string(), None, started_at);
+        let mut state = State {
+            running: None,
+            queue: vec![
+                Event::Start {
+                    timer: timer.clone(),
+                },
+                Event::Stop {
+                    timer: timer.clone(),
+                    at: "2021-10-18T10:00:00Z".parse().unwrap(),
+                },
+            ],
+        };
+
+        state.queue.remove(0);
+        state.task_created(&timer.id, "6171b5fe4e6a4c6a1c0d1f2a");
+
+        match &state.queue[0] {
+            Event::Stop { timer, .. } => {
+                assert_eq!(timer.task.as_deref(), Some("6171b5fe4e6a4c6a1c0d1f2a"))
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn state_survives_a_round_trip() {
+        let dir = std::env::temp_dir().join(format!("track-test-{}", std::process::id()));
+        let started_at = "2021-10-18T09:00:00Z".parse().unwrap();
+        let timer = Timer::new(
+            "Design".to_string(),
+            Some("Website".to_string()),
+            started_at,
+        );
+        let state = State {
+            running: Some(timer.clone()),
+            queue: vec![Event::Start { timer }],
+        };
+
+        state.save(&dir).unwrap();
+        assert_eq!(State::load(&dir).unwrap(), state);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(State::load(&dir).unwrap(), State::default());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::db::{DB, DEFAULT_WORKSPACE};
+use crate::models::report::ReportQuery;
+use crate::seed::SeedOptions;
+use crate::Result;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Time tracking API on MongoDB. Connects to `MONGODB_URI`, the local server
+/// by default.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve the HTTP API (the default)
+    Serve(ServeArgs),
+    /// Replace clients, projects and tasks with fake ones
+    Seed(SeedArgs),
+    /// Drop the whole database
+    Wipe {
+        /// Confirm that everything is deleted
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Load the collections of an export file
+    Import {
+        file: PathBuf,
+        /// Remove the existing documents of each imported collection first
+        #[arg(long)]
+        replace: bool,
+    },
+    /// Write every collection as extended JSON
+    Export {
+        /// File to write instead of the standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Apply the pending data migrations
+    Migrate,
+    /// Print the tracked time per project (or activity)
+    Report(ReportArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    #[arg(long, default_value_t = 5000)]
+    pub port: u16,
+    /// Also serve the `/seed` and `dangerously-delete-all-*` routes, which
+    /// replace or delete every document
+    #[arg(long)]
+    pub seed_routes: bool,
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command::Serve(ServeArgs {
+            port: 5000,
+            seed_routes: false,
+        })
+    }
+}
+
+/// Unset values keep the `SeedOptions` defaults.
+#[derive(Debug, Args)]
+pub struct SeedArgs {
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[arg(long)]
+    pub clients: Option<usize>,
+    #[arg(long)]
+    pub projects: Option<usize>,
+    #[arg(long)]
+    pub tasks: Option<usize>,
+    /// Number of days the tasks are spread over
+    #[arg(long)]
+    pub days: Option<u32>,
+    /// Last day of the tasks (YYYY-MM-DD), today by default
+    #[arg(long)]
+    pub until: Option<String>,
+}
+
+impl SeedArgs {
+    fn options(self) -> SeedOptions {
+        let defaults = SeedOptions::default();
+
+        SeedOptions {
+            seed: self.seed.unwrap_or(defaults.seed),
+            clients: self.clients.unwrap_or(defaults.clients),
+            projects: self.projects.unwrap_or(defaults.projects),
+            tasks: self.tasks.unwrap_or(defaults.tasks),
+            days: self.days.unwrap_or(defaults.days),
+            until: self.until,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ReportArgs {
+    /// First day (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Last day (YYYY-MM-DD)
+    #[arg(long)]
+    pub to: Option<String>,
+    /// Only tasks with this tag id
+    #[arg(long)]
+    pub tag: Option<String>,
+    /// IANA time zone of the days, UTC by default
+    #[arg(long)]
+    pub tz: Option<String>,
+    /// Workspace whose rounding rule applies
+    #[arg(long, default_value = DEFAULT_WORKSPACE)]
+    pub workspace: String,
+    /// Group by activity instead of project
+    #[arg(long)]
+    pub activities: bool,
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Runs every command but `serve`.
+pub async fn run(db: &DB, command: Command) -> Result<()> {
+    match command {
+        Command::Serve(_) => unreachable!("served by main"),
+        Command::Seed(args) => {
+            let summary = db.seed_all(&args.options()).await?;
+            println!(
+                "Seeded {} clients, {} projects and {} tasks",
+                summary.clients, summary.projects, summary.tasks
+            );
+        }
+        Command::Wipe { yes } => {
+            if !yes {
+                eprintln!(
+                    "This drops the whole {} database, pass --yes to go on",
+                    db.name
+                );
+                std::process::exit(2);
+            }
+            db.wipe().await?;
+            println!("Dropped {}", db.name);
+        }
+        Command::Import { file, replace } => {
+            let collections = serde_json::from_str(&std::fs::read_to_string(file)?)?;
+            let inserted = db.import_collections(collections, replace).await?;
+            println!("Imported {} documents", inserted);
+        }
+        Command::Export { output } => {
+            let collections = db.export_collections().await?;
+            let json = serde_json::to_string_pretty(&collections)?;
+            match output {
+                Some(output) => std::fs::write(output, json)?,
+                None => println!("{}", json),
+            }
+        }
+        Command::Migrate => {
+            let applied = db.migrate().await?;
+            if applied.is_empty() {
+                println!("Nothing to migrate");
+            }
+            for (name, changed) in applied {
+                println!("Applied {} ({} documents changed)", name, changed);
+            }
+        }
+        Command::Report(args) => report(db, args).await?,
+    }
+
+    Ok(())
+}
+
+async fn report(db: &DB, args: ReportArgs) -> Result<()> {
+    let query = ReportQuery {
+        from: args.from,
+        to: args.to,
+        tag: args.tag,
+        tz: args.tz,
+    };
+
+    // Name, client (or project), tracked and rounded time, tasks
+    let (rows, total, rounded_total) = if args.activities {
+        let report = db.get_activity_report(&args.workspace, &query).await?;
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        let rows: Vec<_> = report
+            .activities
+            .into_iter()
+            .map(|a| {
+                (
+                    a.activity_name,
+                    a.project_name,
+                    a.total,
+                    a.rounded,
+                    a.task_count,
+                )
+            })
+            .collect();
+        (rows, report.total, report.rounded_total)
+    } else {
+        let report = db.get_summary_report(&args.workspace, &query).await?;
+        if args.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        let rows: Vec<_> = report
+            .projects
+            .into_iter()
+            .map(|p| {
+                (
+                    p.project_name,
+                    p.client_name,
+                    p.total,
+                    p.rounded,
+                    p.task_count,
+                )
+            })
+            .collect();
+        (rows, report.total, report.rounded_total)
+    };
+
+    let (name, group) = if args.activities {
+        ("ACTIVITY", "PROJECT")
+    } else {
+        ("PROJECT", "CLIENT")
+    };
+    println!(
+        "{:<30} {:<30} {:>10} {:>10} {:>6}",
+        name, group, "TRACKED", "ROUNDED", "TASKS"
+    );
+    for (name, group, tracked, rounded, tasks) in rows {
+        println!(
+            "{:<30} {:<30} {:>10} {:>10} {:>6}",
+            name.unwrap_or_else(|| "(none)".to_string()),
+            group.unwrap_or_default(),
+            tracked.formatted,
+            rounded.formatted,
+            tasks
+        );
+    }
+    println!(
+        "{:<30} {:<30} {:>10} {:>10}",
+        "TOTAL", "", total.formatted, rounded_total.formatted
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_without_a_command() {
+        let cli = Cli::try_parse_from(["rust-mongodb-warp"]).unwrap();
+        assert!(matches!(
+            cli.command.unwrap_or_default(),
+            Command::Serve(ServeArgs {
+                port: 5000,
+                seed_routes: false
+            })
+        ));
+    }
+
+    #[test]
+    fn seed_flags_override_the_defaults() {
+        let cli = Cli::try_parse_from(["rust-mongodb-warp", "seed", "--tasks", "500"]).unwrap();
+        let options = match cli.command {
+            Some(Command::Seed(args)) => args.options(),
+            command => panic!("unexpected command {:?}", command),
+        };
+
+        assert_eq!(options.tasks, 500);
+        assert_eq!(options.seed, SeedOptions::default().seed);
+    }
+
+    #[test]
+    fn wipe_needs_confirmation_flag() {
+        let cli = Cli::try_parse_from(["rust-mongodb-warp", "wipe"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Wipe { yes: false })));
+        assert!(Cli::try_parse_from(["rust-mongodb-warp", "import"]).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use crate::db::DB;
+use crate::error::ErrorResponse;
+use crate::models::activity::{ActivityRequest, ActivityResponse, ActivitySuggestionsQuery};
+use crate::WebResult;
+use warp::{http::StatusCode, reject, reply::json, Reply};
+
+#[utoipa::path(
+    get,
+    path = "/projects/{id}/activities",
+    tag = "activities",
+    params(("id" = String, Path, description = "Project id")),
+    responses((status = 200, description = "Activities of the project", body = [ActivityResponse])),
+)]
+pub async fn fetch_project_activities_handler(project: String, db: DB) -> WebResult<impl Reply> {
+    let activities = db
+        .get_project_activities(&project)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&activities))
+}
+
+#[utoipa::path(
+    post,
+    path = "/projects/{id}/activities",
+    tag = "activities",
+    params(("id" = String, Path, description = "Project id")),
+    request_body = ActivityRequest,
+    responses((status = 201, description = "Id of the new activity", body = String), (status = 409, description = "Name already in use for the project", body = ErrorResponse)),
+)]
+pub async fn create_activity_handler(
+    project: String,
+    body: ActivityRequest,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let activity = db
+        .create_activity(&project, &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(warp::reply::with_status(
+        json(&activity),
+        StatusCode::CREATED,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/activities/suggestions",
+    tag = "activities",
+    params(ActivitySuggestionsQuery),
+    responses((status = 200, description = "Matching activities", body = [ActivityResponse])),
+)]
+pub async fn fetch_activity_suggestions_handler(
+    query: ActivitySuggestionsQuery,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let activities = db
+        .get_activity_suggestions(&query)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&activities))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/activities/{id}",
+    tag = "activities",
+    params(("id" = String, Path, description = "Activity id")),
+    responses((status = 200, description = "Activity was removed"), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn delete_activity_handler(id: String, db: DB) -> WebResult<impl Reply> {
+    db.delete_activity(&id).await.map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
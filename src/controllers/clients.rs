@@ -1,28 +1,144 @@
 use crate::db::DB;
-use crate::models::client::ClientRequest;
+use crate::error::ErrorResponse;
+use crate::models::client::{ClientPatch, ClientRequest, ClientResponse, ClientsQuery};
 use crate::WebResult;
 use warp::{http::StatusCode, reject, reply::json, Reply};
 
-pub async fn fetch_all_clients_handler(db: DB) -> WebResult<impl Reply> {
-    let tasks = db.get_all_clients().await.map_err(|e| reject::custom(e))?;
+#[utoipa::path(
+    get,
+    path = "/clients",
+    tag = "clients",
+    params(ClientsQuery),
+    responses((status = 200, description = "Clients", body = [ClientResponse])),
+)]
+pub async fn fetch_all_clients_handler(query: ClientsQuery, db: DB) -> WebResult<impl Reply> {
+    let tasks = db.get_all_clients(&query).await.map_err(reject::custom)?;
     Ok(json(&tasks))
 }
 
+#[utoipa::path(
+    get,
+    path = "/clients/{id}",
+    tag = "clients",
+    params(("id" = String, Path, description = "Client id")),
+    responses((status = 200, description = "Client", body = ClientResponse), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
 pub async fn fetch_client_handler(id: String, db: DB) -> WebResult<impl Reply> {
-    let tasks = db.find_client(&id).await.map_err(|e| reject::custom(e))?;
+    let tasks = db.find_client(&id).await.map_err(reject::custom)?;
     Ok(json(&tasks))
 }
 
-pub async fn create_client_handler(body: ClientRequest, db: DB) -> WebResult<impl Reply> {
-    db.create_client(&body)
+#[utoipa::path(
+    post,
+    path = "/clients",
+    tag = "clients",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = ClientRequest,
+    responses((status = 201, description = "Client was created"), (status = 400, description = "Invalid currency, contact email or hourly rate", body = ErrorResponse)),
+)]
+pub async fn create_client_handler(
+    body: ClientRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    body.validate().map_err(reject::custom)?;
+    db.create_client(&workspace, &body)
         .await
-        .map_err(|e| reject::custom(e))?;
+        .map_err(reject::custom)?;
     // TODO: Return the created object
     Ok(StatusCode::CREATED)
 }
 
-pub async fn delete_client_handler(id: String, db: DB) -> WebResult<impl Reply> {
-    let deleted_id = db.delete_client(&id).await.map_err(|e| reject::custom(e))?;
+#[utoipa::path(
+    put,
+    path = "/clients/{id}",
+    tag = "clients",
+    params(("id" = String, Path, description = "Client id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body(content = ClientRequest, description = "The whole client, fields left out are cleared"),
+    responses((status = 200, description = "Client was replaced"), (status = 400, description = "Invalid currency, contact email or hourly rate", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn edit_client_handler(
+    id: String,
+    body: ClientRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    body.validate().map_err(reject::custom)?;
+    db.edit_client(&workspace, &id, &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    patch,
+    path = "/clients/{id}",
+    tag = "clients",
+    params(("id" = String, Path, description = "Client id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body(content = ClientPatch, description = "Fields to change, the others are kept"),
+    responses((status = 200, description = "Client was updated"), (status = 400, description = "Invalid currency, contact email or hourly rate", body = ErrorResponse), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn patch_client_handler(
+    id: String,
+    body: ClientPatch,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    body.validate().map_err(reject::custom)?;
+    db.patch_client(&workspace, &id, &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/clients/{id}/archive",
+    tag = "clients",
+    params(("id" = String, Path, description = "Client id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Client was archived"), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn archive_client_handler(
+    id: String,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.set_client_archived(&workspace, &id, true)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/clients/{id}/unarchive",
+    tag = "clients",
+    params(("id" = String, Path, description = "Client id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Client was restored"), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn unarchive_client_handler(
+    id: String,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.set_client_archived(&workspace, &id, false)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/clients/{id}",
+    tag = "clients",
+    params(("id" = String, Path, description = "Client id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Id of the removed client", body = String), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn delete_client_handler(id: String, workspace: String, db: DB) -> WebResult<impl Reply> {
+    let deleted_id = db
+        .delete_client(&workspace, &id)
+        .await
+        .map_err(reject::custom)?;
 
     Ok(json(&deleted_id))
 }
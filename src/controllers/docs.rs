@@ -0,0 +1,31 @@
+use crate::openapi::ApiDoc;
+use crate::WebResult;
+use utoipa::OpenApi;
+use warp::{reply, Reply};
+
+// Swagger UI is loaded from unpkg.com, the page only points it at the spec.
+// Browsers without internet access get a link to the spec instead.
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8" />
+    <title>rust-mongodb-warp API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"
+        onerror="document.getElementById('swagger-ui').innerHTML = 'Swagger UI could not be loaded from unpkg.com, the spec is at <a href=&quot;/openapi.json&quot;>/openapi.json</a>.'"></script>
+    <script>
+        window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+    </script>
+</body>
+</html>"##;
+
+pub async fn docs_openapi_handler() -> WebResult<impl Reply> {
+    Ok(reply::json(&ApiDoc::openapi()))
+}
+
+pub async fn docs_ui_handler() -> WebResult<impl Reply> {
+    Ok(reply::html(SWAGGER_UI))
+}
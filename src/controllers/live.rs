@@ -0,0 +1,71 @@
+use crate::db::DB;
+use crate::events::{ChangeEvent, EventsQuery};
+use crate::WebResult;
+use futures::{SinkExt, Stream, StreamExt};
+use std::convert::Infallible;
+use warp::ws::{Message, WebSocket, Ws};
+use warp::{sse, Reply};
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "events",
+    params(EventsQuery, ("x-workspace" = Option<String>, Header, description = "Workspace, wins over the `workspace` parameter")),
+    responses((status = 200, description = "Server-sent change events", content_type = "text/event-stream")),
+)]
+pub async fn events_handler(
+    query: EventsQuery,
+    header: Option<String>,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let workspace = query.workspace(header);
+    let events = db.events.subscribe(workspace, query.user).map(|change| {
+        Ok::<_, Infallible>(
+            sse::Event::default()
+                .event(change.event.as_str())
+                .json_data(&change)
+                .unwrap(),
+        )
+    });
+    Ok(sse::reply(sse::keep_alive().stream(events)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/events/ws",
+    tag = "events",
+    params(EventsQuery, ("x-workspace" = Option<String>, Header, description = "Workspace, wins over the `workspace` parameter")),
+    responses((status = 101, description = "WebSocket of change events as JSON text messages")),
+)]
+pub async fn events_ws_handler(
+    ws: Ws,
+    query: EventsQuery,
+    header: Option<String>,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let workspace = query.workspace(header);
+    let events = db.events.subscribe(workspace, query.user);
+    Ok(ws.on_upgrade(move |socket| forward_events(socket, events)))
+}
+
+/// Sends each event as a JSON text message until either side goes away.
+async fn forward_events(socket: WebSocket, events: impl Stream<Item = ChangeEvent>) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut events = Box::pin(events);
+
+    loop {
+        tokio::select! {
+            change = events.next() => {
+                let Some(change) = change else { break };
+                let message = Message::text(serde_json::to_string(&change).unwrap());
+                if sender.send(message).await.is_err() {
+                    break;
+                }
+            }
+            message = receiver.next() => match message {
+                Some(Ok(message)) if !message.is_close() => continue,
+                _ => break,
+            },
+        }
+    }
+}
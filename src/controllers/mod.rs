@@ -1,4 +1,12 @@
+pub mod activities;
 pub mod clients;
+pub mod docs;
+pub mod live;
 pub mod projects;
+pub mod reports;
 pub mod seed;
+pub mod tags;
 pub mod tasks;
+pub mod timesheets;
+pub mod webhooks;
+pub mod workspaces;
@@ -1,36 +1,134 @@
+use crate::db::DB;
+use crate::error::ErrorResponse;
+use crate::models::project::{
+    ProjectBudget, ProjectParentRequest, ProjectRequest, ProjectResponse, ProjectStatusRequest,
+    ProjectsGroupedByClient, ProjectsQuery,
+};
 use crate::WebResult;
-use crate::{db::DB, models::project::ProjectRequest};
 use warp::{http::StatusCode, reject, reply::json, Reply};
 
-pub async fn fetch_all_projects_handler(db: DB) -> WebResult<impl Reply> {
+#[utoipa::path(
+    get,
+    path = "/projects",
+    tag = "projects",
+    params(ProjectsQuery),
+    responses((status = 200, description = "Projects by client", body = [ProjectsGroupedByClient])),
+)]
+pub async fn fetch_all_projects_handler(query: ProjectsQuery, db: DB) -> WebResult<impl Reply> {
     let project = db
-        .get_projects_grouped_by_client()
+        .get_projects_grouped_by_client(&query)
         .await
-        .map_err(|e| reject::custom(e))?;
+        .map_err(reject::custom)?;
     Ok(json(&project))
 }
 
+#[utoipa::path(
+    get,
+    path = "/projects/{id}",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project id")),
+    responses((status = 200, description = "Project", body = ProjectResponse), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
 pub async fn fetch_project_handler(id: String, db: DB) -> WebResult<impl Reply> {
-    let project = db.find_project(&id).await.map_err(|e| reject::custom(e))?;
+    let project = db.find_project(&id).await.map_err(reject::custom)?;
     Ok(json(&project))
 }
-pub async fn create_project_handler(body: ProjectRequest, db: DB) -> WebResult<impl Reply> {
-    db.create_project(&body)
+#[utoipa::path(
+    get,
+    path = "/projects/{id}/budget",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project id")),
+    responses((status = 200, description = "Budget consumption", body = ProjectBudget), (status = 404, description = "Not found", body = ErrorResponse), (status = 422, description = "Project has no estimate", body = ErrorResponse)),
+)]
+pub async fn fetch_project_budget_handler(id: String, db: DB) -> WebResult<impl Reply> {
+    let budget = db.get_project_budget(&id).await.map_err(reject::custom)?;
+    Ok(json(&budget))
+}
+
+#[utoipa::path(
+    post,
+    path = "/projects",
+    tag = "projects",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = ProjectRequest,
+    responses((status = 201, description = "Project was created"), (status = 422, description = "Invalid parent project or estimate", body = ErrorResponse)),
+)]
+pub async fn create_project_handler(
+    body: ProjectRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.create_project(&workspace, &body)
         .await
-        .map_err(|e| reject::custom(e))?;
+        .map_err(reject::custom)?;
     Ok(StatusCode::CREATED)
 }
 
-pub async fn delete_project_handler(id: String, db: DB) -> WebResult<impl Reply> {
-    db.delete_project(&id)
+#[utoipa::path(
+    put,
+    path = "/projects/{id}/status",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = ProjectStatusRequest,
+    responses((status = 200, description = "Status was changed"), (status = 404, description = "Not found", body = ErrorResponse), (status = 409, description = "Invalid status transition", body = ErrorResponse)),
+)]
+pub async fn update_project_status_handler(
+    id: String,
+    body: ProjectStatusRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.update_project_status(&workspace, &id, body.status)
         .await
-        .map_err(|e| reject::custom(e))?;
+        .map_err(reject::custom)?;
     Ok(StatusCode::OK)
 }
 
-pub async fn delete_all_projects_handler(db: DB) -> WebResult<impl Reply> {
-    db.delete_all_projects()
+#[utoipa::path(
+    put,
+    path = "/projects/{id}/parent",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = ProjectParentRequest,
+    responses((status = 200, description = "Parent was changed"), (status = 404, description = "Not found", body = ErrorResponse), (status = 422, description = "Invalid parent project", body = ErrorResponse)),
+)]
+pub async fn update_project_parent_handler(
+    id: String,
+    body: ProjectParentRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.update_project_parent(&workspace, &id, body.parent)
         .await
-        .map_err(|e| reject::custom(e))?;
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/projects/{id}",
+    tag = "projects",
+    params(("id" = String, Path, description = "Project id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Project was removed"), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn delete_project_handler(
+    id: String,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.delete_project(&workspace, &id)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/projects/dangerously-delete-all-projects",
+    tag = "projects",
+    responses((status = 200, description = "Every project was removed")),
+)]
+pub async fn delete_all_projects_handler(db: DB) -> WebResult<impl Reply> {
+    db.delete_all_projects().await.map_err(reject::custom)?;
     Ok(StatusCode::OK)
 }
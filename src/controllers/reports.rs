@@ -0,0 +1,43 @@
+use crate::db::DB;
+use crate::error::ErrorResponse;
+use crate::models::report::{ActivityReport, ReportQuery, SummaryReport};
+use crate::WebResult;
+use warp::{reject, reply::json, Reply};
+
+#[utoipa::path(
+    get,
+    path = "/reports/summary",
+    tag = "reports",
+    params(ReportQuery, ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Time per project", body = SummaryReport), (status = 400, description = "Invalid id, date or time zone", body = ErrorResponse)),
+)]
+pub async fn fetch_summary_report_handler(
+    query: ReportQuery,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let report = db
+        .get_summary_report(&workspace, &query)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&report))
+}
+
+#[utoipa::path(
+    get,
+    path = "/reports/activities",
+    tag = "reports",
+    params(ReportQuery, ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Time per activity", body = ActivityReport), (status = 400, description = "Invalid id, date or time zone", body = ErrorResponse)),
+)]
+pub async fn fetch_activity_report_handler(
+    query: ReportQuery,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let report = db
+        .get_activity_report(&workspace, &query)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&report))
+}
@@ -0,0 +1,91 @@
+use crate::db::DB;
+use crate::error::ErrorResponse;
+use crate::models::tag::{TagMergeRequest, TagRequest, TagResponse};
+use crate::WebResult;
+use warp::{http::StatusCode, reject, reply::json, Reply};
+
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "tags",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Tags of the workspace", body = [TagResponse])),
+)]
+pub async fn fetch_all_tags_handler(workspace: String, db: DB) -> WebResult<impl Reply> {
+    let tags = db.get_all_tags(&workspace).await.map_err(reject::custom)?;
+    Ok(json(&tags))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tags",
+    tag = "tags",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = TagRequest,
+    responses((status = 201, description = "Id of the new tag", body = String), (status = 409, description = "Name already in use", body = ErrorResponse)),
+)]
+pub async fn create_tag_handler(
+    body: TagRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let tag = db
+        .create_tag(&workspace, &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(warp::reply::with_status(json(&tag), StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    put,
+    path = "/tags/{id}",
+    tag = "tags",
+    params(("id" = String, Path, description = "Tag id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = TagRequest,
+    responses((status = 200, description = "Tag was renamed"), (status = 404, description = "Not found", body = ErrorResponse), (status = 409, description = "Name already in use", body = ErrorResponse)),
+)]
+pub async fn rename_tag_handler(
+    id: String,
+    body: TagRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.rename_tag(&workspace, &id, &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    post,
+    path = "/tags/{id}/merge",
+    tag = "tags",
+    params(("id" = String, Path, description = "Tag id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = TagMergeRequest,
+    responses((status = 200, description = "Tag was merged"), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn merge_tag_handler(
+    id: String,
+    body: TagMergeRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.merge_tags(&workspace, &id, &body.into)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tags/{id}",
+    tag = "tags",
+    params(("id" = String, Path, description = "Tag id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Tag was removed"), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn delete_tag_handler(id: String, workspace: String, db: DB) -> WebResult<impl Reply> {
+    db.delete_tag(&workspace, &id)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
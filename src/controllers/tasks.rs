@@ -1,47 +1,179 @@
+use crate::db::DB;
+use crate::error::ErrorResponse;
+use crate::models::task::{
+    OverlapsQuery, TaskOverlap, TaskRequest, TaskResponse, TaskSplitRequest, TaskSplitResponse,
+    TaskWriteResponse, TasksGroupedByDate, TasksQuery,
+};
 use crate::WebResult;
-use crate::{db::DB, models::task::TaskRequest};
 use warp::{http::StatusCode, reject, reply::json, Reply};
 
-pub async fn fetch_all_tasks_handler(db: DB) -> WebResult<impl Reply> {
-    let tasks = db.get_all_tasks().await.map_err(|e| reject::custom(e))?;
+#[utoipa::path(
+    get,
+    path = "/tasks",
+    tag = "tasks",
+    params(TasksQuery, ("x-workspace" = Option<String>, Header, description = "Workspace of the `tag` filter, `default` when missing")),
+    responses((status = 200, description = "Tasks", body = [TaskResponse])),
+)]
+pub async fn fetch_all_tasks_handler(
+    query: TasksQuery,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let tasks = db
+        .get_all_tasks(&workspace, &query)
+        .await
+        .map_err(reject::custom)?;
     Ok(json(&tasks))
 }
 
-pub async fn fetch_tasks_grouped_by_date(db: DB) -> WebResult<impl Reply> {
+#[utoipa::path(
+    get,
+    path = "/tasks/group",
+    tag = "tasks",
+    params(TasksQuery, ("x-workspace" = Option<String>, Header, description = "Workspace of the `tag` filter, `default` when missing")),
+    responses((status = 200, description = "Tasks by local day, most recent first", body = [TasksGroupedByDate]), (status = 400, description = "Invalid id, date or time zone", body = ErrorResponse)),
+)]
+pub async fn fetch_tasks_grouped_by_date(
+    query: TasksQuery,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
     let tasks = db
-        .get_tasks_grouped_by_date()
+        .get_tasks_grouped_by_date(&workspace, &query)
         .await
-        .map_err(|e| reject::custom(e))?;
+        .map_err(reject::custom)?;
     Ok(json(&tasks))
 }
 
-pub async fn fetch_task_handler(id: String, db: DB) -> WebResult<impl Reply> {
-    let tasks = db.find_task(&id).await.map_err(|e| reject::custom(e))?;
+#[utoipa::path(
+    get,
+    path = "/tasks/{id}",
+    tag = "tasks",
+    params(("id" = String, Path, description = "Task id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Task", body = TaskResponse), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn fetch_task_handler(id: String, workspace: String, db: DB) -> WebResult<impl Reply> {
+    let tasks = db
+        .find_task(&workspace, &id)
+        .await
+        .map_err(reject::custom)?;
     Ok(json(&tasks))
 }
 
-pub async fn create_task_handler(body: TaskRequest, db: DB) -> WebResult<impl Reply> {
-    let task = db.create_task(&body).await.map_err(|e| reject::custom(e))?;
+#[utoipa::path(
+    get,
+    path = "/tasks/overlaps",
+    tag = "tasks",
+    params(OverlapsQuery, ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Overlapping pairs of tasks", body = [TaskOverlap])),
+)]
+pub async fn fetch_task_overlaps_handler(
+    query: OverlapsQuery,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let overlaps = db
+        .get_task_overlaps(&workspace, &query)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&overlaps))
+}
+
+#[utoipa::path(
+    post,
+    path = "/tasks",
+    tag = "tasks",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing"), ("x-lock-override" = Option<String>, Header, description = "Workspace admin writing inside a locked period")),
+    request_body = TaskRequest,
+    responses((status = 200, description = "Created task", body = TaskWriteResponse), (status = 403, description = "Lock override by someone who is not a workspace admin", body = ErrorResponse), (status = 409, description = "Overlapping, locked by a timesheet or in a locked period", body = ErrorResponse), (status = 422, description = "Unknown tag, activity, or archived project or client", body = ErrorResponse)),
+)]
+pub async fn create_task_handler(
+    body: TaskRequest,
+    workspace: String,
+    lock_override: Option<String>,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let task = db
+        .create_task(&workspace, lock_override.as_deref(), &body)
+        .await
+        .map_err(reject::custom)?;
     // TODO: Return the created object
     // This is returning the id of the inserted object
     Ok(json(&task))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/tasks/dangerously-delete-all-tasks",
+    tag = "tasks",
+    responses((status = 200, description = "Every task was removed")),
+)]
 pub async fn delete_all_tasks_handler(db: DB) -> WebResult<impl Reply> {
-    db.delete_all_tasks().await.map_err(|e| reject::custom(e))?;
+    db.delete_all_tasks().await.map_err(reject::custom)?;
     // TODO: Return the deleted object
     Ok(StatusCode::OK)
 }
 
-pub async fn edit_task_handler(id: String, body: TaskRequest, db: DB) -> WebResult<impl Reply> {
-    db.edit_task(&id, &body)
+#[utoipa::path(
+    put,
+    path = "/tasks/{id}",
+    tag = "tasks",
+    params(("id" = String, Path, description = "Task id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing"), ("x-lock-override" = Option<String>, Header, description = "Workspace admin writing inside a locked period")),
+    request_body = TaskRequest,
+    responses((status = 200, description = "Updated task", body = TaskWriteResponse), (status = 404, description = "Not found", body = ErrorResponse), (status = 403, description = "Lock override by someone who is not a workspace admin", body = ErrorResponse), (status = 409, description = "Overlapping, locked by a timesheet or in a locked period", body = ErrorResponse)),
+)]
+pub async fn edit_task_handler(
+    id: String,
+    body: TaskRequest,
+    workspace: String,
+    lock_override: Option<String>,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let task = db
+        .edit_task(&workspace, lock_override.as_deref(), &id, &body)
         .await
-        .map_err(|e| reject::custom(e))?;
+        .map_err(reject::custom)?;
     // TODO: Return the edited object
-    Ok(StatusCode::OK)
+    Ok(json(&task))
+}
+#[utoipa::path(
+    post,
+    path = "/tasks/{id}/split",
+    tag = "tasks",
+    params(("id" = String, Path, description = "Task id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing"), ("x-lock-override" = Option<String>, Header, description = "Workspace admin writing inside a locked period")),
+    request_body = TaskSplitRequest,
+    responses((status = 200, description = "Both halves", body = TaskSplitResponse), (status = 404, description = "Not found", body = ErrorResponse), (status = 403, description = "Lock override by someone who is not a workspace admin", body = ErrorResponse), (status = 409, description = "Overlapping, locked by a timesheet or in a locked period", body = ErrorResponse)),
+)]
+pub async fn split_task_handler(
+    id: String,
+    body: TaskSplitRequest,
+    workspace: String,
+    lock_override: Option<String>,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let tasks = db
+        .split_task(&workspace, lock_override.as_deref(), &id, &body.at)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&tasks))
 }
-pub async fn delete_task_handler(id: String, db: DB) -> WebResult<impl Reply> {
-    db.delete_task(&id).await.map_err(|e| reject::custom(e))?;
+
+#[utoipa::path(
+    delete,
+    path = "/tasks/{id}",
+    tag = "tasks",
+    params(("id" = String, Path, description = "Task id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing"), ("x-lock-override" = Option<String>, Header, description = "Workspace admin writing inside a locked period")),
+    responses((status = 200, description = "Task was removed"), (status = 404, description = "Not found", body = ErrorResponse), (status = 403, description = "Lock override by someone who is not a workspace admin", body = ErrorResponse), (status = 409, description = "Overlapping, locked by a timesheet or in a locked period", body = ErrorResponse)),
+)]
+pub async fn delete_task_handler(
+    id: String,
+    workspace: String,
+    lock_override: Option<String>,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.delete_task(&workspace, lock_override.as_deref(), &id)
+        .await
+        .map_err(reject::custom)?;
     // Return the deleted object
     Ok(StatusCode::OK)
 }
@@ -0,0 +1,81 @@
+use crate::db::DB;
+use crate::error::ErrorResponse;
+use crate::models::timesheet::{
+    TimesheetResponse, TimesheetStatusRequest, WeekQuery, WeekTimesheet, WeekTimesheetRequest,
+};
+use crate::WebResult;
+use warp::{reject, reply::json, Reply};
+
+#[utoipa::path(
+    get,
+    path = "/timesheets/week",
+    tag = "timesheets",
+    params(WeekQuery, ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Week grid", body = WeekTimesheet), (status = 400, description = "Invalid id, date or time zone", body = ErrorResponse)),
+)]
+pub async fn fetch_week_timesheet_handler(
+    query: WeekQuery,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let timesheet = db
+        .get_week_timesheet(&workspace, &query)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&timesheet))
+}
+
+#[utoipa::path(
+    put,
+    path = "/timesheets/week",
+    tag = "timesheets",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = WeekTimesheetRequest,
+    responses((status = 200, description = "Updated week grid", body = WeekTimesheet), (status = 409, description = "Touches an approved week, locked or overlaps other tasks", body = ErrorResponse), (status = 422, description = "Invalid timesheet", body = ErrorResponse)),
+)]
+pub async fn update_week_timesheet_handler(
+    body: WeekTimesheetRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let timesheet = db
+        .update_week_timesheet(&workspace, &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&timesheet))
+}
+
+#[utoipa::path(
+    put,
+    path = "/timesheets/week/status",
+    tag = "timesheets",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = TimesheetStatusRequest,
+    responses((status = 200, description = "Timesheet", body = TimesheetResponse), (status = 403, description = "Review by someone who is not a workspace admin", body = ErrorResponse), (status = 409, description = "Invalid status transition", body = ErrorResponse), (status = 422, description = "Invalid timesheet", body = ErrorResponse)),
+)]
+pub async fn update_timesheet_status_handler(
+    body: TimesheetStatusRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let timesheet = db
+        .update_timesheet_status(&workspace, &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&timesheet))
+}
+
+#[utoipa::path(
+    get,
+    path = "/timesheets/pending",
+    tag = "timesheets",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Submitted timesheets", body = [TimesheetResponse])),
+)]
+pub async fn fetch_pending_timesheets_handler(workspace: String, db: DB) -> WebResult<impl Reply> {
+    let timesheets = db
+        .get_pending_timesheets(&workspace)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&timesheets))
+}
@@ -0,0 +1,74 @@
+use crate::db::DB;
+use crate::error::ErrorResponse;
+use crate::models::webhook::{WebhookDeliveryResponse, WebhookRequest, WebhookResponse};
+use crate::WebResult;
+use warp::{http::StatusCode, reject, reply::json, Reply};
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tag = "webhooks",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Webhooks", body = [WebhookResponse])),
+)]
+pub async fn fetch_webhooks_handler(workspace: String, db: DB) -> WebResult<impl Reply> {
+    let webhooks = db.get_webhooks(&workspace).await.map_err(reject::custom)?;
+    Ok(json(&webhooks))
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "webhooks",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    request_body = WebhookRequest,
+    responses((status = 201, description = "Id of the new webhook", body = String), (status = 422, description = "Invalid webhook", body = ErrorResponse)),
+)]
+pub async fn create_webhook_handler(
+    body: WebhookRequest,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let id = db
+        .create_webhook(&workspace, &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(warp::reply::with_status(json(&id), StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Webhook was removed"), (status = 404, description = "Not found", body = ErrorResponse)),
+)]
+pub async fn delete_webhook_handler(
+    id: String,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.delete_webhook(&workspace, &id)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = String, Path, description = "Webhook id"), ("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Delivery log, most recent first", body = [WebhookDeliveryResponse])),
+)]
+pub async fn fetch_webhook_deliveries_handler(
+    id: String,
+    workspace: String,
+    db: DB,
+) -> WebResult<impl Reply> {
+    let deliveries = db
+        .get_webhook_deliveries(&workspace, &id)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&deliveries))
+}
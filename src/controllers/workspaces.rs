@@ -0,0 +1,55 @@
+use crate::db::DB;
+use crate::error::ErrorResponse;
+use crate::models::workspace::{LockOverrideResponse, WorkspaceSettings};
+use crate::WebResult;
+use warp::{http::StatusCode, reject, reply::json, Reply};
+
+#[utoipa::path(
+    get,
+    path = "/workspace/settings",
+    tag = "workspace",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Settings", body = WorkspaceSettings)),
+)]
+pub async fn fetch_workspace_settings_handler(workspace: String, db: DB) -> WebResult<impl Reply> {
+    let settings = db
+        .get_workspace_settings(&workspace)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&settings))
+}
+
+#[utoipa::path(
+    put,
+    path = "/workspace/settings",
+    tag = "workspace",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing"), ("x-lock-override" = Option<String>, Header, description = "Workspace admin, needed to change `lock_date` or `admins`")),
+    request_body = WorkspaceSettings,
+    responses((status = 200, description = "Settings were saved"), (status = 400, description = "Invalid id, date or time zone", body = ErrorResponse), (status = 403, description = "Lock date or admins changed without a workspace admin", body = ErrorResponse)),
+)]
+pub async fn update_workspace_settings_handler(
+    body: WorkspaceSettings,
+    workspace: String,
+    lock_override: Option<String>,
+    db: DB,
+) -> WebResult<impl Reply> {
+    db.update_workspace_settings(&workspace, lock_override.as_deref(), &body)
+        .await
+        .map_err(reject::custom)?;
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(
+    get,
+    path = "/workspace/lock-overrides",
+    tag = "workspace",
+    params(("x-workspace" = Option<String>, Header, description = "Workspace, `default` when missing")),
+    responses((status = 200, description = "Writes inside the locked period and changes of the lock date or admins", body = [LockOverrideResponse])),
+)]
+pub async fn fetch_lock_overrides_handler(workspace: String, db: DB) -> WebResult<impl Reply> {
+    let overrides = db
+        .get_lock_overrides(&workspace)
+        .await
+        .map_err(reject::custom)?;
+    Ok(json(&overrides))
+}
@@ -0,0 +1,194 @@
+use crate::models::activity::{ActivityRequest, ActivityResponse, ActivitySuggestionsQuery};
+use crate::{error::Error::*, Result};
+use chrono::SecondsFormat;
+use futures::StreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Document, Regex};
+use mongodb::Collection;
+
+use super::{is_duplicate_key, DB};
+
+const SUGGESTIONS_LIMIT: i64 = 10;
+
+fn escape_regex(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(|c| {
+            if "\\.+*?()|[]{}^$".contains(c) {
+                vec!['\\', c]
+            } else {
+                vec![c]
+            }
+        })
+        .collect()
+}
+
+impl DB {
+    pub(super) fn get_activities_collection(&self) -> Collection<Document> {
+        self.database().collection("activities")
+    }
+
+    fn doc_to_activity(&self, doc: &Document) -> Result<ActivityResponse> {
+        let id = doc.get_object_id("_id")?;
+        let project = doc.get_object_id("project")?;
+        let name = doc.get_str("name")?;
+        let created_at = doc.get_datetime("created_at")?;
+        let updated_at = doc.get_datetime("updated_at")?;
+
+        let activity = ActivityResponse {
+            _id: id.to_hex(),
+            project: project.to_hex(),
+            name: name.to_owned(),
+            created_at: created_at
+                .to_chrono()
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            updated_at: updated_at
+                .to_chrono()
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        };
+
+        Ok(activity)
+    }
+
+    pub async fn get_project_activities(&self, project: &str) -> Result<Vec<ActivityResponse>> {
+        let oid = ObjectId::parse_str(project).map_err(|_| InvalidIDError(project.to_owned()))?;
+        let mut cursor = self
+            .get_activities_collection()
+            .find(doc! { "project": oid }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut result: Vec<ActivityResponse> = Vec::new();
+
+        while let Some(doc) = cursor.next().await {
+            result.push(self.doc_to_activity(&doc?)?);
+        }
+
+        Ok(result)
+    }
+
+    pub async fn create_activity(&self, project: &str, _entry: &ActivityRequest) -> Result<String> {
+        let oid = ObjectId::parse_str(project).map_err(|_| InvalidIDError(project.to_owned()))?;
+        // Fails with ObjNotFound for unknown projects
+        self.get_project_status(oid).await?;
+
+        let new_activity = self
+            .get_activities_collection()
+            .insert_one(
+                doc! {
+                "project": oid,
+                "name": _entry.name.clone(),
+                "created_at": chrono::Utc::now(),
+                "updated_at": chrono::Utc::now(),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| {
+                if is_duplicate_key(&e) {
+                    DuplicateNameError
+                } else {
+                    MongoQueryError(e)
+                }
+            })?;
+
+        Ok(new_activity.inserted_id.as_object_id().unwrap().to_hex())
+    }
+
+    pub async fn delete_activity(&self, id: &str) -> Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        // Keep the time entries, they just lose their activity
+        self.get_tasks_collection()
+            .update_many(
+                doc! { "activity": oid },
+                doc! { "$set": { "activity": null } },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.get_activities_collection()
+            .delete_one(doc! { "_id": oid }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    /// Activities must belong to the project of the task using them.
+    pub async fn ensure_activity_in_project(
+        &self,
+        activity: ObjectId,
+        project: Option<ObjectId>,
+    ) -> Result<()> {
+        let query = doc! {
+            "_id": activity,
+            "project": project,
+        };
+        self.get_activities_collection()
+            .find_one(query, None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(UnknownActivityError)?;
+
+        Ok(())
+    }
+
+    /// Most recently used activities first, for autocompletion.
+    pub async fn get_activity_suggestions(
+        &self,
+        filter: &ActivitySuggestionsQuery,
+    ) -> Result<Vec<ActivityResponse>> {
+        let mut task_filter = doc! { "activity": { "$type": "objectId" } };
+        if let Some(project) = &filter.project {
+            let oid =
+                ObjectId::parse_str(project).map_err(|_| InvalidIDError(project.to_owned()))?;
+            task_filter.insert("project", oid);
+        }
+
+        let mut activity_filter = doc! {};
+        if let Some(q) = &filter.q {
+            let pattern = Regex {
+                pattern: format!("^{}", escape_regex(q)),
+                options: "i".to_string(),
+            };
+            activity_filter.insert("activity.name", pattern);
+        }
+
+        let pipeline = vec![
+            doc! { "$match": task_filter },
+            doc! {
+                "$group": {
+                    "_id": "$activity",
+                    "last_used": { "$max": "$initial_time" },
+                },
+            },
+            doc! { "$sort": { "last_used": -1 } },
+            doc! {
+                "$lookup": {
+                    "from": "activities",
+                    "localField": "_id",
+                    "foreignField": "_id",
+                    "as": "activity",
+                }
+            },
+            doc! { "$unwind": "$activity" },
+            doc! { "$match": activity_filter },
+            doc! { "$limit": SUGGESTIONS_LIMIT },
+            doc! { "$replaceRoot": { "newRoot": "$activity" } },
+        ];
+
+        let mut cursor = self
+            .get_tasks_collection()
+            .aggregate(pipeline, None)
+            .await?;
+
+        let mut result: Vec<ActivityResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            result.push(self.doc_to_activity(&doc?)?);
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{error::Error::*, Result};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use serde_json::{Map, Value};
+
+use super::DB;
+
+// Resume tokens only make sense to the server that issued them
+const SKIPPED_COLLECTIONS: [&str; 1] = ["event_stream"];
+// Prefix of the collections `import --replace` fills before swapping them in
+const STAGING_PREFIX: &str = "import_staging.";
+
+// Names MongoDB would refuse, or that belong to the server or the import
+fn check_collection_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name.contains(['$', '\0'])
+        || name.starts_with("system.")
+        || name.starts_with(STAGING_PREFIX)
+    {
+        return Err(InvalidImportError(format!(
+            "{:?} is not a collection name to import",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+fn to_document(collection: &str, value: Value) -> Result<Document> {
+    match Bson::try_from(value) {
+        Ok(Bson::Document(doc)) => Ok(doc),
+        _ => Err(InvalidImportError(format!(
+            "{} holds something other than documents",
+            collection
+        ))),
+    }
+}
+
+impl DB {
+    /// Every collection as canonical extended JSON, so types like dates and
+    /// ObjectIds survive `import_collections`.
+    pub async fn export_collections(&self) -> Result<Map<String, Value>> {
+        let mut names = self
+            .database()
+            .list_collection_names(None)
+            .await
+            .map_err(MongoQueryError)?;
+        names.sort();
+
+        let mut collections = Map::new();
+        for name in names {
+            if name.starts_with("system.")
+                || name.starts_with(STAGING_PREFIX)
+                || SKIPPED_COLLECTIONS.contains(&name.as_str())
+            {
+                continue;
+            }
+
+            let docs: Vec<Document> = self
+                .database()
+                .collection::<Document>(&name)
+                .find(None, None)
+                .await
+                .map_err(MongoQueryError)?
+                .try_collect()
+                .await
+                .map_err(MongoQueryError)?;
+            let docs = docs
+                .into_iter()
+                .map(|doc| Bson::Document(doc).into_canonical_extjson())
+                .collect();
+
+            collections.insert(name, Value::Array(docs));
+        }
+
+        Ok(collections)
+    }
+
+    /// Inserts the documents of an export. With `replace` each collection ends
+    /// up holding only the imported documents. Returns the number inserted.
+    pub async fn import_collections(
+        &self,
+        collections: Map<String, Value>,
+        replace: bool,
+    ) -> Result<usize> {
+        // Everything is checked before the first write
+        let mut parsed: Vec<(String, Vec<Document>)> = vec![];
+        for (name, docs) in collections {
+            check_collection_name(&name)?;
+            let docs = match docs {
+                Value::Array(docs) => docs
+                    .into_iter()
+                    .map(|doc| to_document(&name, doc))
+                    .collect::<Result<Vec<Document>>>()?,
+                _ => {
+                    return Err(InvalidImportError(format!(
+                        "{} is not a list of documents",
+                        name
+                    )))
+                }
+            };
+            parsed.push((name, docs));
+        }
+
+        if replace {
+            return self.replace_collections(parsed).await;
+        }
+
+        let mut inserted = 0;
+        for (name, docs) in parsed {
+            inserted += self.insert_documents(&name, docs).await?;
+        }
+
+        Ok(inserted)
+    }
+
+    async fn insert_documents(&self, collection: &str, docs: Vec<Document>) -> Result<usize> {
+        if docs.is_empty() {
+            return Ok(0);
+        }
+
+        Ok(self
+            .database()
+            .collection::<Document>(collection)
+            .insert_many(docs, None)
+            .await
+            .map_err(MongoQueryError)?
+            .inserted_ids
+            .len())
+    }
+
+    /// Fills a staging copy of every collection first, so a failed import
+    /// leaves the existing documents untouched, then renames the copies over
+    /// the originals. The renames are not atomic together: if one fails
+    /// (e.g. the server goes away), the collections before it are already
+    /// replaced, which the error lists, and running the import again
+    /// finishes it.
+    async fn replace_collections(
+        &self,
+        collections: Vec<(String, Vec<Document>)>,
+    ) -> Result<usize> {
+        // Views cannot be renamed over, refuse before anything changes
+        let names: Vec<&str> = collections.iter().map(|(name, _)| name.as_str()).collect();
+        let views = self
+            .database()
+            .list_collection_names(doc! { "name": { "$in": &names }, "type": "view" })
+            .await
+            .map_err(MongoQueryError)?;
+        if !views.is_empty() {
+            return Err(InvalidImportError(format!(
+                "{} cannot be replaced, they are views",
+                views.join(", ")
+            )));
+        }
+
+        self.drop_staging_collections().await?;
+
+        let mut inserted = 0;
+        for (name, docs) in &collections {
+            let staging = format!("{}{}", STAGING_PREFIX, name);
+            let staged = async {
+                self.database()
+                    .create_collection(&staging, None)
+                    .await
+                    .map_err(MongoQueryError)?;
+                self.insert_documents(&staging, docs.clone()).await
+            };
+            match staged.await {
+                Ok(count) => inserted += count,
+                Err(e) => {
+                    self.drop_staging_collections().await?;
+                    return Err(e);
+                }
+            }
+        }
+
+        for (swapped, (name, _)) in collections.iter().enumerate() {
+            let renamed = self
+                .client
+                .database("admin")
+                .run_command(
+                    doc! {
+                        "renameCollection": format!("{}.{}{}", self.name, STAGING_PREFIX, name),
+                        "to": format!("{}.{}", self.name, name),
+                        "dropTarget": true,
+                    },
+                    None,
+                )
+                .await;
+            if let Err(e) = renamed {
+                return Err(InvalidImportError(format!(
+                    "replacing {} failed ({}), already replaced: [{}]; import again to finish",
+                    name,
+                    e,
+                    names[..swapped].join(", ")
+                )));
+            }
+        }
+        // Dropping the originals dropped their indexes too
+        self.create_indexes().await?;
+
+        Ok(inserted)
+    }
+
+    async fn drop_staging_collections(&self) -> Result<()> {
+        let names = self
+            .database()
+            .list_collection_names(doc! { "name": { "$regex": format!("^{}", STAGING_PREFIX) } })
+            .await
+            .map_err(MongoQueryError)?;
+        for name in names {
+            self.database()
+                .collection::<Document>(&name)
+                .drop(None)
+                .await
+                .map_err(MongoQueryError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Drops the whole database, indexes included (they are created again on
+    /// the next start).
+    pub async fn wipe(&self) -> Result<()> {
+        self.database().drop(None).await.map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use crate::error;
 use crate::error::Error::*;
-use crate::models::client::{ClientRequest, ClientResponse};
+use crate::models::client::{ClientPatch, ClientRequest, ClientResponse, ClientsQuery};
+use crate::models::webhook::WebhookEvent;
 
 use bson::Document;
 use futures::StreamExt;
@@ -8,22 +9,31 @@ use mongodb::bson::oid::ObjectId;
 use mongodb::bson::{self, doc};
 use mongodb::Collection;
 
-use super::{DB, DB_NAME};
+use super::{get_embedded, DB};
 
 impl DB {
-    fn get_clients_collection(&self) -> Collection<Document> {
-        self.client.database(DB_NAME).collection("clients")
+    pub(super) fn get_clients_collection(&self) -> Collection<Document> {
+        self.database().collection("clients")
     }
 
     pub fn doc_to_client(&self, doc: &Document) -> Result<ClientResponse, error::Error> {
         let id = doc.get_object_id("_id")?;
         let name = doc.get_str("name")?;
+        // Clients created before archiving existed have no flag
+        let archived = doc.get_bool("archived").unwrap_or(false);
         let created_at = doc.get_datetime("created_at")?;
         let updated_at = doc.get_datetime("updated_at")?;
 
         let client = ClientResponse {
             _id: id.to_hex(),
             name: name.to_owned(),
+            contacts: get_embedded(doc, "contacts").unwrap_or_default(),
+            billing_address: get_embedded(doc, "billing_address"),
+            tax_id: get_embedded(doc, "tax_id"),
+            currency: get_embedded(doc, "currency"),
+            hourly_rate: get_embedded(doc, "hourly_rate"),
+            notes: get_embedded(doc, "notes"),
+            archived,
             created_at: created_at.to_chrono().to_rfc3339(),
             updated_at: updated_at.to_chrono().to_rfc3339(),
         };
@@ -31,10 +41,18 @@ impl DB {
         Ok(client)
     }
 
-    pub async fn get_all_clients(&self) -> Result<Vec<ClientResponse>, error::Error> {
+    pub async fn get_all_clients(
+        &self,
+        filter: &ClientsQuery,
+    ) -> Result<Vec<ClientResponse>, error::Error> {
+        let query = match filter.include_archived {
+            Some(true) => doc! {},
+            _ => doc! { "archived": { "$ne": true } },
+        };
+
         let mut cursor = self
             .get_clients_collection()
-            .find(None, None)
+            .find(query, None)
             .await
             .map_err(MongoQueryError)?;
 
@@ -69,18 +87,34 @@ impl DB {
         Ok(result)
     }
 
-    pub async fn create_client(&self, _entry: &ClientRequest) -> Result<(), error::Error> {
-        self.get_clients_collection()
+    pub async fn create_client(
+        &self,
+        workspace: &str,
+        _entry: &ClientRequest,
+    ) -> Result<(), error::Error> {
+        let client = self
+            .get_clients_collection()
             .insert_one(
                 doc! {
+                "workspace": workspace,
                 "name": _entry.name.clone(),
-                "created_at": chrono::Utc::now().clone(),
-                "updated_at": chrono::Utc::now().clone(),
+                "contacts": bson::to_bson(&_entry.contacts)?,
+                "billing_address": bson::to_bson(&_entry.billing_address)?,
+                "tax_id": _entry.tax_id.clone(),
+                "currency": _entry.currency.clone(),
+                "hourly_rate": _entry.hourly_rate,
+                "notes": _entry.notes.clone(),
+                "archived": false,
+                "created_at": chrono::Utc::now(),
+                "updated_at": chrono::Utc::now(),
                 },
                 None,
             )
             .await
             .map_err(MongoQueryError)?;
+        let oid = client.inserted_id.as_object_id().unwrap();
+        self.publish_change(workspace, WebhookEvent::ClientCreated, oid)
+            .await;
 
         Ok(())
     }
@@ -96,20 +130,143 @@ impl DB {
         Ok(())
     }
 
-    pub async fn delete_client(&self, id: &str) -> Result<String, error::Error> {
+    pub async fn edit_client(
+        &self,
+        workspace: &str,
+        id: &str,
+        _entry: &ClientRequest,
+    ) -> Result<(), error::Error> {
         let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
         let query = doc! {
             "_id": oid,
+            "workspace": workspace,
         };
-        let deleted_result = self.get_clients_collection()
+        let doc = doc! {
+            "$set": {
+                "name": _entry.name.clone(),
+                "contacts": bson::to_bson(&_entry.contacts)?,
+                "billing_address": bson::to_bson(&_entry.billing_address)?,
+                "tax_id": _entry.tax_id.clone(),
+                "currency": _entry.currency.clone(),
+                "hourly_rate": _entry.hourly_rate,
+                "notes": _entry.notes.clone(),
+                "updated_at": chrono::Utc::now(),
+            }
+        };
+        let updated_result = self
+            .get_clients_collection()
+            .update_one(query, doc, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if updated_result.matched_count == 0 {
+            return Err(ObjNotFound);
+        }
+        self.publish_change(workspace, WebhookEvent::ClientUpdated, oid)
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn patch_client(
+        &self,
+        workspace: &str,
+        id: &str,
+        _entry: &ClientPatch,
+    ) -> Result<(), error::Error> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let query = doc! {
+            "_id": oid,
+            "workspace": workspace,
+        };
+        // Only the fields sent are changed
+        let mut changes = doc! { "updated_at": chrono::Utc::now() };
+        if let Some(name) = &_entry.name {
+            changes.insert("name", name);
+        }
+        if let Some(contacts) = &_entry.contacts {
+            changes.insert("contacts", bson::to_bson(contacts)?);
+        }
+        if let Some(billing_address) = &_entry.billing_address {
+            changes.insert("billing_address", bson::to_bson(billing_address)?);
+        }
+        if let Some(tax_id) = &_entry.tax_id {
+            changes.insert("tax_id", tax_id);
+        }
+        if let Some(currency) = &_entry.currency {
+            changes.insert("currency", currency);
+        }
+        if let Some(hourly_rate) = _entry.hourly_rate {
+            changes.insert("hourly_rate", hourly_rate);
+        }
+        if let Some(notes) = &_entry.notes {
+            changes.insert("notes", notes);
+        }
+        let updated_result = self
+            .get_clients_collection()
+            .update_one(query, doc! { "$set": changes }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if updated_result.matched_count == 0 {
+            return Err(ObjNotFound);
+        }
+        self.publish_change(workspace, WebhookEvent::ClientUpdated, oid)
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn set_client_archived(
+        &self,
+        workspace: &str,
+        id: &str,
+        archived: bool,
+    ) -> Result<(), error::Error> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let query = doc! {
+            "_id": oid,
+            "workspace": workspace,
+        };
+        let doc = doc! {
+            "$set": {
+                "archived": archived,
+                "updated_at": chrono::Utc::now(),
+            }
+        };
+        let updated_result = self
+            .get_clients_collection()
+            .update_one(query, doc, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if updated_result.matched_count == 0 {
+            return Err(ObjNotFound);
+        }
+        self.publish_change(workspace, WebhookEvent::ClientUpdated, oid)
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn delete_client(&self, workspace: &str, id: &str) -> Result<String, error::Error> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let query = doc! {
+            "_id": oid,
+            "workspace": workspace,
+        };
+        let deleted_result = self
+            .get_clients_collection()
             .delete_one(query, None)
             .await
             .map_err(MongoQueryError)?;
-        
+
         if deleted_result.deleted_count == 0 {
-            return Err(ObjNotFound)
+            return Err(ObjNotFound);
         }
-        
+        self.publish_change(workspace, WebhookEvent::ClientDeleted, oid)
+            .await;
+
         Ok(oid.to_hex())
     }
 
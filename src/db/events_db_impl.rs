@@ -0,0 +1,189 @@
+use crate::models::webhook::WebhookEvent;
+use crate::{error::Error::*, Result};
+use futures::StreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::change_stream::event::{ChangeStreamEvent, OperationType, ResumeToken};
+use mongodb::error::ErrorKind;
+use mongodb::options::{ChangeStreamOptions, FullDocumentBeforeChangeType, FullDocumentType};
+use mongodb::Collection;
+
+use super::{get_embedded, DB, DEFAULT_WORKSPACE};
+
+// Single document holding the resume token of the watcher
+const CHANGE_STREAM_ID: &str = "changes";
+const WATCHED_COLLECTIONS: [&str; 3] = ["tasks", "projects", "clients"];
+
+// The stored token is older than the oplog, so the missed changes are gone
+fn is_history_lost(error: &mongodb::error::Error) -> bool {
+    const CHANGE_STREAM_HISTORY_LOST: i32 = 286;
+
+    matches!(
+        error.kind.as_ref(),
+        ErrorKind::Command(e) if e.code == CHANGE_STREAM_HISTORY_LOST
+    )
+}
+
+fn change_to_event(collection: &str, operation: &OperationType) -> Option<WebhookEvent> {
+    use WebhookEvent::*;
+
+    let event = match (collection, operation) {
+        ("tasks", OperationType::Insert) => TaskCreated,
+        ("tasks", OperationType::Update | OperationType::Replace) => TaskUpdated,
+        ("tasks", OperationType::Delete) => TaskDeleted,
+        ("projects", OperationType::Insert) => ProjectCreated,
+        ("projects", OperationType::Update | OperationType::Replace) => ProjectUpdated,
+        ("projects", OperationType::Delete) => ProjectDeleted,
+        ("clients", OperationType::Insert) => ClientCreated,
+        ("clients", OperationType::Update | OperationType::Replace) => ClientUpdated,
+        ("clients", OperationType::Delete) => ClientDeleted,
+        _ => return None,
+    };
+
+    Some(event)
+}
+
+impl DB {
+    fn get_event_stream_collection(&self) -> Collection<Document> {
+        self.database().collection("event_stream")
+    }
+
+    /// Change streams need a replica set or a sharded cluster.
+    pub async fn supports_change_streams(&self) -> bool {
+        match self
+            .client
+            .database("admin")
+            .run_command(doc! { "isMaster": 1 }, None)
+            .await
+        {
+            Ok(reply) => reply.contains_key("setName") || reply.get_str("msg") == Ok("isdbgrid"),
+            Err(_) => false,
+        }
+    }
+
+    /// Keeps the previous version of changed documents, so deletes still know
+    /// their workspace. Only MongoDB 6 and later support it.
+    async fn enable_pre_images(&self) -> bool {
+        let db = self.database();
+        let mut enabled = true;
+
+        for collection in WATCHED_COLLECTIONS {
+            // Fails when the collection already exists, which is fine
+            let _ = db.create_collection(collection, None).await;
+            enabled &= db
+                .run_command(
+                    doc! {
+                        "collMod": collection,
+                        "changeStreamPreAndPostImages": { "enabled": true },
+                    },
+                    None,
+                )
+                .await
+                .is_ok();
+        }
+
+        enabled
+    }
+
+    async fn save_resume_token(&self, token: &ResumeToken) -> Result<()> {
+        self.get_event_stream_collection()
+            .update_one(
+                doc! { "_id": CHANGE_STREAM_ID },
+                doc! { "$set": {
+                    "resume_token": bson::to_bson(token)?,
+                    "updated_at": chrono::Utc::now(),
+                } },
+                mongodb::options::UpdateOptions::builder()
+                    .upsert(true)
+                    .build(),
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    /// Publishes every write to tasks, projects and clients, including those
+    /// of other processes, until the stream fails or ends. Picks up after the
+    /// last published change, so nothing written while the server was down is
+    /// lost.
+    pub async fn watch_changes(&self) -> Result<()> {
+        let token = self
+            .get_event_stream_collection()
+            .find_one(doc! { "_id": CHANGE_STREAM_ID }, None)
+            .await
+            .map_err(MongoQueryError)?
+            .and_then(|doc| get_embedded::<ResumeToken>(&doc, "resume_token"));
+
+        let before_change = if self.enable_pre_images().await {
+            Some(FullDocumentBeforeChangeType::WhenAvailable)
+        } else {
+            None
+        };
+        let options = ChangeStreamOptions::builder()
+            .full_document(Some(FullDocumentType::UpdateLookup))
+            .full_document_before_change(before_change)
+            .start_after(token)
+            .build();
+        let pipeline = [doc! { "$match": { "ns.coll": { "$in": WATCHED_COLLECTIONS.to_vec() } } }];
+
+        let mut stream = match self.database().watch(pipeline, options).await {
+            Ok(stream) => stream,
+            Err(e) if is_history_lost(&e) => {
+                eprintln!("change stream history lost, starting from now: {:?}", e);
+                self.get_event_stream_collection()
+                    .delete_one(doc! { "_id": CHANGE_STREAM_ID }, None)
+                    .await
+                    .map_err(MongoQueryError)?;
+                return Err(MongoQueryError(e));
+            }
+            Err(e) => return Err(MongoQueryError(e)),
+        };
+
+        // Handlers stop publishing once the stream is open, so no write is
+        // missed. When the guard drops, even on a panic, they take over again.
+        let _streaming = self.events.start_streaming();
+        if let Some(token) = stream.resume_token() {
+            self.save_resume_token(&token).await?;
+        }
+
+        while let Some(change) = stream.next().await {
+            let change = change.map_err(MongoQueryError)?;
+            self.publish_stream_change(&change).await;
+            self.save_resume_token(&change.id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn publish_stream_change(&self, change: &ChangeStreamEvent<Document>) {
+        let collection = change.ns.as_ref().and_then(|ns| ns.coll.as_deref());
+        let event = match collection.and_then(|c| change_to_event(c, &change.operation_type)) {
+            Some(event) => event,
+            None => return,
+        };
+        let id = match change
+            .document_key
+            .as_ref()
+            .and_then(|key| key.get_object_id("_id").ok())
+        {
+            Some(id) => id,
+            None => return,
+        };
+
+        // Documents written before workspaces were stored belong to the default one
+        let workspace = change
+            .full_document
+            .as_ref()
+            .or(change.full_document_before_change.as_ref())
+            .and_then(|doc| doc.get_str("workspace").ok())
+            .unwrap_or(DEFAULT_WORKSPACE);
+        match &change.full_document {
+            Some(doc) => self.publish_document(workspace, event, id, Some(doc)).await,
+            None if event.is_delete_event() => {
+                self.publish_document(workspace, event, id, None).await
+            }
+            // Updated documents deleted since, their delete follows
+            None => {}
+        }
+    }
+}
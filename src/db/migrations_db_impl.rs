@@ -0,0 +1,218 @@
+use crate::models::project::{Estimate, EstimateKind, EstimateRecurrence, ProjectStatus};
+use crate::{error::Error::*, Result};
+use futures::StreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::Collection;
+
+use super::{DB, DEFAULT_WORKSPACE};
+
+/// Rewrites documents of an older shape. Applied ones are recorded in the
+/// `migrations` collection and never run again.
+struct Migration {
+    name: &'static str,
+    collection: &'static str,
+    filter: Document,
+    update: Update,
+}
+
+enum Update {
+    // The same update for every matching document
+    All(Document),
+    // An update built from each matching document
+    Each(fn(&Document) -> Result<Document>),
+}
+
+/// Reads the free text estimates projects used to have, like `20h`,
+/// `1.5 hours`, `12:30` or `40h/month`. Anything else is `None`.
+fn parse_legacy_estimate(value: &str) -> Option<Estimate> {
+    let value = value.trim().to_lowercase();
+    let (value, recurrence) = match value
+        .strip_suffix("/month")
+        .or_else(|| value.strip_suffix("per month"))
+    {
+        Some(value) => (value.trim(), EstimateRecurrence::Monthly),
+        None => (value.as_str(), EstimateRecurrence::Total),
+    };
+
+    let amount = match value.split_once(':') {
+        Some((hours, minutes)) => {
+            let minutes: u32 = minutes.parse().ok().filter(|minutes| *minutes < 60)?;
+            hours.parse::<u32>().ok()? as f64 + minutes as f64 / 60.0
+        }
+        None => {
+            let number = ["hours", "hour", "hrs", "hr", "h"]
+                .iter()
+                .find_map(|unit| value.strip_suffix(unit))?;
+            number.trim().parse::<f64>().ok()?
+        }
+    };
+    if !amount.is_finite() || amount < 0.0 {
+        return None;
+    }
+
+    Some(Estimate {
+        kind: EstimateKind::Hours,
+        amount,
+        recurrence,
+        hourly_rate: None,
+    })
+}
+
+fn migrate_legacy_estimate(project: &Document) -> Result<Document> {
+    let legacy = project.get_str("estimate")?;
+
+    Ok(match parse_legacy_estimate(legacy) {
+        Some(estimate) => doc! { "$set": { "estimate": bson::to_bson(&estimate)? } },
+        None => doc! {
+            "$set": { "legacy_estimate": legacy },
+            "$unset": { "estimate": "" },
+        },
+    })
+}
+
+// Append only, in the order they have to run
+fn migrations() -> Vec<Migration> {
+    let mut migrations = vec![
+        // Older projects were created with an empty status string
+        Migration {
+            name: "project-status",
+            collection: "projects",
+            filter: doc! { "status": { "$in": ["", null] } },
+            update: Update::All(doc! { "$set": { "status": ProjectStatus::Active.as_str() } }),
+        },
+        // Estimates used to be free text, which is read as no estimate. Text
+        // that is not a number of hours is kept in `legacy_estimate`.
+        Migration {
+            name: "project-estimate-string",
+            collection: "projects",
+            filter: doc! { "estimate": { "$type": "string" } },
+            update: Update::Each(migrate_legacy_estimate),
+        },
+    ];
+
+    // Documents written before workspaces were stored belong to the default one
+    for (name, collection) in [
+        ("task-workspace", "tasks"),
+        ("project-workspace", "projects"),
+        ("client-workspace", "clients"),
+        ("timesheet-workspace", "timesheets"),
+    ] {
+        migrations.push(Migration {
+            name,
+            collection,
+            filter: doc! { "workspace": { "$exists": false } },
+            update: Update::All(doc! { "$set": { "workspace": DEFAULT_WORKSPACE } }),
+        });
+    }
+
+    migrations
+}
+
+impl DB {
+    fn get_migrations_collection(&self) -> Collection<Document> {
+        self.database().collection("migrations")
+    }
+
+    /// Runs the migrations not applied yet, returning their names and the
+    /// number of documents each one changed.
+    pub async fn migrate(&self) -> Result<Vec<(&'static str, u64)>> {
+        let mut applied = vec![];
+
+        for migration in migrations() {
+            let done = self
+                .get_migrations_collection()
+                .find_one(doc! { "_id": migration.name }, None)
+                .await
+                .map_err(MongoQueryError)?
+                .is_some();
+            if done {
+                continue;
+            }
+
+            let collection = self.database().collection::<Document>(migration.collection);
+            let changed = match migration.update {
+                Update::All(update) => {
+                    collection
+                        .update_many(migration.filter, update, None)
+                        .await
+                        .map_err(MongoQueryError)?
+                        .modified_count
+                }
+                Update::Each(update) => {
+                    let mut cursor = collection
+                        .find(migration.filter, None)
+                        .await
+                        .map_err(MongoQueryError)?;
+                    let mut changed = 0;
+                    while let Some(document) = cursor.next().await {
+                        let document = document?;
+                        changed += collection
+                            .update_one(
+                                doc! { "_id": document.get_object_id("_id")? },
+                                update(&document)?,
+                                None,
+                            )
+                            .await
+                            .map_err(MongoQueryError)?
+                            .modified_count;
+                    }
+                    changed
+                }
+            };
+            self.get_migrations_collection()
+                .insert_one(
+                    doc! { "_id": migration.name, "applied_at": chrono::Utc::now() },
+                    None,
+                )
+                .await
+                .map_err(MongoQueryError)?;
+
+            applied.push((migration.name, changed));
+        }
+
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn migration_names_are_unique() {
+        let names: HashSet<&str> = migrations().iter().map(|m| m.name).collect();
+        assert_eq!(names.len(), migrations().len());
+    }
+
+    #[test]
+    fn legacy_estimates_in_hours_are_parsed() {
+        let estimate = parse_legacy_estimate("20h").unwrap();
+        assert_eq!(estimate.kind, EstimateKind::Hours);
+        assert_eq!(estimate.amount, 20.0);
+        assert_eq!(estimate.recurrence, EstimateRecurrence::Total);
+
+        assert_eq!(parse_legacy_estimate(" 1.5 Hours").unwrap().amount, 1.5);
+        assert_eq!(parse_legacy_estimate("12:30").unwrap().amount, 12.5);
+        assert_eq!(
+            parse_legacy_estimate("40h/month").unwrap().recurrence,
+            EstimateRecurrence::Monthly
+        );
+
+        for legacy in ["", "20", "about a week", "500 EUR", "12:75", "-3h"] {
+            assert!(parse_legacy_estimate(legacy).is_none(), "{}", legacy);
+        }
+    }
+
+    #[test]
+    fn unparsed_estimates_are_kept_as_legacy() {
+        let update = migrate_legacy_estimate(&doc! { "estimate": "about a week" }).unwrap();
+        assert_eq!(
+            update,
+            doc! {
+                "$set": { "legacy_estimate": "about a week" },
+                "$unset": { "estimate": "" },
+            }
+        );
+    }
+}
@@ -1,24 +1,184 @@
+pub mod activities_db_impl;
+pub mod backup_db_impl;
 pub mod clients_db_impl;
+pub mod events_db_impl;
+pub mod migrations_db_impl;
 pub mod project_db_impl;
+pub mod reports_db_impl;
+pub mod seed_db_impl;
+pub mod tags_db_impl;
 pub mod tasks_db_impl;
+pub mod timesheets_db_impl;
+pub mod webhooks_db_impl;
+pub mod workspaces_db_impl;
 
+use crate::events::EventBus;
+use crate::notifications::Notifier;
 use crate::Result;
-use mongodb::{options::ClientOptions, Client};
+use mongodb::bson::{self, doc, Document};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{ClientOptions, IndexOptions};
+use mongodb::{Client, Database, IndexModel};
+use serde::de::DeserializeOwned;
 
 pub const DB_NAME: &str = "rust-time-tracker-base";
+const DEFAULT_MONGODB_URI: &str = "mongodb://127.0.0.1:27017";
+// Used when a request does not send an `x-workspace` header
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+// Unique index violations only, like re-queueing a change seen twice or
+// reusing a tag name
+fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    const DUPLICATE_KEY: i32 = 11000;
+
+    match error.kind.as_ref() {
+        ErrorKind::BulkWrite(failure) => {
+            failure.write_concern_error.is_none()
+                && failure
+                    .write_errors
+                    .iter()
+                    .flatten()
+                    .all(|e| e.code == DUPLICATE_KEY)
+        }
+        ErrorKind::Write(WriteFailure::WriteError(e)) => e.code == DUPLICATE_KEY,
+        _ => false,
+    }
+}
+
+// Dropping an index that (or whose collection) does not exist yet
+fn is_missing_index(error: &mongodb::error::Error) -> bool {
+    const NAMESPACE_NOT_FOUND: i32 = 26;
+    const INDEX_NOT_FOUND: i32 = 27;
+
+    matches!(
+        error.kind.as_ref(),
+        ErrorKind::Command(e) if e.code == NAMESPACE_NOT_FOUND || e.code == INDEX_NOT_FOUND
+    )
+}
+
+// Optional embedded values. Documents written before a field existed (or with
+// an older shape, like the plain string project estimates) are read as `None`.
+pub fn get_embedded<T: DeserializeOwned>(doc: &Document, key: &str) -> Option<T> {
+    doc.get(key)
+        .and_then(|value| bson::from_bson(value.clone()).ok())
+}
 
 #[derive(Clone, Debug)]
 pub struct DB {
     pub client: Client,
+    pub notifier: Notifier,
+    pub events: EventBus,
+    // Tests use a database of their own on the same server
+    pub name: String,
 }
 
 impl DB {
+    /// Connects to `MONGODB_URI`, the local server by default.
     pub async fn init() -> Result<Self> {
-        let mut client_options = ClientOptions::parse("mongodb://127.0.0.1:27017").await?;
+        let uri = std::env::var("MONGODB_URI").unwrap_or_else(|_| DEFAULT_MONGODB_URI.to_string());
+        Self::connect(&uri, DB_NAME).await
+    }
+
+    pub async fn connect(uri: &str, name: &str) -> Result<Self> {
+        let mut client_options = ClientOptions::parse(uri).await?;
         client_options.app_name = Some(DB_NAME.to_string());
 
-        Ok(Self {
+        let db = Self {
             client: Client::with_options(client_options)?,
-        })
+            notifier: Notifier::from_env(),
+            events: EventBus::default(),
+            name: name.to_string(),
+        };
+        db.create_indexes().await?;
+
+        Ok(db)
+    }
+
+    pub fn database(&self) -> Database {
+        self.client.database(&self.name)
+    }
+
+    /// Drops an index an older version created with other keys, if present.
+    async fn drop_replaced_index(&self, collection: &str, name: &str) -> Result<()> {
+        match self
+            .database()
+            .collection::<Document>(collection)
+            .drop_index(name, None)
+            .await
+        {
+            Err(e) if !is_missing_index(&e) => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn create_indexes(&self) -> Result<()> {
+        // Guarantees each budget threshold is only sent once per period and channel
+        self.drop_replaced_index("budget_alerts", "project_1_threshold_1")
+            .await?;
+        self.drop_replaced_index("budget_alerts", "project_1_threshold_1_period_1")
+            .await?;
+        let budget_alerts_index = IndexModel::builder()
+            .keys(doc! { "project": 1, "threshold": 1, "period": 1, "channel": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.database()
+            .collection::<Document>("budget_alerts")
+            .create_index(budget_alerts_index, None)
+            .await?;
+
+        let activities_index = IndexModel::builder()
+            .keys(doc! { "project": 1, "name": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.database()
+            .collection::<Document>("activities")
+            .create_index(activities_index, None)
+            .await?;
+
+        let tags_index = IndexModel::builder()
+            .keys(doc! { "workspace": 1, "name": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.database()
+            .collection::<Document>("tags")
+            .create_index(tags_index, None)
+            .await?;
+
+        // One timesheet per user and week of a workspace
+        self.drop_replaced_index("timesheets", "user_1_start_1")
+            .await?;
+        let timesheets = self.database().collection::<Document>("timesheets");
+        let timesheets_index = IndexModel::builder()
+            .keys(doc! { "workspace": 1, "user": 1, "start": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        timesheets.create_index(timesheets_index, None).await?;
+
+        // Lets the delivery worker find due deliveries
+        let deliveries_index = IndexModel::builder()
+            .keys(doc! { "status": 1, "next_attempt_at": 1 })
+            .build();
+        self.database()
+            .collection::<Document>("webhook_deliveries")
+            .create_index(deliveries_index, None)
+            .await?;
+
+        // A change is seen again when the change stream replays it after a
+        // restart, or replays a write the handlers published while it was down
+        let change_deliveries_index = IndexModel::builder()
+            .keys(doc! { "webhook": 1, "change": 1 })
+            .options(
+                IndexOptions::builder()
+                    .unique(true)
+                    .partial_filter_expression(doc! { "change": { "$exists": true } })
+                    .build(),
+            )
+            .build();
+        self.database()
+            .collection::<Document>("webhook_deliveries")
+            .create_index(change_deliveries_index, None)
+            .await?;
+
+        Ok(())
     }
 }
@@ -1,17 +1,39 @@
 use crate::models::project::{
-    ProjectAfterAggregation, ProjectRequest, ProjectResponse, ProjectsGroupedByClient,
+    BudgetAlert, BudgetAlertSettings, Estimate, ProjectAfterAggregation, ProjectBudget,
+    ProjectRequest, ProjectResponse, ProjectStatus, ProjectTree, ProjectsGroupedByClient,
+    ProjectsQuery,
 };
+use crate::models::task::ProjectTrackedTime;
+use crate::models::webhook::WebhookEvent;
 use crate::{error::Error::*, Result};
 use futures::StreamExt;
 use mongodb::bson::oid::ObjectId;
-use mongodb::bson::{doc, Document};
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::options::UpdateOptions;
 use mongodb::Collection;
+use std::collections::HashMap;
 
-use super::{DB, DB_NAME};
+use super::{get_embedded, DB};
+
+fn get_estimate(doc: &Document) -> Option<Estimate> {
+    get_embedded(doc, "estimate")
+}
+
+// Older projects were created with an empty status string
+fn get_status(doc: &Document) -> ProjectStatus {
+    doc.get_str("status")
+        .ok()
+        .and_then(|status| status.parse().ok())
+        .unwrap_or_default()
+}
 
 impl DB {
-    fn get_projects_collection(&self) -> Collection<Document> {
-        self.client.database(DB_NAME).collection("projects")
+    pub(super) fn get_projects_collection(&self) -> Collection<Document> {
+        self.database().collection("projects")
+    }
+
+    fn get_budget_alerts_collection(&self) -> Collection<Document> {
+        self.database().collection("budget_alerts")
     }
 
     pub fn doc_to_project(&self, doc: &Document) -> Result<ProjectResponse> {
@@ -19,18 +41,22 @@ impl DB {
         let client = doc.get_object_id("client")?;
         let name = doc.get_str("name")?;
         let color = doc.get_str("color")?;
-        let estimate = doc.get_str("estimate")?;
-        let status = doc.get_str("status")?;
+        let estimate = get_estimate(doc);
+        let status = get_status(doc);
         let created_at = doc.get_datetime("created_at")?;
         let updated_at = doc.get_datetime("updated_at")?;
 
         let project = ProjectResponse {
             _id: id.to_hex(),
             client: client.to_hex(),
+            parent: doc.get_object_id("parent").ok().map(|id| id.to_hex()),
             name: name.to_owned(),
             color: color.to_owned(),
-            estimate: estimate.to_owned(),
-            status: status.to_owned(),
+            estimate,
+            legacy_estimate: doc.get_str("legacy_estimate").ok().map(str::to_string),
+            budget_alerts: get_embedded(doc, "budget_alerts"),
+            rounding: get_embedded(doc, "rounding"),
+            status,
             created_at: created_at.to_chrono().to_rfc3339(),
             updated_at: updated_at.to_chrono().to_rfc3339(),
         };
@@ -38,7 +64,12 @@ impl DB {
         Ok(project)
     }
 
-    fn doc_project_grouped_by_client(&self, doc: &Document) -> Result<ProjectsGroupedByClient> {
+    fn doc_project_grouped_by_client(
+        &self,
+        doc: &Document,
+        tracked: &HashMap<ObjectId, ProjectTrackedTime>,
+        tree: &ProjectTree,
+    ) -> Result<ProjectsGroupedByClient> {
         let id = doc.get_str("_id")?;
         let projects = doc.get_array("projects")?;
 
@@ -50,8 +81,12 @@ impl DB {
             let name = project_doc.get_str("name")?;
             let color = project_doc.get_str("color")?;
             let client_name = project_doc.get_str("client_name")?;
-            let estimate = project_doc.get_str("estimate")?;
-            let status = project_doc.get_str("status")?;
+            let estimate = get_estimate(project_doc);
+            let status = get_status(project_doc);
+            let tracked_time = tree.rolled_up_time(project_id, tracked);
+            let budget = estimate.as_ref().map(|estimate| {
+                estimate.budget(tracked_time.total_seconds, tracked_time.month_seconds)
+            });
 
             // Need Better Names
             let proj = ProjectAfterAggregation {
@@ -59,8 +94,14 @@ impl DB {
                 name: name.to_string(),
                 color: color.to_string(),
                 client_name: client_name.to_string(),
-                estimate: estimate.to_string(),
-                status: status.to_string(),
+                parent: project_doc
+                    .get_object_id("parent")
+                    .ok()
+                    .map(|id| id.to_hex()),
+                estimate,
+                status,
+                budget,
+                subprojects: vec![],
             };
 
             projects_vec.push(proj);
@@ -68,7 +109,7 @@ impl DB {
 
         let results = ProjectsGroupedByClient {
             _id: id.to_string(),
-            projects: projects_vec,
+            projects: ProjectAfterAggregation::into_tree(projects_vec),
         };
 
         Ok(results)
@@ -85,17 +126,39 @@ impl DB {
             .await
             .map_err(MongoQueryError)?;
 
-        // if document == None {
-        //     // return error::Err(warp::reject::not_found());
-        //     return Err(ObjNotFound);
-        // }
-
-        let result = self.doc_to_project(&document.expect("Document not found"))?;
+        let result = self.doc_to_project(&document.ok_or(ObjNotFound)?)?;
 
         Ok(result)
     }
 
-    pub async fn get_projects_grouped_by_client(&self) -> Result<Vec<ProjectsGroupedByClient>> {
+    pub async fn get_projects_grouped_by_client(
+        &self,
+        filter: &ProjectsQuery,
+    ) -> Result<Vec<ProjectsGroupedByClient>> {
+        let include_archived = filter.include_archived.unwrap_or(false);
+
+        let status = match (filter.status, include_archived) {
+            // Projects written before statuses existed read as active
+            (Some(ProjectStatus::Active), _) => {
+                doc! { "status": { "$in": [ProjectStatus::Active.as_str(), "", Bson::Null] } }
+            }
+            (Some(status), _) => doc! { "status": status.as_str() },
+            (None, true) => doc! {},
+            (None, false) => doc! { "status": { "$ne": ProjectStatus::Archived.as_str() } },
+        };
+        let match_status = doc! {
+            "$match": status,
+        };
+
+        let archived_clients = if include_archived {
+            doc! {}
+        } else {
+            doc! { "client_name.archived": { "$ne": true } }
+        };
+        let match_archived_clients = doc! {
+            "$match": archived_clients,
+        };
+
         let lookup_clients = doc! {
             "$lookup": {
                 "from": "clients",
@@ -119,7 +182,7 @@ impl DB {
                 "client_name": { "$arrayElemAt": ["$client_name.name", 0] },
                 "estimate": "$estimate",
                 "status": "$status",
-                "subprojects": "$subprojects",
+                "parent": "$parent",
             },
         };
 
@@ -130,50 +193,383 @@ impl DB {
              },
         };
 
-        let pipeline = vec![lookup_clients, sort, project, group];
+        let pipeline = vec![
+            match_status,
+            lookup_clients,
+            match_archived_clients,
+            sort,
+            project,
+            group,
+        ];
 
         let mut cursor = self
             .get_projects_collection()
             .aggregate(pipeline, None)
             .await?;
 
+        let tracked = self.get_tracked_time_by_project(None).await?;
+        let tree = self.get_project_tree().await?;
+
         let mut results: Vec<ProjectsGroupedByClient> = Vec::new();
         while let Some(doc) = cursor.next().await {
-            results.push(self.doc_project_grouped_by_client(&doc?)?);
+            results.push(self.doc_project_grouped_by_client(&doc?, &tracked, &tree)?);
         }
 
         Ok(results)
     }
 
-    pub async fn create_project(&self, _entry: &ProjectRequest) -> Result<()> {
+    pub async fn get_project_tree(&self) -> Result<ProjectTree> {
+        let mut cursor = self
+            .get_projects_collection()
+            .find(doc! { "parent": { "$type": "objectId" } }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut parents: HashMap<ObjectId, ObjectId> = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            parents.insert(doc.get_object_id("_id")?, doc.get_object_id("parent")?);
+        }
+
+        Ok(ProjectTree::new(parents))
+    }
+
+    /// Time tracked on the project and all of its subprojects.
+    async fn get_rolled_up_time(&self, project: ObjectId) -> Result<ProjectTrackedTime> {
+        let tree = self.get_project_tree().await?;
+        let tracked = self
+            .get_tracked_time_by_project(Some(&tree.with_descendants(project)))
+            .await?;
+
+        Ok(tree.rolled_up_time(project, &tracked))
+    }
+
+    pub async fn get_project_budget(&self, id: &str) -> Result<ProjectBudget> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let query = doc! {
+            "_id": oid,
+        };
+        let document = self
+            .get_projects_collection()
+            .find_one(query, None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+
+        let estimate = get_estimate(&document).ok_or(MissingEstimateError)?;
+        let tracked_time = self.get_rolled_up_time(oid).await?;
+
+        Ok(estimate.budget(tracked_time.total_seconds, tracked_time.month_seconds))
+    }
+
+    /// Sends every alert threshold the project has crossed that was not sent
+    /// in the current period of its estimate yet, to each channel on its own.
+    /// `tracked_time` includes the subprojects.
+    pub async fn check_budget_alerts(
+        &self,
+        project: ObjectId,
+        tracked_time: ProjectTrackedTime,
+    ) -> Result<()> {
+        let document = self
+            .get_projects_collection()
+            .find_one(doc! { "_id": project }, None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+
+        let settings: BudgetAlertSettings = match get_embedded(&document, "budget_alerts") {
+            Some(settings) => settings,
+            None => return Ok(()),
+        };
+        let estimate = match get_estimate(&document) {
+            Some(estimate) => estimate,
+            None => return Ok(()),
+        };
+
+        let budget = estimate.budget(tracked_time.total_seconds, tracked_time.month_seconds);
+        let project_name = document.get_str("name")?;
+
+        let period = estimate.alert_period(chrono::Utc::now());
+
+        let mut alerts = vec![];
+        for threshold in &settings.thresholds {
+            if budget.percentage >= *threshold as f64 {
+                alerts.extend(settings.channels().into_iter().map(|c| (threshold, c)));
+            }
+        }
+
+        for (threshold, channel) in alerts {
+            // Claims the alert on this channel, so concurrent checks do not
+            // send it twice and a failed channel does not resend the others
+            let query = doc! {
+                "project": project,
+                "threshold": *threshold as i64,
+                "period": &period,
+                "channel": channel.name(),
+            };
+            let update = doc! {
+                "$setOnInsert": {
+                    "percentage": budget.percentage,
+                    "sent": false,
+                    "created_at": chrono::Utc::now(),
+                },
+            };
+            let options = UpdateOptions::builder().upsert(true).build();
+            let result = self
+                .get_budget_alerts_collection()
+                .update_one(query.clone(), update, options)
+                .await
+                .map_err(MongoQueryError)?;
+
+            // Already sent (or being sent) for this period
+            if result.upserted_id.is_none() {
+                continue;
+            }
+
+            let alert = BudgetAlert {
+                project: project.to_hex(),
+                project_name: project_name.to_string(),
+                threshold: *threshold,
+                percentage: budget.percentage,
+            };
+            match self.notifier.send_budget_alert(&channel, &alert).await {
+                Ok(()) => {
+                    self.get_budget_alerts_collection()
+                        .update_one(
+                            query,
+                            doc! { "$set": { "sent": true, "sent_at": chrono::Utc::now() } },
+                            None,
+                        )
+                        .await
+                        .map_err(MongoQueryError)?;
+                }
+                // Released, so the next task written on the project tries again
+                Err(e) => {
+                    eprintln!("budget alert for project {} failed: {}", project, e);
+                    self.get_budget_alerts_collection()
+                        .delete_one(query, None)
+                        .await
+                        .map_err(MongoQueryError)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_project_status(&self, project: ObjectId) -> Result<ProjectStatus> {
+        let document = self
+            .get_projects_collection()
+            .find_one(doc! { "_id": project }, None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+
+        Ok(get_status(&document))
+    }
+
+    /// Archived projects, and projects of archived clients, take no new tasks.
+    pub async fn ensure_project_open(&self, project: ObjectId) -> Result<()> {
+        let document = self
+            .get_projects_collection()
+            .find_one(doc! { "_id": project }, None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+        if get_status(&document) == ProjectStatus::Archived {
+            return Err(ArchivedProjectError);
+        }
+
+        if let Ok(client) = document.get_object_id("client") {
+            let archived_client = self
+                .get_clients_collection()
+                .find_one(doc! { "_id": client, "archived": true }, None)
+                .await
+                .map_err(MongoQueryError)?;
+            if archived_client.is_some() {
+                return Err(ArchivedClientError);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn update_project_status(
+        &self,
+        workspace: &str,
+        id: &str,
+        status: ProjectStatus,
+    ) -> Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let current = self.get_project_status(oid).await?;
+
+        if !current.can_transition_to(status) {
+            return Err(InvalidStatusTransitionError(current, status));
+        }
+
+        let query = doc! {
+            "_id": oid,
+            "workspace": workspace,
+        };
+        let doc = doc! {
+            "$set": {
+                "status": status.as_str(),
+                "updated_at": chrono::Utc::now(),
+            }
+        };
+        let updated = self
+            .get_projects_collection()
+            .update_one(query, doc, None)
+            .await
+            .map_err(MongoQueryError)?;
+        if updated.matched_count == 0 {
+            return Err(ObjNotFound);
+        }
+        self.publish_change(workspace, WebhookEvent::ProjectUpdated, oid)
+            .await;
+
+        Ok(())
+    }
+
+    /// Moves the project under another one of the same client, or to the top
+    /// level. A project can not move below itself or its own subprojects.
+    pub async fn update_project_parent(
+        &self,
+        workspace: &str,
+        id: &str,
+        parent: Option<ObjectId>,
+    ) -> Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let project = self
+            .get_projects_collection()
+            .find_one(doc! { "_id": oid, "workspace": workspace }, None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+
+        if let Some(parent) = parent {
+            let parent_doc = self
+                .get_projects_collection()
+                .find_one(doc! { "_id": parent, "workspace": workspace }, None)
+                .await
+                .map_err(MongoQueryError)?
+                .ok_or(InvalidParentProjectError)?;
+            if parent_doc.get_object_id("client")? != project.get_object_id("client")? {
+                return Err(InvalidParentProjectError);
+            }
+
+            let tree = self.get_project_tree().await?;
+            if tree.with_descendants(oid).contains(&parent) {
+                return Err(InvalidParentProjectError);
+            }
+        }
+
         self.get_projects_collection()
+            .update_one(
+                doc! { "_id": oid },
+                doc! { "$set": { "parent": parent, "updated_at": chrono::Utc::now() } },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+        self.publish_change(workspace, WebhookEvent::ProjectUpdated, oid)
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn create_project(&self, workspace: &str, _entry: &ProjectRequest) -> Result<()> {
+        if let Some(estimate) = &_entry.estimate {
+            estimate.validate()?;
+        }
+        if let Some(parent) = _entry.parent {
+            let parent_doc = self
+                .get_projects_collection()
+                .find_one(doc! { "_id": parent, "workspace": workspace }, None)
+                .await
+                .map_err(MongoQueryError)?
+                .ok_or(InvalidParentProjectError)?;
+
+            if parent_doc.get_object_id("client")? != _entry.client {
+                return Err(InvalidParentProjectError);
+            }
+        }
+
+        let project = self
+            .get_projects_collection()
             .insert_one(
                 doc! {
+                "workspace": workspace,
                 "name": _entry.name.clone(),
                 "color": _entry.color.clone(),
-                "estimate": _entry.estimate.clone(),
-                "status": _entry.status.clone(),
+                "estimate": bson::to_bson(&_entry.estimate)?,
+                "budget_alerts": bson::to_bson(&_entry.budget_alerts)?,
+                "rounding": bson::to_bson(&_entry.rounding)?,
+                "status": _entry.status.as_str(),
                 "client": _entry.client,
-                "created_at": chrono::Utc::now().clone(),
-                "updated_at": chrono::Utc::now().clone(),
+                "parent": _entry.parent,
+                "created_at": chrono::Utc::now(),
+                "updated_at": chrono::Utc::now(),
                 },
                 None,
             )
             .await
             .map_err(MongoQueryError)?;
+        let oid = project.inserted_id.as_object_id().unwrap();
+        self.publish_change(workspace, WebhookEvent::ProjectCreated, oid)
+            .await;
 
         Ok(())
     }
 
-    pub async fn delete_project(&self, id: &str) -> Result<()> {
+    pub async fn delete_project(&self, workspace: &str, id: &str) -> Result<()> {
         let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
         let query = doc! {
             "_id": oid,
+            "workspace": workspace,
         };
-        self.get_projects_collection()
+        let project = self
+            .get_projects_collection()
+            .find_one(query.clone(), None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+        let deleted = self
+            .get_projects_collection()
             .delete_one(query, None)
             .await
             .map_err(MongoQueryError)?;
+        if deleted.deleted_count == 0 {
+            return Err(ObjNotFound);
+        }
+        self.publish_change(workspace, WebhookEvent::ProjectDeleted, oid)
+            .await;
+
+        // Subprojects move up to the parent of the deleted project, instead of
+        // pointing at a project that no longer exists
+        let parent = project.get_object_id("parent").ok();
+        let children: Vec<ObjectId> = self
+            .get_projects_collection()
+            .find(doc! { "parent": oid, "workspace": workspace }, None)
+            .await
+            .map_err(MongoQueryError)?
+            .filter_map(|child| async move { child.ok()?.get_object_id("_id").ok() })
+            .collect()
+            .await;
+        if !children.is_empty() {
+            self.get_projects_collection()
+                .update_many(
+                    doc! { "_id": { "$in": &children } },
+                    doc! { "$set": { "parent": parent, "updated_at": chrono::Utc::now() } },
+                    None,
+                )
+                .await
+                .map_err(MongoQueryError)?;
+        }
+        for child in children {
+            self.publish_change(workspace, WebhookEvent::ProjectUpdated, child)
+                .await;
+        }
 
         Ok(())
     }
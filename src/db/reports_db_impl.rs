@@ -0,0 +1,400 @@
+use crate::models::report::{
+    ActivityReport, ActivitySummary, ProjectSummary, ReportQuery, SummaryReport,
+};
+use crate::models::task::{start_of_day, TrackedDuration};
+use crate::models::workspace::RoundingRule;
+use crate::{error::Error::*, Result};
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use futures::StreamExt;
+use mongodb::bson::{self, doc, oid::ObjectId, Bson, Document};
+use std::collections::{HashMap, HashSet};
+
+use super::tasks_db_impl::{get_number, parse_time_zone, tag_filter};
+use super::{get_embedded, DB};
+
+pub(super) fn parse_day(day: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| InvalidDateError(day.to_owned()))
+}
+
+/// Start and end instants of the inclusive range of days of the report, in
+/// the requester's time zone.
+fn report_range(filter: &ReportQuery) -> Result<(Option<bson::DateTime>, Option<bson::DateTime>)> {
+    let tz = parse_time_zone(&filter.tz)?;
+    let start = match &filter.from {
+        Some(from) => Some(start_of_day(parse_day(from)?, tz).into()),
+        None => None,
+    };
+    let end = match &filter.to {
+        Some(to) => Some(start_of_day(parse_day(to)?.succ_opt().unwrap(), tz).into()),
+        None => None,
+    };
+
+    Ok((start, end))
+}
+
+/// Applies the project rounding rule (or the workspace one) to the clipped
+/// entries of a report row. Without any rule the raw time is returned.
+fn rounded_seconds(doc: &Document, workspace_rule: Option<RoundingRule>, tz: Tz) -> i64 {
+    let entries: Vec<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> = doc
+        .get_array("entries")
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| entry.as_document())
+                .filter_map(|entry| {
+                    Some((
+                        entry.get_datetime("start").ok()?.to_chrono(),
+                        entry.get_datetime("end").ok()?.to_chrono(),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    match get_embedded::<RoundingRule>(doc, "rounding").or(workspace_rule) {
+        Some(rule) => rule.apply(&entries, tz),
+        None => entries
+            .iter()
+            .map(|(start, end)| (*end - *start).num_seconds())
+            .sum(),
+    }
+}
+
+impl DB {
+    /// Builds the `$match` stage shared by the reports: workspace and tag
+    /// plus every task touching the range of days.
+    pub(super) fn report_filter(&self, workspace: &str, filter: &ReportQuery) -> Result<Document> {
+        let mut query = tag_filter(workspace, &filter.tag)?;
+        let (start, end) = report_range(filter)?;
+
+        if let Some(start) = start {
+            query.insert("end_time", doc! { "$gt": start });
+        }
+        if let Some(end) = end {
+            query.insert("initial_time", doc! { "$lt": end });
+        }
+
+        Ok(query)
+    }
+
+    /// Start and end of each task clipped to the range of the report, so tasks
+    /// crossing its first or last midnight only count their part.
+    fn report_bounds(&self, filter: &ReportQuery) -> Result<(Bson, Bson)> {
+        let (start, end) = report_range(filter)?;
+
+        let initial_time = match start {
+            Some(start) => Bson::Document(doc! { "$max": ["$initial_time", start] }),
+            None => Bson::String("$initial_time".to_string()),
+        };
+        let end_time = match end {
+            Some(end) => Bson::Document(doc! { "$min": ["$end_time", end] }),
+            None => Bson::String("$end_time".to_string()),
+        };
+
+        Ok((initial_time, end_time))
+    }
+
+    /// `$group` accumulators shared by the reports: total milliseconds, task
+    /// count and the clipped entries used to apply rounding rules.
+    fn report_accumulators(&self, filter: &ReportQuery, id: &str) -> Result<Document> {
+        let (initial_time, end_time) = self.report_bounds(filter)?;
+
+        Ok(doc! {
+            "_id": id,
+            "total_ms": { "$sum": { "$subtract": [end_time.clone(), initial_time.clone()] } },
+            "task_count": { "$sum": 1 },
+            "entries": { "$push": { "start": initial_time, "end": end_time } },
+        })
+    }
+
+    pub async fn get_summary_report(
+        &self,
+        workspace: &str,
+        filter: &ReportQuery,
+    ) -> Result<SummaryReport> {
+        let rounding = self.get_workspace_settings(workspace).await?.rounding;
+        let tz = parse_time_zone(&filter.tz)?;
+
+        let match_filter = doc! {
+            "$match": self.report_filter(workspace, filter)?,
+        };
+
+        let group = doc! {
+            "$group": self.report_accumulators(filter, "$project")?,
+        };
+
+        let lookup_projects = doc! {
+            "$lookup": {
+                "from": "projects",
+                "localField": "_id",
+                "foreignField": "_id",
+                "as": "project",
+            }
+        };
+        let lookup_clients = doc! {
+            "$lookup": {
+              "from": "clients",
+              "localField": "project.client",
+              "foreignField": "_id",
+              "as": "client",
+            }
+        };
+
+        let project = doc! {
+            "$project": {
+                "_id": "$_id",
+                "total_ms": "$total_ms",
+                "task_count": "$task_count",
+                "entries": "$entries",
+                "project_name": { "$arrayElemAt": ["$project.name", 0] },
+                "client_name": { "$arrayElemAt": ["$client.name", 0] },
+                "rounding": { "$arrayElemAt": ["$project.rounding", 0] },
+            },
+        };
+
+        let sort = doc! {
+            "$sort": {
+                "total_ms": -1,
+            },
+        };
+
+        let pipeline = vec![
+            match_filter,
+            group,
+            lookup_projects,
+            lookup_clients,
+            project,
+            sort,
+        ];
+
+        let mut cursor = self
+            .get_tasks_collection()
+            .aggregate(pipeline, None)
+            .await?;
+
+        let mut projects: Vec<ProjectSummary> = vec![];
+        while let Some(doc) = cursor.next().await {
+            projects.push(self.doc_to_project_summary(&doc?, rounding, tz));
+        }
+
+        self.roll_up_project_summaries(&mut projects).await?;
+
+        Ok(SummaryReport {
+            from: filter.from.clone(),
+            to: filter.to.clone(),
+            total: projects
+                .iter()
+                .map(|project| project.total.seconds)
+                .sum::<i64>()
+                .into(),
+            rounded_total: projects
+                .iter()
+                .map(|project| project.rounded.seconds)
+                .sum::<i64>()
+                .into(),
+            projects,
+        })
+    }
+
+    fn doc_to_project_summary(
+        &self,
+        doc: &Document,
+        rounding: Option<RoundingRule>,
+        tz: Tz,
+    ) -> ProjectSummary {
+        ProjectSummary {
+            project: doc.get_object_id("_id").ok().map(|id| id.to_hex()),
+            parent: None,
+            project_name: doc
+                .get_str("project_name")
+                .ok()
+                .map(|name| name.to_string()),
+            client_name: doc.get_str("client_name").ok().map(|name| name.to_string()),
+            total: (get_number(doc, "total_ms") / 1000).into(),
+            rounded: rounded_seconds(doc, rounding, tz).into(),
+            total_with_subprojects: TrackedDuration::default(),
+            rounded_with_subprojects: TrackedDuration::default(),
+            task_count: get_number(doc, "task_count"),
+        }
+    }
+
+    /// Adds parents without own time in the period and sums the time of every
+    /// subproject into its ancestors.
+    async fn roll_up_project_summaries(&self, projects: &mut Vec<ProjectSummary>) -> Result<()> {
+        let tree = self.get_project_tree().await?;
+
+        let listed: HashSet<ObjectId> = projects
+            .iter()
+            .filter_map(|summary| summary.project.as_ref())
+            .filter_map(|id| ObjectId::parse_str(id).ok())
+            .collect();
+        let missing: HashSet<ObjectId> = listed
+            .iter()
+            .flat_map(|project| tree.ancestors(*project))
+            .filter(|project| !listed.contains(project))
+            .collect();
+
+        if !missing.is_empty() {
+            let missing: Vec<ObjectId> = missing.into_iter().collect();
+            let pipeline = vec![
+                doc! { "$match": { "_id": { "$in": missing } } },
+                doc! {
+                    "$lookup": {
+                      "from": "clients",
+                      "localField": "client",
+                      "foreignField": "_id",
+                      "as": "client",
+                    }
+                },
+                doc! {
+                    "$project": {
+                        "_id": "$_id",
+                        "project_name": "$name",
+                        "client_name": { "$arrayElemAt": ["$client.name", 0] },
+                    },
+                },
+            ];
+
+            let mut cursor = self
+                .get_projects_collection()
+                .aggregate(pipeline, None)
+                .await?;
+            while let Some(doc) = cursor.next().await {
+                projects.push(self.doc_to_project_summary(&doc?, None, Tz::UTC));
+            }
+        }
+
+        let own_seconds: HashMap<ObjectId, (i64, i64)> = projects
+            .iter()
+            .filter_map(|summary| {
+                let id = ObjectId::parse_str(summary.project.as_ref()?).ok()?;
+                Some((id, (summary.total.seconds, summary.rounded.seconds)))
+            })
+            .collect();
+
+        for summary in projects.iter_mut() {
+            let id = match summary.project.as_ref().map(ObjectId::parse_str) {
+                Some(Ok(id)) => id,
+                _ => {
+                    summary.total_with_subprojects = summary.total.clone();
+                    summary.rounded_with_subprojects = summary.rounded.clone();
+                    continue;
+                }
+            };
+            summary.parent = tree.parents.get(&id).map(|parent| parent.to_hex());
+            let (mut total, mut rounded) = (0, 0);
+            for project in tree.with_descendants(id) {
+                if let Some((seconds, rounded_seconds)) = own_seconds.get(&project) {
+                    total += seconds;
+                    rounded += rounded_seconds;
+                }
+            }
+            summary.total_with_subprojects = total.into();
+            summary.rounded_with_subprojects = rounded.into();
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_activity_report(
+        &self,
+        workspace: &str,
+        filter: &ReportQuery,
+    ) -> Result<ActivityReport> {
+        let rounding = self.get_workspace_settings(workspace).await?.rounding;
+        let tz = parse_time_zone(&filter.tz)?;
+
+        let match_filter = doc! {
+            "$match": self.report_filter(workspace, filter)?,
+        };
+
+        let group = doc! {
+            "$group": self.report_accumulators(filter, "$activity")?,
+        };
+
+        let lookup_activities = doc! {
+            "$lookup": {
+                "from": "activities",
+                "localField": "_id",
+                "foreignField": "_id",
+                "as": "activity",
+            }
+        };
+        let lookup_projects = doc! {
+            "$lookup": {
+              "from": "projects",
+              "localField": "activity.project",
+              "foreignField": "_id",
+              "as": "project",
+            }
+        };
+
+        let project = doc! {
+            "$project": {
+                "_id": "$_id",
+                "total_ms": "$total_ms",
+                "task_count": "$task_count",
+                "entries": "$entries",
+                "activity_name": { "$arrayElemAt": ["$activity.name", 0] },
+                "project_name": { "$arrayElemAt": ["$project.name", 0] },
+                "rounding": { "$arrayElemAt": ["$project.rounding", 0] },
+            },
+        };
+
+        let sort = doc! {
+            "$sort": {
+                "total_ms": -1,
+            },
+        };
+
+        let pipeline = vec![
+            match_filter,
+            group,
+            lookup_activities,
+            lookup_projects,
+            project,
+            sort,
+        ];
+
+        let mut cursor = self
+            .get_tasks_collection()
+            .aggregate(pipeline, None)
+            .await?;
+
+        let mut activities: Vec<ActivitySummary> = vec![];
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            activities.push(ActivitySummary {
+                activity: doc.get_object_id("_id").ok().map(|id| id.to_hex()),
+                activity_name: doc
+                    .get_str("activity_name")
+                    .ok()
+                    .map(|name| name.to_string()),
+                project_name: doc
+                    .get_str("project_name")
+                    .ok()
+                    .map(|name| name.to_string()),
+                total: (get_number(&doc, "total_ms") / 1000).into(),
+                rounded: rounded_seconds(&doc, rounding, tz).into(),
+                task_count: get_number(&doc, "task_count"),
+            });
+        }
+
+        Ok(ActivityReport {
+            from: filter.from.clone(),
+            to: filter.to.clone(),
+            total: activities
+                .iter()
+                .map(|activity| activity.total.seconds)
+                .sum::<i64>()
+                .into(),
+            rounded_total: activities
+                .iter()
+                .map(|activity| activity.rounded.seconds)
+                .sum::<i64>()
+                .into(),
+            activities,
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use crate::seed::{self, SeedOptions, SeedSummary};
+use crate::{error::Error::*, Result};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+
+use super::DB;
+
+// Documents about the clients, projects and tasks a seed replaces
+const DEPENDENT_COLLECTIONS: [&str; 4] = ["tags", "activities", "timesheets", "budget_alerts"];
+
+// Sorted, so the seeded documents do not depend on the order MongoDB returns
+fn parse_ids(ids: Vec<String>) -> Result<Vec<ObjectId>> {
+    let mut ids = ids
+        .iter()
+        .map(|id| ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned())))
+        .collect::<Result<Vec<ObjectId>>>()?;
+    ids.sort();
+
+    Ok(ids)
+}
+
+impl DB {
+    /// Replaces every client, project and task (see `remove_all_data`) with
+    /// `options.clients` clients.
+    pub async fn seed_clients(&self, options: &SeedOptions) -> Result<SeedSummary> {
+        options.validate()?;
+        let clients = seed::generate_clients(options)?;
+
+        self.remove_all_data().await?;
+        let summary = SeedSummary {
+            clients: clients.len(),
+            ..Default::default()
+        };
+        if !clients.is_empty() {
+            self.create_many_clients(clients).await?;
+        }
+
+        Ok(summary)
+    }
+
+    /// Replaces every project and task with projects of the existing clients.
+    pub async fn seed_projects(&self, options: &SeedOptions) -> Result<SeedSummary> {
+        options.validate()?;
+        let client_ids = parse_ids(self.get_all_clients_ids().await?)?;
+        let projects = seed::generate_projects(options, &client_ids)?;
+
+        self.delete_all_projects().await?;
+        self.delete_all_tasks().await?;
+        let summary = SeedSummary {
+            projects: projects.len(),
+            ..Default::default()
+        };
+        if !projects.is_empty() {
+            self.create_many_projects(projects).await?;
+        }
+
+        Ok(summary)
+    }
+
+    /// Replaces every task with tasks of the existing projects.
+    pub async fn seed_tasks(&self, options: &SeedOptions) -> Result<SeedSummary> {
+        options.validate()?;
+        let project_ids = parse_ids(self.get_all_projects_ids().await?)?;
+        let tasks = seed::generate_tasks(options, &project_ids)?;
+
+        self.delete_all_tasks().await?;
+        let summary = SeedSummary {
+            tasks: tasks.len(),
+            ..Default::default()
+        };
+        if !tasks.is_empty() {
+            self.create_many_tasks(tasks).await?;
+        }
+
+        Ok(summary)
+    }
+
+    pub async fn seed_all(&self, options: &SeedOptions) -> Result<SeedSummary> {
+        let clients = self.seed_clients(options).await?;
+        let projects = self.seed_projects(options).await?;
+        let tasks = self.seed_tasks(options).await?;
+
+        Ok(SeedSummary {
+            clients: clients.clients,
+            projects: projects.projects,
+            tasks: tasks.tasks,
+        })
+    }
+
+    /// Removes the clients, projects and tasks, and what only exists for them:
+    /// tags, activities, timesheets and sent budget alerts.
+    pub async fn remove_all_data(&self) -> Result<()> {
+        self.delete_all_clients().await?;
+        self.delete_all_projects().await?;
+        self.delete_all_tasks().await?;
+        for collection in DEPENDENT_COLLECTIONS {
+            self.database()
+                .collection::<Document>(collection)
+                .delete_many(doc! {}, None)
+                .await
+                .map_err(MongoQueryError)?;
+        }
+
+        Ok(())
+    }
+}
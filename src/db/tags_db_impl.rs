@@ -0,0 +1,181 @@
+use crate::models::tag::{TagRequest, TagResponse};
+use crate::{error::Error::*, Result};
+use chrono::SecondsFormat;
+use futures::StreamExt;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Document};
+use mongodb::Collection;
+
+use super::{is_duplicate_key, DB};
+
+impl DB {
+    pub(super) fn get_tags_collection(&self) -> Collection<Document> {
+        self.database().collection("tags")
+    }
+
+    fn doc_to_tag(&self, doc: &Document) -> Result<TagResponse> {
+        let id = doc.get_object_id("_id")?;
+        let name = doc.get_str("name")?;
+        let workspace = doc.get_str("workspace")?;
+        let created_at = doc.get_datetime("created_at")?;
+        let updated_at = doc.get_datetime("updated_at")?;
+
+        let tag = TagResponse {
+            _id: id.to_hex(),
+            name: name.to_owned(),
+            workspace: workspace.to_owned(),
+            created_at: created_at
+                .to_chrono()
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            updated_at: updated_at
+                .to_chrono()
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        };
+
+        Ok(tag)
+    }
+
+    async fn find_workspace_tag(&self, workspace: &str, id: &str) -> Result<ObjectId> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let query = doc! {
+            "_id": oid,
+            "workspace": workspace,
+        };
+        self.get_tags_collection()
+            .find_one(query, None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+
+        Ok(oid)
+    }
+
+    pub async fn get_all_tags(&self, workspace: &str) -> Result<Vec<TagResponse>> {
+        let mut cursor = self
+            .get_tags_collection()
+            .find(doc! { "workspace": workspace }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut result: Vec<TagResponse> = Vec::new();
+
+        while let Some(doc) = cursor.next().await {
+            result.push(self.doc_to_tag(&doc?)?);
+        }
+
+        Ok(result)
+    }
+
+    pub async fn create_tag(&self, workspace: &str, _entry: &TagRequest) -> Result<String> {
+        let new_tag = self
+            .get_tags_collection()
+            .insert_one(
+                doc! {
+                "name": _entry.name.clone(),
+                "workspace": workspace,
+                "created_at": chrono::Utc::now(),
+                "updated_at": chrono::Utc::now(),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| {
+                if is_duplicate_key(&e) {
+                    DuplicateNameError
+                } else {
+                    MongoQueryError(e)
+                }
+            })?;
+
+        Ok(new_tag.inserted_id.as_object_id().unwrap().to_hex())
+    }
+
+    pub async fn rename_tag(&self, workspace: &str, id: &str, _entry: &TagRequest) -> Result<()> {
+        let oid = self.find_workspace_tag(workspace, id).await?;
+        let doc = doc! {
+            "$set": {
+                "name": _entry.name.clone(),
+                "updated_at": chrono::Utc::now(),
+            }
+        };
+        self.get_tags_collection()
+            .update_one(doc! { "_id": oid }, doc, None)
+            .await
+            .map_err(|e| {
+                if is_duplicate_key(&e) {
+                    DuplicateNameError
+                } else {
+                    MongoQueryError(e)
+                }
+            })?;
+
+        Ok(())
+    }
+
+    /// Moves every task tagged with `id` over to `into` and removes `id`.
+    pub async fn merge_tags(&self, workspace: &str, id: &str, into: &str) -> Result<()> {
+        let oid = self.find_workspace_tag(workspace, id).await?;
+        let into_oid = self.find_workspace_tag(workspace, into).await?;
+
+        if oid == into_oid {
+            return Ok(());
+        }
+
+        self.get_tasks_collection()
+            .update_many(
+                doc! { "tags": oid },
+                doc! { "$addToSet": { "tags": into_oid } },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.delete_tag(workspace, id).await
+    }
+
+    pub async fn delete_tag(&self, workspace: &str, id: &str) -> Result<()> {
+        let oid = self.find_workspace_tag(workspace, id).await?;
+
+        self.get_tasks_collection()
+            .update_many(
+                doc! { "tags": oid },
+                doc! { "$pull": { "tags": oid } },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.get_tags_collection()
+            .delete_one(doc! { "_id": oid }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    /// Every tag must exist in `workspace`.
+    pub async fn ensure_tags_exist(&self, workspace: &str, tags: &[ObjectId]) -> Result<()> {
+        if tags.is_empty() {
+            return Ok(());
+        }
+
+        let mut unique = tags.to_vec();
+        unique.sort();
+        unique.dedup();
+
+        let found = self
+            .get_tags_collection()
+            .count_documents(
+                doc! { "_id": { "$in": &unique }, "workspace": workspace },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        if found as usize != unique.len() {
+            return Err(UnknownTagError);
+        }
+
+        Ok(())
+    }
+}
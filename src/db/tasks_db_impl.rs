@@ -1,24 +1,103 @@
-use crate::models::task::{TaskAfterGrouped, TaskRequest, TaskResponse, TasksGroupedByDate};
+use crate::models::task::{
+    find_overlapping_pairs, group_by_day, start_of_day, trim_overlaps, OverlapEntry, OverlapsQuery,
+    ProjectTrackedTime, TaskAfterGrouped, TaskOverlap, TaskRequest, TaskResponse,
+    TaskSplitResponse, TaskWriteResponse, TasksGroupedByDate, TasksQuery,
+};
+use crate::models::webhook::WebhookEvent;
+use crate::models::workspace::OverlapPolicy;
 use crate::{error::Error::*, Result};
 use chrono::prelude::*;
+use chrono_tz::Tz;
 use futures::StreamExt;
 use mongodb::bson::{self, Bson};
 use mongodb::bson::{doc, document::Document, oid::ObjectId};
+use mongodb::options::FindOptions;
 use mongodb::Collection;
+use std::collections::HashMap;
 
-use super::{DB, DB_NAME};
+use super::reports_db_impl::parse_day;
+use super::DB;
+
+// Days listed by the grouped view when the query has no `from`
+const DEFAULT_GROUPED_DAYS: i64 = 30;
+
+// Aggregation sums come back as Int32, Int64 or Double depending on the operands
+pub(super) fn get_number(doc: &Document, key: &str) -> i64 {
+    match doc.get(key) {
+        Some(Bson::Int32(value)) => *value as i64,
+        Some(Bson::Int64(value)) => *value,
+        Some(Bson::Double(value)) => *value as i64,
+        _ => 0,
+    }
+}
+
+pub(super) fn get_tag_ids(doc: &Document) -> Vec<String> {
+    doc.get_array("tags")
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| tag.as_object_id())
+                .map(|tag| tag.to_hex())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_time_range(entry: &TaskRequest) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let initial_time: DateTime<Utc> = entry
+        .initial_time
+        .parse()
+        .map_err(|_| InvalidDateError(entry.initial_time.clone()))?;
+    let end_time: DateTime<Utc> = entry
+        .end_time
+        .parse()
+        .map_err(|_| InvalidDateError(entry.end_time.clone()))?;
+
+    if end_time < initial_time {
+        return Err(InvalidDateError(entry.end_time.clone()));
+    }
+
+    Ok((initial_time, end_time))
+}
+
+pub(super) fn parse_time_zone(tz: &Option<String>) -> Result<Tz> {
+    match tz {
+        Some(tz) => tz.parse().map_err(|_| InvalidTimeZoneError(tz.to_owned())),
+        None => Ok(Tz::UTC),
+    }
+}
+
+/// Tasks of `workspace`, only those carrying `tag` when given. Tag ids of
+/// other workspaces match nothing.
+pub(super) fn tag_filter(workspace: &str, tag: &Option<String>) -> Result<Document> {
+    let mut query = doc! { "workspace": workspace };
+    if let Some(tag) = tag {
+        let oid = ObjectId::parse_str(tag).map_err(|_| InvalidIDError(tag.to_owned()))?;
+        query.insert("tags", oid);
+    }
+
+    Ok(query)
+}
+
+// Tag and user filters shared by the task list and the grouped view
+fn tasks_filter(workspace: &str, filter: &TasksQuery) -> Result<Document> {
+    let mut query = tag_filter(workspace, &filter.tag)?;
+    if let Some(user) = &filter.user {
+        query.insert("user", user);
+    }
+
+    Ok(query)
+}
 
 impl DB {
-    fn get_tasks_collection(&self) -> Collection<Document> {
-        self.client.database(DB_NAME).collection("tasks")
+    pub(super) fn get_tasks_collection(&self) -> Collection<Document> {
+        self.database().collection("tasks")
     }
 
-    fn doc_to_task(&self, doc: &Document) -> Result<TaskResponse> {
+    pub(super) fn doc_to_task(&self, doc: &Document) -> Result<TaskResponse> {
         let id = doc.get_object_id("_id")?;
         let name = doc.get_str("name")?;
         let initial_time = doc.get_datetime("initial_time")?;
         let end_time = doc.get_datetime("end_time")?;
-        let project = doc.get_object_id("project")?;
         let created_at = doc.get_datetime("created_at")?;
         let updated_at = doc.get_datetime("updated_at")?;
 
@@ -30,6 +109,7 @@ impl DB {
         let task = TaskResponse {
             _id: id.to_hex(),
             name: name.to_owned(),
+            user: doc.get_str("user").ok().map(|user| user.to_string()),
             // initial_time: initial_time.to_string(),
             initial_time: initial_time
                 .to_chrono()
@@ -38,7 +118,13 @@ impl DB {
             end_time: end_time
                 .to_chrono()
                 .to_rfc3339_opts(SecondsFormat::Secs, true),
-            project: Some(project.to_hex()),
+            project: doc.get_object_id("project").ok().map(|id| id.to_hex()),
+            activity: doc.get_object_id("activity").ok().map(|id| id.to_hex()),
+            description: doc.get_str("description").ok().map(|d| d.to_string()),
+            tags: get_tag_ids(doc),
+            duration: (end_time.to_chrono() - initial_time.to_chrono())
+                .num_seconds()
+                .into(),
             created_at: created_at
                 .to_chrono()
                 .to_rfc3339_opts(SecondsFormat::Secs, true),
@@ -50,10 +136,85 @@ impl DB {
         Ok(task)
     }
 
-    pub async fn get_all_tasks(&self) -> Result<Vec<TaskResponse>> {
+    // Receivers can take a while to answer, so alerts are checked in the
+    // background instead of holding the write that triggered them.
+    pub(super) fn spawn_budget_alerts(&self, project: ObjectId) {
+        let db = self.clone();
+        tokio::spawn(async move { db.check_budget_alerts_for_task(project).await });
+    }
+
+    // The task is already written at this point, so alert failures are only logged.
+    // Parent projects include the time of their subprojects, so they are checked too.
+    async fn check_budget_alerts_for_task(&self, project: ObjectId) {
+        let tree = match self.get_project_tree().await {
+            Ok(tree) => tree,
+            Err(e) => {
+                eprintln!("could not load project tree for {}: {:?}", project, e);
+                return;
+            }
+        };
+        let ancestors = tree.ancestors(project);
+
+        // The subtree of the root holds the time of every project checked
+        let root = ancestors.last().copied().unwrap_or(project);
+        let tracked = match self
+            .get_tracked_time_by_project(Some(&tree.with_descendants(root)))
+            .await
+        {
+            Ok(tracked) => tracked,
+            Err(e) => {
+                eprintln!("could not load tracked time for {}: {:?}", project, e);
+                return;
+            }
+        };
+
+        for project in std::iter::once(project).chain(ancestors) {
+            let tracked_time = tree.rolled_up_time(project, &tracked);
+            if let Err(e) = self.check_budget_alerts(project, tracked_time).await {
+                eprintln!("could not check budget alerts for {}: {:?}", project, e);
+            }
+        }
+    }
+
+    /// The stored task, tasks of other workspaces are not found.
+    async fn get_task_document(&self, workspace: &str, oid: ObjectId) -> Result<Document> {
+        self.get_tasks_collection()
+            .find_one(doc! { "_id": oid, "workspace": workspace }, None)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)
+    }
+
+    /// Tasks included in an approved timesheet can no longer change.
+    fn ensure_task_unlocked(task: &Document) -> Result<()> {
+        match task.get_object_id("locked_by") {
+            Ok(_) => Err(TimesheetLockedError),
+            Err(_) => Ok(()),
+        }
+    }
+
+    pub async fn get_all_tasks(
+        &self,
+        workspace: &str,
+        filter: &TasksQuery,
+    ) -> Result<Vec<TaskResponse>> {
+        let tz = parse_time_zone(&filter.tz)?;
+        let mut query = tasks_filter(workspace, filter)?;
+        if let Some(to) = &filter.to {
+            let end = start_of_day(parse_day(to)?.succ_opt().unwrap(), tz);
+            query.insert("initial_time", doc! { "$lt": end });
+        }
+        // Started tasks end where they start, one started at midnight still counts
+        if let Some(from) = &filter.from {
+            query.insert(
+                "end_time",
+                doc! { "$gte": start_of_day(parse_day(from)?, tz) },
+            );
+        }
+
         let mut cursor = self
             .get_tasks_collection()
-            .find(None, None)
+            .find(query, None)
             .await
             .map_err(MongoQueryError)?;
 
@@ -66,7 +227,31 @@ impl DB {
         Ok(result)
     }
 
-    pub async fn get_tasks_grouped_by_date(&self) -> Result<Vec<TasksGroupedByDate>> {
+    pub async fn get_tasks_grouped_by_date(
+        &self,
+        workspace: &str,
+        filter: &TasksQuery,
+    ) -> Result<Vec<TasksGroupedByDate>> {
+        let tz = parse_time_zone(&filter.tz)?;
+        let to = match &filter.to {
+            Some(to) => parse_day(to)?,
+            None => Utc::now().with_timezone(&tz).date_naive(),
+        };
+        let from = match &filter.from {
+            Some(from) => parse_day(from)?,
+            None => to - chrono::Duration::days(DEFAULT_GROUPED_DAYS - 1),
+        };
+
+        let mut query = tasks_filter(workspace, filter)?;
+        query.insert(
+            "initial_time",
+            doc! { "$lt": start_of_day(to.succ_opt().unwrap(), tz) },
+        );
+        query.insert("end_time", doc! { "$gt": start_of_day(from, tz) });
+        let match_range = doc! {
+            "$match": query,
+        };
+
         let lookup_projects = doc! {
             "$lookup": {
                 "from": "projects",
@@ -83,6 +268,14 @@ impl DB {
               "as": "client",
             }
         };
+        let lookup_activities = doc! {
+            "$lookup": {
+              "from": "activities",
+              "localField": "activity",
+              "foreignField": "_id",
+              "as": "activity",
+            }
+        };
 
         let project = doc! {
               "$project": {
@@ -93,172 +286,436 @@ impl DB {
                     "project": { "$arrayElemAt": ["$project.name", 0] },
                     "project_color": { "$arrayElemAt": ["$project.color", 0] },
                     "client": { "$arrayElemAt": ["$client.name", 0] },
+                    "activity": { "$arrayElemAt": ["$activity.name", 0] },
+                    "description": "$description",
+                    "tags": "$tags",
                 },
         };
 
-        let group = doc! {
-            "$group": {
-                "_id": { "$dateToString": { "format": "%Y-%m-%d", "date": "$initial_time" } },
-                "tasks": { "$push": "$$ROOT" },
-                "totalTime": {
-                    "$sum":{
-                        "$divide": [{ "$subtract": ["$end_time", "$initial_time"] }, 1000],
-                    },
-                },
-            },
-        };
-
         let sort = doc! {
              "$sort": {
-                "_id": -1,
+                "initial_time": -1,
             },
         };
 
-        let pipeline = vec![lookup_projects, lookup_clients, project, group, sort];
+        let pipeline = vec![
+            match_range,
+            lookup_projects,
+            lookup_clients,
+            lookup_activities,
+            project,
+            sort,
+        ];
 
         let mut cursor = self
             .get_tasks_collection()
             .aggregate(pipeline, None)
             .await?;
 
-        let mut grouped_tasks_vec: Vec<TasksGroupedByDate> = vec![];
-        let mut tasks_vec: Vec<TaskAfterGrouped> = vec![];
+        let mut tasks: Vec<(DateTime<Utc>, DateTime<Utc>, TaskAfterGrouped)> = vec![];
 
         while let Some(doc) = cursor.next().await {
-            let doc_real = doc.unwrap();
-            let id = doc_real.get_str("_id")?;
-            let tasks = doc_real.get_array("tasks")?;
-            let total_time = doc_real.get_i32("totalTime").unwrap_or(0);
-
-            for item in tasks {
-                let task_document = item.as_document().unwrap();
-
-                let _id = task_document.get_object_id("_id")?.to_hex();
-                let name = task_document.get_str("name")?.to_string();
-                let initial_time = task_document
-                    .get_datetime("initial_time")?
-                    .to_chrono()
-                    .to_rfc3339_opts(SecondsFormat::Secs, true);
-                let end_time = task_document
-                    .get_datetime("end_time")?
-                    .to_chrono()
-                    .to_rfc3339_opts(SecondsFormat::Secs, true);
-                let project = task_document.get_str("project").ok();
-
-                let project_color = task_document.get_str("project_color").ok();
-
-                let client = task_document.get_str("client").ok();
-
-                fn project_name(proj: Option<&str>) -> Option<String> {
-                    match proj {
-                        Some(proj) => Some(proj.to_string()),
-                        None => None,
-                    }
-                }
+            let task_document = doc?;
+
+            let initial_time = task_document.get_datetime("initial_time")?.to_chrono();
+            let end_time = task_document.get_datetime("end_time")?.to_chrono();
+            let project = task_document.get_str("project").ok();
+
+            let project_color = task_document.get_str("project_color").ok();
+
+            let client = task_document.get_str("client").ok();
 
-                let task = TaskAfterGrouped {
-                    _id,
-                    name,
-                    initial_time,
-                    end_time,
-                    project: project_name(project),
-                    project_color: project_name(project_color),
-                    client: project_name(client),
-                };
-
-                tasks_vec.push(task);
+            let activity = task_document.get_str("activity").ok();
+
+            let description = task_document.get_str("description").ok();
+
+            fn project_name(proj: Option<&str>) -> Option<String> {
+                proj.map(|proj| proj.to_string())
             }
-            let grouped_tasks = TasksGroupedByDate {
-                _id: id.to_string(),
-                tasks: tasks_vec.to_owned(),
-                total_time: total_time,
+
+            let task = TaskAfterGrouped {
+                _id: task_document.get_object_id("_id")?.to_hex(),
+                name: task_document.get_str("name")?.to_string(),
+                initial_time: initial_time.to_rfc3339_opts(SecondsFormat::Secs, true),
+                end_time: end_time.to_rfc3339_opts(SecondsFormat::Secs, true),
+                project: project_name(project),
+                project_color: project_name(project_color),
+                client: project_name(client),
+                activity: project_name(activity),
+                description: project_name(description),
+                tags: get_tag_ids(&task_document),
+                duration: (end_time - initial_time).num_seconds().into(),
             };
 
-            grouped_tasks_vec.push(grouped_tasks);
+            tasks.push((initial_time, end_time, task));
         }
 
-        Ok(grouped_tasks_vec.to_vec())
+        // Tasks crossing midnight (in the requester's time zone) are listed on
+        // every day they span, each day only counting its own part. Days
+        // outside the range come from tasks crossing its edges.
+        let (from, to) = (from.to_string(), to.to_string());
+        Ok(group_by_day(tasks, tz)
+            .into_iter()
+            .filter(|day| day._id >= from && day._id <= to)
+            .collect())
     }
 
-    pub async fn find_task(&self, id: &str) -> Result<TaskResponse> {
+    pub async fn find_task(&self, workspace: &str, id: &str) -> Result<TaskResponse> {
         let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let document = self.get_task_document(workspace, oid).await?;
+
+        self.doc_to_task(&document)
+    }
+
+    /// Stored tasks of `user` in `workspace` overlapping the time range, as
+    /// their ids and ranges. Tasks without a user never overlap.
+    pub(super) async fn find_overlaps(
+        &self,
+        workspace: &str,
+        user: Option<&str>,
+        exclude: &[ObjectId],
+        initial_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<(Vec<String>, Vec<(DateTime<Utc>, DateTime<Utc>)>)> {
+        let mut ids: Vec<String> = vec![];
+        let mut ranges: Vec<(DateTime<Utc>, DateTime<Utc>)> = vec![];
+        let user = match user {
+            Some(user) => user,
+            None => return Ok((ids, ranges)),
+        };
+
         let query = doc! {
-            "_id": oid,
+            "_id": { "$nin": exclude },
+            "workspace": workspace,
+            "user": user,
+            "initial_time": { "$lt": end_time },
+            "end_time": { "$gt": initial_time },
         };
-        let document = self
+        let mut cursor = self
             .get_tasks_collection()
-            .find_one(query, None)
+            .find(query, None)
             .await
             .map_err(MongoQueryError)?;
 
-        if document.is_none() {
-            // return error::Err(warp::reject::not_found());
-            return Err(ObjNotFound);
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            ids.push(doc.get_object_id("_id")?.to_hex());
+            ranges.push((
+                doc.get_datetime("initial_time")?.to_chrono(),
+                doc.get_datetime("end_time")?.to_chrono(),
+            ));
         }
 
-        let result = self.doc_to_task(&document.unwrap())?;
+        Ok((ids, ranges))
+    }
 
-        Ok(result)
+    /// Applies the workspace overlap policy to a new time range of `user`,
+    /// returning the range to store and the ids of the tasks it overlaps.
+    async fn resolve_overlaps(
+        &self,
+        workspace: &str,
+        user: Option<&str>,
+        exclude: &[ObjectId],
+        initial_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>, Vec<String>)> {
+        let (ids, ranges) = self
+            .find_overlaps(workspace, user, exclude, initial_time, end_time)
+            .await?;
+
+        if ids.is_empty() {
+            return Ok((initial_time, end_time, ids));
+        }
+
+        match self.get_workspace_settings(workspace).await?.overlap_policy {
+            OverlapPolicy::Reject => Err(OverlappingTaskError(ids)),
+            OverlapPolicy::Warn => Ok((initial_time, end_time, ids)),
+            OverlapPolicy::AutoTrim => match trim_overlaps(initial_time, end_time, &ranges) {
+                Some((initial_time, end_time)) => Ok((initial_time, end_time, vec![])),
+                None => Err(OverlappingTaskError(ids)),
+            },
+        }
     }
 
-    pub async fn create_task(&self, _entry: &TaskRequest) -> Result<Bson> {
-        let initial_time: chrono::DateTime<Utc> = _entry.initial_time.parse().unwrap();
-        // let initial_time: bson::DateTime = chrono_dt.into();
+    pub async fn create_task(
+        &self,
+        workspace: &str,
+        lock_override: Option<&str>,
+        _entry: &TaskRequest,
+    ) -> Result<TaskWriteResponse> {
+        let (initial_time, end_time) = parse_time_range(_entry)?;
+        let overridden_by = self
+            .check_period_lock(workspace, lock_override, &[initial_time])
+            .await?;
+        self.ensure_weeks_open(workspace, _entry.user.as_deref(), initial_time, end_time)
+            .await?;
 
-        let end_time: chrono::DateTime<Utc> = _entry.end_time.parse().unwrap();
-        // let end_time: bson::DateTime = chrono_endtime.into();
+        let project: Option<ObjectId> = _entry.project;
+        self.ensure_tags_exist(workspace, &_entry.tags).await?;
+        if let Some(activity) = _entry.activity {
+            self.ensure_activity_in_project(activity, project).await?;
+        }
+
+        if let Some(project) = project {
+            self.ensure_project_open(project).await?;
+        }
 
-        let project: Option<ObjectId> = _entry.project.clone();
+        let (initial_time, end_time, overlapping_tasks) = self
+            .resolve_overlaps(
+                workspace,
+                _entry.user.as_deref(),
+                &[],
+                initial_time,
+                end_time,
+            )
+            .await?;
 
         let new_task = self
             .get_tasks_collection()
             .insert_one(
                 doc! {
+                "workspace": workspace,
                 "name": _entry.name.clone(),
+                "user": _entry.user.clone(),
                 "initial_time": initial_time,
                 "end_time": end_time,
                 "project": Some(project),
-                "created_at": chrono::Utc::now().clone(),
-                "updated_at": chrono::Utc::now().clone(),
+                "activity": _entry.activity,
+                "description": _entry.description.clone(),
+                "tags": &_entry.tags,
+                "created_at": chrono::Utc::now(),
+                "updated_at": chrono::Utc::now(),
                 },
                 None,
             )
             .await
             .map_err(MongoQueryError)?;
+        let oid = new_task.inserted_id.as_object_id().unwrap();
 
-        Ok(new_task.inserted_id)
+        if let Some(admin) = overridden_by {
+            self.record_lock_override(workspace, admin, "create", oid)
+                .await?;
+        }
+        self.publish_change(workspace, WebhookEvent::TaskCreated, oid)
+            .await;
+
+        if let Some(project) = project {
+            self.spawn_budget_alerts(project);
+        }
+
+        Ok(TaskWriteResponse {
+            _id: oid.to_hex(),
+            overlapping_tasks,
+        })
     }
 
-    pub async fn edit_task(&self, id: &str, _entry: &TaskRequest) -> Result<()> {
+    pub async fn edit_task(
+        &self,
+        workspace: &str,
+        lock_override: Option<&str>,
+        id: &str,
+        _entry: &TaskRequest,
+    ) -> Result<TaskWriteResponse> {
         let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let task = self.get_task_document(workspace, oid).await?;
+        Self::ensure_task_unlocked(&task)?;
+
+        let (initial_time, end_time) = parse_time_range(_entry)?;
+        // Moving a task out of a locked period changes that period too
+        let times = vec![initial_time, task.get_datetime("initial_time")?.to_chrono()];
+        let overridden_by = self
+            .check_period_lock(workspace, lock_override, &times)
+            .await?;
+        self.ensure_weeks_open(workspace, _entry.user.as_deref(), initial_time, end_time)
+            .await?;
+        let project: Option<ObjectId> = _entry.project;
+        self.ensure_tags_exist(workspace, &_entry.tags).await?;
+        if let Some(activity) = _entry.activity {
+            self.ensure_activity_in_project(activity, project).await?;
+        }
 
-        let chrono_dt: chrono::DateTime<Utc> = _entry.initial_time.parse().unwrap();
-        let initial_time: bson::DateTime = chrono_dt.into();
-        let chrono_endtime: chrono::DateTime<Utc> = _entry.end_time.parse().unwrap();
-        let end_time: bson::DateTime = chrono_endtime.into();
-        let project: Option<ObjectId> = _entry.project.clone();
+        let (initial_time, end_time, overlapping_tasks) = self
+            .resolve_overlaps(
+                workspace,
+                _entry.user.as_deref(),
+                &[oid],
+                initial_time,
+                end_time,
+            )
+            .await?;
+        let initial_time: bson::DateTime = initial_time.into();
+        let end_time: bson::DateTime = end_time.into();
 
         let query = doc! {
             "_id": oid,
+            "workspace": workspace,
         };
 
         let doc = doc! {
             "$set": {
                 "name": _entry.name.clone(),
-                "initial_time": initial_time.clone(),
-                "end_time": end_time.clone(),
+                "user": _entry.user.clone(),
+                "initial_time": initial_time,
+                "end_time": end_time,
                 "project": project,
-                "updated_at": chrono::Utc::now().clone(),
+                "activity": _entry.activity,
+                "description": _entry.description.clone(),
+                "tags": &_entry.tags,
+                "updated_at": chrono::Utc::now(),
                 }
         };
 
         self.get_tasks_collection()
             .find_one_and_update(query, doc, None)
             .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+
+        if let Some(admin) = overridden_by {
+            self.record_lock_override(workspace, admin, "edit", oid)
+                .await?;
+        }
+        self.publish_change(workspace, WebhookEvent::TaskUpdated, oid)
+            .await;
+
+        if let Some(project) = project {
+            self.spawn_budget_alerts(project);
+        }
+
+        Ok(TaskWriteResponse {
+            _id: oid.to_hex(),
+            overlapping_tasks,
+        })
+    }
+
+    /// Every pair of tasks of the same user in `workspace` covering the same
+    /// minutes. Tasks without a user never overlap.
+    pub async fn get_task_overlaps(
+        &self,
+        workspace: &str,
+        filter: &OverlapsQuery,
+    ) -> Result<Vec<TaskOverlap>> {
+        let query = match &filter.user {
+            Some(user) => doc! { "workspace": workspace, "user": user },
+            None => doc! { "workspace": workspace, "user": { "$ne": null } },
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "user": 1, "initial_time": 1 })
+            .build();
+        let mut cursor = self
+            .get_tasks_collection()
+            .find(query, options)
+            .await
             .map_err(MongoQueryError)?;
 
-        Ok(())
+        let mut tasks: Vec<OverlapEntry> = vec![];
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            tasks.push((
+                doc.get_object_id("_id")?.to_hex(),
+                doc.get_str("user").ok().map(|user| user.to_string()),
+                doc.get_datetime("initial_time")?.to_chrono(),
+                doc.get_datetime("end_time")?.to_chrono(),
+            ));
+        }
+
+        Ok(find_overlapping_pairs(&tasks))
     }
+
+    /// Ends the task at `at` and creates a copy covering the rest of it.
+    pub async fn split_task(
+        &self,
+        workspace: &str,
+        lock_override: Option<&str>,
+        id: &str,
+        at: &str,
+    ) -> Result<TaskSplitResponse> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let at: DateTime<Utc> = at.parse().map_err(|_| InvalidDateError(at.to_owned()))?;
+        let mut task = self.get_task_document(workspace, oid).await?;
+        Self::ensure_task_unlocked(&task)?;
+
+        let initial_time = task.get_datetime("initial_time")?.to_chrono();
+        let end_time = task.get_datetime("end_time")?.to_chrono();
+        if at <= initial_time || at >= end_time {
+            return Err(InvalidDateError(at.to_rfc3339()));
+        }
+        let overridden_by = self
+            .check_period_lock(workspace, lock_override, &[initial_time, at])
+            .await?;
+
+        let user = task.get_str("user").ok().map(str::to_string);
+        let (initial_time, first_end, mut overlapping_tasks) = self
+            .resolve_overlaps(workspace, user.as_deref(), &[oid], initial_time, at)
+            .await?;
+        let (second_start, end_time, second_overlaps) = self
+            .resolve_overlaps(workspace, user.as_deref(), &[oid], at, end_time)
+            .await?;
+        overlapping_tasks.extend(second_overlaps);
+
+        task.remove("_id");
+        task.insert("initial_time", second_start);
+        task.insert("end_time", end_time);
+        task.insert("created_at", chrono::Utc::now());
+        task.insert("updated_at", chrono::Utc::now());
+
+        // Both halves are written or neither is
+        let collection = self.get_tasks_collection();
+        let mut session = self.client.start_session(None).await?;
+        session.start_transaction(None).await?;
+
+        let first = collection
+            .update_one_with_session(
+                doc! { "_id": oid },
+                doc! {
+                    "$set": {
+                        "initial_time": initial_time,
+                        "end_time": first_end,
+                        "updated_at": chrono::Utc::now(),
+                    }
+                },
+                None,
+                &mut session,
+            )
+            .await;
+        let second = match first {
+            Ok(_) => {
+                collection
+                    .insert_one_with_session(task, None, &mut session)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let second = match second {
+            Ok(second) => second,
+            Err(e) => {
+                session.abort_transaction().await?;
+                return Err(MongoQueryError(e));
+            }
+        };
+
+        session
+            .commit_transaction()
+            .await
+            .map_err(MongoQueryError)?;
+
+        if let Some(admin) = overridden_by {
+            self.record_lock_override(workspace, admin, "split", oid)
+                .await?;
+        }
+        let second = second.inserted_id.as_object_id().unwrap();
+        self.publish_change(workspace, WebhookEvent::TaskUpdated, oid)
+            .await;
+        self.publish_change(workspace, WebhookEvent::TaskCreated, second)
+            .await;
+
+        Ok(TaskSplitResponse {
+            first: oid.to_hex(),
+            second: second.to_hex(),
+            overlapping_tasks,
+        })
+    }
+
     pub async fn delete_all_tasks(&self) -> Result<()> {
         self.get_tasks_collection()
             .delete_many(doc! {}, None)
@@ -268,19 +725,102 @@ impl DB {
         Ok(())
     }
 
-    pub async fn delete_task(&self, id: &str) -> Result<()> {
+    pub async fn delete_task(
+        &self,
+        workspace: &str,
+        lock_override: Option<&str>,
+        id: &str,
+    ) -> Result<()> {
         let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let task = self.get_task_document(workspace, oid).await?;
+        Self::ensure_task_unlocked(&task)?;
+        let overridden_by = self
+            .check_period_lock(
+                workspace,
+                lock_override,
+                &[task.get_datetime("initial_time")?.to_chrono()],
+            )
+            .await?;
+
         let query = doc! {
             "_id": oid,
+            "workspace": workspace,
         };
-        self.get_tasks_collection()
+        let deleted = self
+            .get_tasks_collection()
             .delete_one(query, None)
             .await
             .map_err(MongoQueryError)?;
 
+        if let Some(admin) = overridden_by {
+            self.record_lock_override(workspace, admin, "delete", oid)
+                .await?;
+        }
+        if deleted.deleted_count > 0 {
+            self.publish_change(workspace, WebhookEvent::TaskDeleted, oid)
+                .await;
+        }
+
         Ok(())
     }
 
+    /// Time tracked per project, of every project or only of `projects`.
+    pub async fn get_tracked_time_by_project(
+        &self,
+        projects: Option<&[ObjectId]>,
+    ) -> Result<HashMap<ObjectId, ProjectTrackedTime>> {
+        let now = Utc::now();
+        let month_start: bson::DateTime = Utc
+            .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+            .unwrap()
+            .into();
+
+        let group = doc! {
+            "$group": {
+                "_id": "$project",
+                "total_ms": {
+                    "$sum": { "$subtract": ["$end_time", "$initial_time"] },
+                },
+                "month_ms": {
+                    "$sum": {
+                        "$cond": [
+                            { "$gte": ["$initial_time", month_start] },
+                            { "$subtract": ["$end_time", "$initial_time"] },
+                            0,
+                        ],
+                    },
+                },
+            },
+        };
+
+        let mut pipeline = vec![group];
+        if let Some(projects) = projects {
+            pipeline.insert(0, doc! { "$match": { "project": { "$in": projects } } });
+        }
+
+        let mut cursor = self
+            .get_tasks_collection()
+            .aggregate(pipeline, None)
+            .await?;
+
+        let mut tracked: HashMap<ObjectId, ProjectTrackedTime> = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            // Tasks without a project are grouped under a null id
+            if let Ok(project) = doc.get_object_id("_id") {
+                tracked.insert(
+                    project,
+                    ProjectTrackedTime {
+                        total_seconds: get_number(&doc, "total_ms") / 1000,
+                        month_seconds: get_number(&doc, "month_ms") / 1000,
+                    },
+                );
+            }
+        }
+
+        Ok(tracked)
+    }
+
     pub async fn create_many_tasks(&self, _entry: Vec<mongodb::bson::Document>) -> Result<()> {
         self.get_tasks_collection()
             .insert_many(_entry, None)
@@ -0,0 +1,632 @@
+use crate::models::timesheet::{
+    build_week, parse_week_start, plan_week, week_bounds, TimesheetChange, TimesheetComment,
+    TimesheetResponse, TimesheetStatus, TimesheetStatusRequest, WeekEntry, WeekQuery,
+    WeekTimesheet, WeekTimesheetRequest, WEEK_DAYS,
+};
+use crate::models::webhook::WebhookEvent;
+use crate::models::workspace::OverlapPolicy;
+use crate::{error::Error::*, Result};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use futures::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use mongodb::{ClientSession, Collection};
+use std::collections::HashSet;
+
+use super::tasks_db_impl::parse_time_zone;
+use super::DB;
+
+fn get_timesheet_status(doc: &Document) -> TimesheetStatus {
+    doc.get_str("status")
+        .ok()
+        .and_then(|status| status.parse().ok())
+        .unwrap_or_default()
+}
+
+fn get_comments(doc: &Document) -> Vec<TimesheetComment> {
+    doc.get_array("comments")
+        .map(|comments| {
+            comments
+                .iter()
+                .filter_map(|comment| comment.as_document())
+                .map(|comment| TimesheetComment {
+                    author: comment.get_str("author").ok().map(|a| a.to_string()),
+                    status: get_timesheet_status(comment),
+                    text: comment.get_str("text").unwrap_or_default().to_string(),
+                    created_at: comment
+                        .get_datetime("created_at")
+                        .map(|date| date.to_chrono().to_rfc3339_opts(SecondsFormat::Secs, true))
+                        .unwrap_or_default(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl DB {
+    fn get_timesheets_collection(&self) -> Collection<Document> {
+        self.database().collection("timesheets")
+    }
+
+    fn doc_to_timesheet(&self, doc: &Document) -> Result<TimesheetResponse> {
+        let id = doc.get_object_id("_id")?;
+        let created_at = doc.get_datetime("created_at")?;
+        let updated_at = doc.get_datetime("updated_at")?;
+
+        Ok(TimesheetResponse {
+            _id: id.to_hex(),
+            user: doc.get_str("user").ok().map(|user| user.to_string()),
+            start: doc.get_str("start")?.to_string(),
+            tz: doc.get_str("tz").ok().map(|tz| tz.to_string()),
+            status: get_timesheet_status(doc),
+            comments: get_comments(doc),
+            tasks: doc
+                .get_array("tasks")
+                .map(|tasks| {
+                    tasks
+                        .iter()
+                        .filter_map(|task| task.as_object_id())
+                        .map(|task| task.to_hex())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            submitted_at: doc
+                .get_datetime("submitted_at")
+                .ok()
+                .map(|date| date.to_chrono().to_rfc3339_opts(SecondsFormat::Secs, true)),
+            approved_by: doc
+                .get_str("approved_by")
+                .ok()
+                .map(|admin| admin.to_string()),
+            created_at: created_at
+                .to_chrono()
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            updated_at: updated_at
+                .to_chrono()
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        })
+    }
+
+    async fn find_week_timesheet(
+        &self,
+        workspace: &str,
+        user: Option<&str>,
+        start: &str,
+    ) -> Result<Option<Document>> {
+        self.get_timesheets_collection()
+            .find_one(
+                doc! { "workspace": workspace, "user": user, "start": start },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)
+    }
+
+    /// Tasks of `user` touching the week, with their project and activity names.
+    async fn get_week_entries(
+        &self,
+        workspace: &str,
+        start: chrono::NaiveDate,
+        tz: Tz,
+        user: Option<&str>,
+    ) -> Result<Vec<WeekEntry>> {
+        let bounds = week_bounds(start, tz);
+
+        let match_week = doc! {
+            "$match": {
+                "workspace": workspace,
+                "user": user,
+                "initial_time": { "$lt": bounds[WEEK_DAYS] },
+                "end_time": { "$gt": bounds[0] },
+            },
+        };
+        let lookup_projects = doc! {
+            "$lookup": {
+                "from": "projects",
+                "localField": "project",
+                "foreignField": "_id",
+                "as": "project_doc",
+            }
+        };
+        let lookup_activities = doc! {
+            "$lookup": {
+                "from": "activities",
+                "localField": "activity",
+                "foreignField": "_id",
+                "as": "activity_doc",
+            }
+        };
+        let project = doc! {
+            "$project": {
+                "_id": "$_id",
+                "project": "$project",
+                "activity": "$activity",
+                "initial_time": "$initial_time",
+                "end_time": "$end_time",
+                "project_name": { "$arrayElemAt": ["$project_doc.name", 0] },
+                "activity_name": { "$arrayElemAt": ["$activity_doc.name", 0] },
+            },
+        };
+
+        let pipeline = vec![match_week, lookup_projects, lookup_activities, project];
+        let mut cursor = self
+            .get_tasks_collection()
+            .aggregate(pipeline, None)
+            .await?;
+
+        let mut entries: Vec<WeekEntry> = vec![];
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            entries.push(WeekEntry {
+                id: Some(doc.get_object_id("_id")?),
+                project: doc.get_object_id("project").ok(),
+                activity: doc.get_object_id("activity").ok(),
+                project_name: doc.get_str("project_name").ok().map(|n| n.to_string()),
+                activity_name: doc.get_str("activity_name").ok().map(|n| n.to_string()),
+                start: doc.get_datetime("initial_time")?.to_chrono(),
+                end: doc.get_datetime("end_time")?.to_chrono(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    pub async fn get_week_timesheet(
+        &self,
+        workspace: &str,
+        filter: &WeekQuery,
+    ) -> Result<WeekTimesheet> {
+        let start = parse_week_start(&filter.start)?;
+        let tz = parse_time_zone(&filter.tz)?;
+
+        let entries = self
+            .get_week_entries(workspace, start, tz, filter.user.as_deref())
+            .await?;
+
+        let mut week = build_week(start, tz, filter.user.clone(), &entries);
+        if let Some(timesheet) = self
+            .find_week_timesheet(workspace, filter.user.as_deref(), &week.start)
+            .await?
+        {
+            week.status = get_timesheet_status(&timesheet);
+        }
+
+        Ok(week)
+    }
+
+    /// Turns the tasks of the week into the requested grid. Every write runs
+    /// in one transaction, so either the whole grid is saved or nothing is.
+    pub async fn update_week_timesheet(
+        &self,
+        workspace: &str,
+        _entry: &WeekTimesheetRequest,
+    ) -> Result<WeekTimesheet> {
+        let start = parse_week_start(&_entry.start)?;
+        let tz = parse_time_zone(&_entry.tz)?;
+
+        // Any approved week the grid touches, whatever day it starts on
+        let bounds = week_bounds(start, tz);
+        self.ensure_weeks_open(
+            workspace,
+            _entry.user.as_deref(),
+            bounds[0],
+            bounds[WEEK_DAYS],
+        )
+        .await?;
+
+        for row in &_entry.rows {
+            if let Some(activity) = row.activity {
+                self.ensure_activity_in_project(activity, row.project)
+                    .await?;
+            }
+        }
+
+        let entries = self
+            .get_week_entries(workspace, start, tz, _entry.user.as_deref())
+            .await?;
+        let changes = plan_week(start, tz, &entries, &_entry.rows)?;
+
+        // Tasks locked by an approved timesheet never change
+        let changed: Vec<ObjectId> = changes
+            .iter()
+            .filter_map(|change| match change {
+                TimesheetChange::Resize { id, .. } | TimesheetChange::Delete { id } => Some(*id),
+                TimesheetChange::Create { .. } => None,
+            })
+            .collect();
+        let locked = self
+            .get_tasks_collection()
+            .count_documents(
+                doc! { "_id": { "$in": &changed }, "locked_by": { "$type": "objectId" } },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+        if locked > 0 {
+            return Err(TimesheetLockedError);
+        }
+
+        // The grid has no admin override, locked periods are edited task by task
+        let times: Vec<chrono::DateTime<chrono::Utc>> = changes
+            .iter()
+            .filter_map(|change| match change {
+                TimesheetChange::Create { start, .. } => Some(*start),
+                TimesheetChange::Resize { id, .. } | TimesheetChange::Delete { id } => entries
+                    .iter()
+                    .find(|entry| entry.id == Some(*id))
+                    .map(|entry| entry.start),
+            })
+            .collect();
+        self.check_period_lock(workspace, None, &times).await?;
+
+        let mut projects: HashSet<ObjectId> = HashSet::new();
+        for change in &changes {
+            if let TimesheetChange::Create {
+                project: Some(project),
+                ..
+            } = change
+            {
+                if projects.insert(*project) {
+                    self.ensure_project_open(*project).await?;
+                }
+            }
+        }
+        projects.extend(entries.iter().filter_map(|entry| entry.project));
+
+        // The plan never overlaps the tasks of the week, but tasks written
+        // since it was read may. The cells ask for exact durations, so
+        // `auto-trim` rejects like `reject` does.
+        let deleted: Vec<ObjectId> = changes
+            .iter()
+            .filter_map(|change| match change {
+                TimesheetChange::Delete { id } => Some(*id),
+                _ => None,
+            })
+            .collect();
+        let mut overlapping_tasks: Vec<String> = vec![];
+        for change in &changes {
+            let (exclude, start, end) = match change {
+                TimesheetChange::Create { start, end, .. } => (deleted.clone(), *start, *end),
+                TimesheetChange::Resize { id, end } => {
+                    let start = match entries.iter().find(|entry| entry.id == Some(*id)) {
+                        Some(entry) => entry.start,
+                        None => continue,
+                    };
+                    let mut exclude = deleted.clone();
+                    exclude.push(*id);
+                    (exclude, start, *end)
+                }
+                TimesheetChange::Delete { .. } => continue,
+            };
+            let (ids, _) = self
+                .find_overlaps(workspace, _entry.user.as_deref(), &exclude, start, end)
+                .await?;
+            overlapping_tasks.extend(ids);
+        }
+        if !overlapping_tasks.is_empty()
+            && self.get_workspace_settings(workspace).await?.overlap_policy != OverlapPolicy::Warn
+        {
+            return Err(OverlappingTaskError(overlapping_tasks));
+        }
+
+        let mut session = self.client.start_session(None).await?;
+        session.start_transaction(None).await?;
+
+        let collection = self.get_tasks_collection();
+        let mut events: Vec<(WebhookEvent, ObjectId)> = vec![];
+        for change in changes {
+            let result = match change {
+                TimesheetChange::Create {
+                    name,
+                    project,
+                    activity,
+                    start,
+                    end,
+                } => collection
+                    .insert_one_with_session(
+                        doc! {
+                            "workspace": workspace,
+                            "name": name,
+                            "user": _entry.user.clone(),
+                            "initial_time": start,
+                            "end_time": end,
+                            "project": project,
+                            "activity": activity,
+                            "description": None::<String>,
+                            "tags": [],
+                            "created_at": chrono::Utc::now(),
+                            "updated_at": chrono::Utc::now(),
+                        },
+                        None,
+                        &mut session,
+                    )
+                    .await
+                    .map(|task| {
+                        let id = task.inserted_id.as_object_id().unwrap();
+                        (WebhookEvent::TaskCreated, id)
+                    })
+                    .map_err(MongoQueryError),
+                // Tasks locked since they were read are not matched
+                TimesheetChange::Resize { id, end } => collection
+                    .update_one_with_session(
+                        doc! { "_id": id, "locked_by": { "$exists": false } },
+                        doc! { "$set": { "end_time": end, "updated_at": chrono::Utc::now() } },
+                        None,
+                        &mut session,
+                    )
+                    .await
+                    .map_err(MongoQueryError)
+                    .and_then(|updated| match updated.matched_count {
+                        0 => Err(TimesheetLockedError),
+                        _ => Ok((WebhookEvent::TaskUpdated, id)),
+                    }),
+                TimesheetChange::Delete { id } => collection
+                    .delete_one_with_session(
+                        doc! { "_id": id, "locked_by": { "$exists": false } },
+                        None,
+                        &mut session,
+                    )
+                    .await
+                    .map_err(MongoQueryError)
+                    .and_then(|deleted| match deleted.deleted_count {
+                        0 => Err(TimesheetLockedError),
+                        _ => Ok((WebhookEvent::TaskDeleted, id)),
+                    }),
+            };
+
+            match result {
+                Ok(event) => events.push(event),
+                Err(e) => {
+                    session.abort_transaction().await?;
+                    return Err(e);
+                }
+            }
+        }
+
+        session
+            .commit_transaction()
+            .await
+            .map_err(MongoQueryError)?;
+
+        for (event, id) in events {
+            self.publish_change(workspace, event, id).await;
+        }
+        for project in projects {
+            self.spawn_budget_alerts(project);
+        }
+
+        let mut week = self
+            .get_week_timesheet(
+                workspace,
+                &WeekQuery {
+                    start: _entry.start.clone(),
+                    user: _entry.user.clone(),
+                    tz: _entry.tz.clone(),
+                },
+            )
+            .await?;
+        week.overlapping_tasks = overlapping_tasks;
+
+        Ok(week)
+    }
+
+    /// Moves the week of a user to a new status, creating the timesheet as a
+    /// draft the first time. Approving locks every task of the week, reopening
+    /// an approved timesheet unlocks them again.
+    pub async fn update_timesheet_status(
+        &self,
+        workspace: &str,
+        _entry: &TimesheetStatusRequest,
+    ) -> Result<TimesheetResponse> {
+        let start = parse_week_start(&_entry.start)?
+            .format("%Y-%m-%d")
+            .to_string();
+        let tz = parse_time_zone(&_entry.tz)?;
+        let user = _entry.user.as_deref();
+
+        let current = match self.find_week_timesheet(workspace, user, &start).await? {
+            Some(doc) => get_timesheet_status(&doc),
+            None => TimesheetStatus::default(),
+        };
+        if !current.can_transition_to(_entry.status) {
+            return Err(InvalidTimesheetTransitionError(current, _entry.status));
+        }
+        if _entry.status == TimesheetStatus::Rejected && _entry.comment.is_none() {
+            return Err(InvalidTimesheetError(
+                "rejections need a comment".to_string(),
+            ));
+        }
+        if current.is_review(_entry.status) {
+            let settings = self.get_workspace_settings(workspace).await?;
+            match _entry.author.as_deref() {
+                Some(author) if settings.is_admin(author) => {}
+                author => return Err(NotAnApproverError(author.map(str::to_string))),
+            }
+        }
+        let approved_by = match _entry.status {
+            TimesheetStatus::Approved => _entry.author.clone(),
+            _ => None,
+        };
+
+        let now = chrono::Utc::now();
+        let mut update = doc! {
+            "$set": {
+                "status": _entry.status.as_str(),
+                "tz": _entry.tz.clone(),
+                "approved_by": approved_by,
+                "updated_at": now,
+            },
+            "$setOnInsert": {
+                "created_at": now,
+            },
+        };
+        if _entry.status == TimesheetStatus::Submitted {
+            update.get_document_mut("$set")?.insert("submitted_at", now);
+        }
+        if let Some(comment) = &_entry.comment {
+            update.insert(
+                "$push",
+                doc! {
+                    "comments": {
+                        "author": _entry.author.clone(),
+                        "status": _entry.status.as_str(),
+                        "text": comment,
+                        "created_at": now,
+                    },
+                },
+            );
+        }
+
+        // Tasks are read first, only the writes need the transaction
+        let tasks = match _entry.status {
+            TimesheetStatus::Approved => Some(
+                self.get_week_entries(workspace, parse_week_start(&start)?, tz, user)
+                    .await?
+                    .into_iter()
+                    .filter_map(|entry| entry.id)
+                    .collect(),
+            ),
+            _ => None,
+        };
+
+        let mut session = self.client.start_session(None).await?;
+        session.start_transaction(None).await?;
+
+        let filter = doc! { "workspace": workspace, "user": user, "start": &start };
+        let timesheet = match self
+            .write_timesheet_status(&mut session, filter, update, tasks)
+            .await
+        {
+            Ok(timesheet) => timesheet,
+            Err(e) => {
+                session.abort_transaction().await?;
+                return Err(e);
+            }
+        };
+
+        session
+            .commit_transaction()
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.doc_to_timesheet(&timesheet)
+    }
+
+    /// Upserts the timesheet, then locks `tasks` to it, or unlocks every task
+    /// it locked when there are none.
+    async fn write_timesheet_status(
+        &self,
+        session: &mut ClientSession,
+        filter: Document,
+        update: Document,
+        tasks: Option<Vec<ObjectId>>,
+    ) -> Result<Document> {
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let timesheet = self
+            .get_timesheets_collection()
+            .find_one_and_update_with_session(filter, update, options, session)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)?;
+        let id = timesheet.get_object_id("_id")?;
+
+        let (task_filter, task_update) = match &tasks {
+            Some(tasks) => (
+                doc! { "_id": { "$in": tasks } },
+                doc! { "$set": { "locked_by": id } },
+            ),
+            None => (
+                doc! { "locked_by": id },
+                doc! { "$unset": { "locked_by": "" } },
+            ),
+        };
+        self.get_tasks_collection()
+            .update_many_with_session(task_filter, task_update, None, session)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.get_timesheets_collection()
+            .find_one_and_update_with_session(
+                doc! { "_id": id },
+                doc! { "$set": { "tasks": tasks.unwrap_or_default() } },
+                FindOneAndUpdateOptions::builder()
+                    .return_document(ReturnDocument::After)
+                    .build(),
+                session,
+            )
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(ObjNotFound)
+    }
+
+    /// Writing tasks of `user` between `initial_time` and `end_time` is refused
+    /// once the timesheet of a week they touch was approved.
+    pub(super) async fn ensure_weeks_open(
+        &self,
+        workspace: &str,
+        user: Option<&str>,
+        initial_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<()> {
+        // Weeks start on any day and in any time zone, so every week starting
+        // up to 8 days earlier is a candidate
+        let from = (initial_time - Duration::days(WEEK_DAYS as i64 + 1))
+            .format("%Y-%m-%d")
+            .to_string();
+        let to = (end_time + Duration::days(1))
+            .format("%Y-%m-%d")
+            .to_string();
+        let query = doc! {
+            "workspace": workspace,
+            "user": user,
+            "status": TimesheetStatus::Approved.as_str(),
+            "start": { "$gte": from, "$lte": to },
+        };
+        let mut cursor = self
+            .get_timesheets_collection()
+            .find(query, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        while let Some(timesheet) = cursor.next().await {
+            let timesheet = timesheet?;
+            let start = parse_week_start(timesheet.get_str("start")?)?;
+            let tz = parse_time_zone(&timesheet.get_str("tz").ok().map(str::to_string))?;
+            let bounds = week_bounds(start, tz);
+            if initial_time < bounds[WEEK_DAYS] && end_time > bounds[0] {
+                return Err(TimesheetLockedError);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Submitted timesheets waiting for a manager, oldest submission first.
+    pub async fn get_pending_timesheets(&self, workspace: &str) -> Result<Vec<TimesheetResponse>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "submitted_at": 1 })
+            .build();
+        let mut cursor = self
+            .get_timesheets_collection()
+            .find(
+                doc! {
+                    "workspace": workspace,
+                    "status": TimesheetStatus::Submitted.as_str(),
+                },
+                options,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut result: Vec<TimesheetResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            result.push(self.doc_to_timesheet(&doc?)?);
+        }
+
+        Ok(result)
+    }
+}
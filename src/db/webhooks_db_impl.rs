@@ -0,0 +1,442 @@
+use crate::events::ChangeEvent;
+use crate::models::webhook::{
+    retry_delay, DeliveryStatus, WebhookDeliveryResponse, WebhookEvent, WebhookRequest,
+    WebhookResponse, MAX_DELIVERY_ATTEMPTS,
+};
+use crate::{error::Error::*, Result};
+use chrono::{SecondsFormat, Utc};
+use futures::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, InsertManyOptions, ReturnDocument};
+use mongodb::Collection;
+
+use super::{is_duplicate_key, DB};
+
+// A claimed delivery is retried after this long if the worker dies mid-send
+const DELIVERY_LEASE_SECONDS: i64 = 60;
+
+fn format_date(doc: &Document, key: &str) -> Option<String> {
+    doc.get_datetime(key)
+        .ok()
+        .map(|date| date.to_chrono().to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Identifies a write the same way whether a handler or the change stream
+/// publishes it, so a write published by both is only sent once: the event,
+/// the document and the `updated_at` the write left on it.
+fn change_key(event: WebhookEvent, id: ObjectId, doc: Option<&Document>) -> String {
+    let version = doc
+        .and_then(|doc| doc.get_datetime("updated_at").ok())
+        .map(|updated_at| updated_at.timestamp_millis())
+        .unwrap_or_default();
+
+    format!("{}:{}:{}", event.as_str(), id.to_hex(), version)
+}
+
+fn get_events(doc: &Document) -> Vec<WebhookEvent> {
+    doc.get_array("events")
+        .map(|events| {
+            events
+                .iter()
+                .filter_map(|event| event.as_str())
+                .filter_map(|event| event.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl DB {
+    fn get_webhooks_collection(&self) -> Collection<Document> {
+        self.database().collection("webhooks")
+    }
+
+    fn get_webhook_deliveries_collection(&self) -> Collection<Document> {
+        self.database().collection("webhook_deliveries")
+    }
+
+    fn doc_to_webhook(&self, doc: &Document) -> Result<WebhookResponse> {
+        Ok(WebhookResponse {
+            _id: doc.get_object_id("_id")?.to_hex(),
+            url: doc.get_str("url")?.to_string(),
+            events: get_events(doc),
+            created_at: format_date(doc, "created_at").unwrap_or_default(),
+        })
+    }
+
+    fn doc_to_delivery(&self, doc: &Document) -> Result<WebhookDeliveryResponse> {
+        Ok(WebhookDeliveryResponse {
+            _id: doc.get_object_id("_id")?.to_hex(),
+            webhook: doc.get_object_id("webhook")?.to_hex(),
+            event: doc.get_str("event")?.to_string(),
+            payload: doc.get_str("payload")?.to_string(),
+            status: doc
+                .get_str("status")
+                .ok()
+                .and_then(|status| status.parse().ok())
+                .unwrap_or_default(),
+            attempts: doc.get_i32("attempts").unwrap_or(0),
+            last_error: doc.get_str("last_error").ok().map(|e| e.to_string()),
+            response_status: doc.get_i32("response_status").ok(),
+            next_attempt_at: format_date(doc, "next_attempt_at"),
+            delivered_at: format_date(doc, "delivered_at"),
+            created_at: format_date(doc, "created_at").unwrap_or_default(),
+        })
+    }
+
+    pub async fn create_webhook(&self, workspace: &str, _entry: &WebhookRequest) -> Result<String> {
+        let url = reqwest::Url::parse(&_entry.url).map_err(|_| InvalidWebhookError)?;
+        if !matches!(url.scheme(), "http" | "https") || _entry.events.is_empty() {
+            return Err(InvalidWebhookError);
+        }
+
+        let events: Vec<&str> = _entry.events.iter().map(|event| event.as_str()).collect();
+        let webhook = self
+            .get_webhooks_collection()
+            .insert_one(
+                doc! {
+                    "workspace": workspace,
+                    "url": _entry.url.clone(),
+                    "secret": _entry.secret.clone(),
+                    "events": events,
+                    "created_at": chrono::Utc::now(),
+                    "updated_at": chrono::Utc::now(),
+                },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(webhook.inserted_id.as_object_id().unwrap().to_hex())
+    }
+
+    pub async fn get_webhooks(&self, workspace: &str) -> Result<Vec<WebhookResponse>> {
+        let mut cursor = self
+            .get_webhooks_collection()
+            .find(doc! { "workspace": workspace }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut result: Vec<WebhookResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            result.push(self.doc_to_webhook(&doc?)?);
+        }
+
+        Ok(result)
+    }
+
+    /// Pending deliveries of a removed webhook are dropped with it.
+    pub async fn delete_webhook(&self, workspace: &str, id: &str) -> Result<()> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let deleted = self
+            .get_webhooks_collection()
+            .delete_one(doc! { "_id": oid, "workspace": workspace }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if deleted.deleted_count == 0 {
+            return Err(ObjNotFound);
+        }
+
+        self.get_webhook_deliveries_collection()
+            .delete_many(
+                doc! { "webhook": oid, "status": DeliveryStatus::Pending.as_str() },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    /// Delivery log of a webhook, most recent first.
+    pub async fn get_webhook_deliveries(
+        &self,
+        workspace: &str,
+        id: &str,
+    ) -> Result<Vec<WebhookDeliveryResponse>> {
+        let oid = ObjectId::parse_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .limit(100)
+            .build();
+        let mut cursor = self
+            .get_webhook_deliveries_collection()
+            .find(doc! { "webhook": oid, "workspace": workspace }, options)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut result: Vec<WebhookDeliveryResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            result.push(self.doc_to_delivery(&doc?)?);
+        }
+
+        Ok(result)
+    }
+
+    /// Publishes a change of a task, project or client written by this
+    /// process. Skipped while the change stream watcher publishes every write.
+    pub(super) async fn publish_change(&self, workspace: &str, event: WebhookEvent, id: ObjectId) {
+        use WebhookEvent::*;
+
+        if self.events.is_streaming() {
+            return;
+        }
+
+        let doc = if event.is_delete_event() {
+            None
+        } else {
+            let collection = match event {
+                TaskCreated | TaskUpdated => self.get_tasks_collection(),
+                ProjectCreated | ProjectUpdated => self.get_projects_collection(),
+                _ => self.get_clients_collection(),
+            };
+            match collection.find_one(doc! { "_id": id }, None).await {
+                Ok(Some(doc)) => Some(doc),
+                // Deleted since, its delete follows
+                Ok(None) => return,
+                Err(e) => {
+                    eprintln!("could not load {} for {}: {:?}", id, event.as_str(), e);
+                    return;
+                }
+            }
+        };
+
+        self.publish_document(workspace, event, id, doc.as_ref())
+            .await;
+    }
+
+    /// Publishes `event` of the document `id`, as stored after the change.
+    /// Only deletes come without a document.
+    pub(super) async fn publish_document(
+        &self,
+        workspace: &str,
+        event: WebhookEvent,
+        id: ObjectId,
+        doc: Option<&Document>,
+    ) {
+        let data = match doc {
+            Some(doc) => self.document_data(event, doc),
+            None => Ok(serde_json::json!({ "_id": id.to_hex() })),
+        };
+
+        match data {
+            Ok(data) => {
+                let change = change_key(event, id, doc);
+                self.publish_event(workspace, event, data, &change).await
+            }
+            Err(e) => eprintln!("could not read {} for {}: {:?}", id, event.as_str(), e),
+        }
+    }
+
+    /// Payload of `event` built from the stored document, without reading it
+    /// again.
+    fn document_data(&self, event: WebhookEvent, doc: &Document) -> Result<serde_json::Value> {
+        use WebhookEvent::*;
+
+        let data = match event {
+            TaskCreated | TaskUpdated => serde_json::json!(self.doc_to_task(doc)?),
+            ProjectCreated | ProjectUpdated => serde_json::json!(self.doc_to_project(doc)?),
+            ClientCreated | ClientUpdated => serde_json::json!(self.doc_to_client(doc)?),
+            TaskDeleted | ProjectDeleted | ClientDeleted => {
+                serde_json::json!({ "_id": doc.get_object_id("_id")?.to_hex() })
+            }
+        };
+
+        Ok(data)
+    }
+
+    /// Pushes the change to live clients and queues a delivery for every
+    /// webhook of the workspace subscribed to `event`. The change is already
+    /// written at this point, so failures are only logged.
+    pub(super) async fn publish_event(
+        &self,
+        workspace: &str,
+        event: WebhookEvent,
+        data: serde_json::Value,
+        change_id: &str,
+    ) {
+        let change = ChangeEvent {
+            event,
+            workspace: workspace.to_string(),
+            occurred_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            data,
+        };
+
+        if let Err(e) = self.queue_deliveries(&change, change_id).await {
+            eprintln!("could not queue {} webhooks: {:?}", event.as_str(), e);
+        }
+        self.events.publish(change_id, change);
+    }
+
+    async fn queue_deliveries(&self, change: &ChangeEvent, change_id: &str) -> Result<()> {
+        let workspace = change.workspace.as_str();
+        let event = change.event;
+        let mut cursor = self
+            .get_webhooks_collection()
+            .find(
+                doc! { "workspace": workspace, "events": event.as_str() },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        let now = chrono::Utc::now();
+        let payload = serde_json::to_string(change).unwrap();
+
+        let mut deliveries: Vec<Document> = vec![];
+        while let Some(webhook) = cursor.next().await {
+            deliveries.push(doc! {
+                "workspace": workspace,
+                "webhook": webhook?.get_object_id("_id")?,
+                "change": change_id,
+                "event": event.as_str(),
+                "payload": &payload,
+                "status": DeliveryStatus::Pending.as_str(),
+                "attempts": 0,
+                "next_attempt_at": now,
+                "created_at": now,
+            });
+        }
+
+        if !deliveries.is_empty() {
+            // Unordered, so deliveries already queued for this change are
+            // skipped without stopping the others
+            let options = InsertManyOptions::builder().ordered(false).build();
+            let inserted = self
+                .get_webhook_deliveries_collection()
+                .insert_many(deliveries, options)
+                .await;
+            if let Err(e) = inserted {
+                if !is_duplicate_key(&e) {
+                    return Err(MongoQueryError(e));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends every delivery that is due. Each one is claimed first by pushing
+    /// its next attempt forward, so concurrent workers never send it twice.
+    pub async fn deliver_pending_webhooks(&self) -> Result<()> {
+        loop {
+            let now = Utc::now();
+            let claim = doc! {
+                "$set": {
+                    "next_attempt_at": now + chrono::Duration::seconds(DELIVERY_LEASE_SECONDS),
+                },
+            };
+            let delivery = self
+                .get_webhook_deliveries_collection()
+                .find_one_and_update(
+                    doc! {
+                        "status": DeliveryStatus::Pending.as_str(),
+                        "next_attempt_at": { "$lte": now },
+                    },
+                    claim,
+                    FindOneAndUpdateOptions::builder()
+                        .sort(doc! { "next_attempt_at": 1 })
+                        .return_document(ReturnDocument::After)
+                        .build(),
+                )
+                .await
+                .map_err(MongoQueryError)?;
+
+            match delivery {
+                Some(delivery) => self.attempt_delivery(&delivery).await?,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    async fn attempt_delivery(&self, delivery: &Document) -> Result<()> {
+        let id = delivery.get_object_id("_id")?;
+        let webhook = self
+            .get_webhooks_collection()
+            .find_one(doc! { "_id": delivery.get_object_id("webhook")? }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let webhook = match webhook {
+            Some(webhook) => webhook,
+            None => {
+                self.get_webhook_deliveries_collection()
+                    .update_one(
+                        doc! { "_id": id },
+                        doc! { "$set": {
+                            "status": DeliveryStatus::Failed.as_str(),
+                            "last_error": "webhook was removed",
+                        } },
+                        None,
+                    )
+                    .await
+                    .map_err(MongoQueryError)?;
+                return Ok(());
+            }
+        };
+
+        let attempt = self
+            .notifier
+            .send_webhook(
+                webhook.get_str("url")?,
+                webhook.get_str("secret")?,
+                delivery.get_str("event")?,
+                &id.to_hex(),
+                delivery.get_str("payload")?,
+            )
+            .await;
+        let attempts = delivery.get_i32("attempts").unwrap_or(0) + 1;
+        let now = Utc::now();
+
+        let mut update = doc! {
+            "attempts": attempts,
+            "response_status": attempt.response_status.map(i32::from),
+            "last_error": attempt.error.clone(),
+        };
+        match attempt.error {
+            None => {
+                update.insert("status", DeliveryStatus::Delivered.as_str());
+                update.insert("delivered_at", now);
+            }
+            Some(_) if attempts >= MAX_DELIVERY_ATTEMPTS => {
+                update.insert("status", DeliveryStatus::Failed.as_str());
+            }
+            Some(_) => {
+                let delay = chrono::Duration::from_std(retry_delay(attempts)).unwrap();
+                update.insert("next_attempt_at", now + delay);
+            }
+        }
+
+        self.get_webhook_deliveries_collection()
+            .update_one(doc! { "_id": id }, doc! { "$set": update }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_are_keyed_by_the_write_they_left() {
+        let id = ObjectId::new();
+        let written = mongodb::bson::DateTime::from_millis(1_634_547_600_000);
+        let task = doc! { "_id": id, "updated_at": written };
+        let key = change_key(WebhookEvent::TaskUpdated, id, Some(&task));
+
+        assert_eq!(key, change_key(WebhookEvent::TaskUpdated, id, Some(&task)));
+        let edited = doc! { "_id": id, "updated_at": mongodb::bson::DateTime::from_millis(1_634_547_600_001) };
+        assert_ne!(
+            key,
+            change_key(WebhookEvent::TaskUpdated, id, Some(&edited))
+        );
+        assert_ne!(key, change_key(WebhookEvent::TaskCreated, id, Some(&task)));
+        assert_eq!(
+            change_key(WebhookEvent::TaskDeleted, id, None),
+            format!("task.deleted:{}:0", id.to_hex())
+        );
+    }
+}
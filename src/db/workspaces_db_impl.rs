@@ -0,0 +1,175 @@
+use crate::models::workspace::{LockOverrideResponse, WorkspaceSettings};
+use crate::{error::Error::*, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::StreamExt;
+use mongodb::bson::{self, doc, oid::ObjectId, Document};
+use mongodb::options::{FindOptions, ReplaceOptions};
+use mongodb::Collection;
+
+use super::DB;
+
+impl DB {
+    fn get_workspaces_collection(&self) -> Collection<Document> {
+        self.database().collection("workspaces")
+    }
+
+    fn get_lock_overrides_collection(&self) -> Collection<Document> {
+        self.database().collection("lock_overrides")
+    }
+
+    /// Workspaces without a stored document use the default settings.
+    pub async fn get_workspace_settings(&self, workspace: &str) -> Result<WorkspaceSettings> {
+        let document = self
+            .get_workspaces_collection()
+            .find_one(doc! { "_id": workspace }, None)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let settings = match document {
+            Some(document) => bson::from_document(document)?,
+            None => WorkspaceSettings::default(),
+        };
+
+        Ok(settings)
+    }
+
+    /// Changing the lock date or the admins needs an admin in
+    /// `x-lock-override`, and is recorded with the lock overrides.
+    pub async fn update_workspace_settings(
+        &self,
+        workspace: &str,
+        lock_override: Option<&str>,
+        settings: &WorkspaceSettings,
+    ) -> Result<()> {
+        settings.locked_until()?;
+
+        let current = self.get_workspace_settings(workspace).await?;
+        let actions = current.guarded_changes(settings);
+        let admin = match actions.is_empty() {
+            true => None,
+            false => Some(current.change_admin(settings, lock_override)?),
+        };
+
+        let mut document = bson::to_document(settings)?;
+        document.insert("_id", workspace);
+
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.get_workspaces_collection()
+            .replace_one(doc! { "_id": workspace }, document, options)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if let Some(admin) = admin {
+            for action in actions {
+                self.get_lock_overrides_collection()
+                    .insert_one(
+                        doc! {
+                            "workspace": workspace,
+                            "action": action,
+                            "admin": admin,
+                            "lock_date": settings.lock_date.clone(),
+                            "admins": &settings.admins,
+                            "created_at": chrono::Utc::now(),
+                        },
+                        None,
+                    )
+                    .await
+                    .map_err(MongoQueryError)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rejects task writes touching times before the workspace lock date.
+    /// One of the workspace `admins` can still go through by sending their
+    /// name in `x-lock-override`, in which case the admin is returned so the
+    /// write gets recorded.
+    pub(super) async fn check_period_lock<'a>(
+        &self,
+        workspace: &str,
+        lock_override: Option<&'a str>,
+        times: &[DateTime<Utc>],
+    ) -> Result<Option<&'a str>> {
+        let settings = self.get_workspace_settings(workspace).await?;
+        let locked_until = match settings.locked_until()? {
+            Some(locked_until) => locked_until,
+            None => return Ok(None),
+        };
+
+        if times.iter().all(|time| *time >= locked_until) {
+            return Ok(None);
+        }
+
+        match lock_override {
+            Some(admin) if settings.is_admin(admin) => Ok(Some(admin)),
+            Some(admin) => Err(UnknownAdminError(admin.to_string())),
+            None => Err(PeriodLockedError(settings.lock_date.unwrap_or_default())),
+        }
+    }
+
+    pub(super) async fn record_lock_override(
+        &self,
+        workspace: &str,
+        admin: &str,
+        action: &str,
+        task: ObjectId,
+    ) -> Result<()> {
+        let lock_date = self.get_workspace_settings(workspace).await?.lock_date;
+
+        self.get_lock_overrides_collection()
+            .insert_one(
+                doc! {
+                    "workspace": workspace,
+                    "task": task,
+                    "action": action,
+                    "admin": admin,
+                    "lock_date": lock_date,
+                    "created_at": chrono::Utc::now(),
+                },
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    /// Every write that went through a locked period and every change of the
+    /// lock date or admins, most recent first.
+    pub async fn get_lock_overrides(&self, workspace: &str) -> Result<Vec<LockOverrideResponse>> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        let mut cursor = self
+            .get_lock_overrides_collection()
+            .find(doc! { "workspace": workspace }, options)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut result: Vec<LockOverrideResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc?;
+            result.push(LockOverrideResponse {
+                _id: doc.get_object_id("_id")?.to_hex(),
+                task: doc.get_object_id("task").ok().map(|task| task.to_hex()),
+                action: doc.get_str("action")?.to_string(),
+                admin: doc.get_str("admin")?.to_string(),
+                lock_date: doc.get_str("lock_date").unwrap_or_default().to_string(),
+                admins: doc.get_array("admins").ok().map(|admins| {
+                    admins
+                        .iter()
+                        .filter_map(|admin| admin.as_str())
+                        .map(str::to_string)
+                        .collect()
+                }),
+                created_at: doc
+                    .get_datetime("created_at")?
+                    .to_chrono()
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+            });
+        }
+
+        Ok(result)
+    }
+}
@@ -1,9 +1,13 @@
+use crate::models::project::ProjectStatus;
+use crate::models::timesheet::TimesheetStatus;
 use mongodb::bson;
 use serde::Serialize;
 use std::convert::Infallible;
 use thiserror::Error;
+use utoipa::ToSchema;
 use warp::{cors::CorsForbidden, http::StatusCode, reply, Rejection, Reply};
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("mongodb error: {0}")]
@@ -12,14 +16,68 @@ pub enum Error {
     MongoQueryError(mongodb::error::Error),
     #[error("could not access field in document: {0}")]
     MongoDataError(#[from] bson::document::ValueAccessError),
+    #[error("could not serialize to bson: {0}")]
+    BsonSerializeError(#[from] bson::ser::Error),
+    #[error("could not deserialize from bson: {0}")]
+    BsonDeserializeError(#[from] bson::de::Error),
     #[error("invalid id used: {0}")]
     InvalidIDError(String),
     #[error("Object Not Found")]
     ObjNotFound,
+    #[error("project has no estimate")]
+    MissingEstimateError,
+    #[error("invalid estimate: {0}")]
+    InvalidEstimateError(String),
+    #[error("project status cannot change from {0:?} to {1:?}")]
+    InvalidStatusTransitionError(ProjectStatus, ProjectStatus),
+    #[error("archived projects do not accept new tasks")]
+    ArchivedProjectError,
+    #[error("projects of archived clients do not accept new tasks")]
+    ArchivedClientError,
+    #[error("task references a tag that does not exist")]
+    UnknownTagError,
+    #[error("name is already used")]
+    DuplicateNameError,
+    #[error("activity does not belong to the task project")]
+    UnknownActivityError,
+    #[error("parent project does not exist, belongs to another client, or is the project itself or one of its subprojects")]
+    InvalidParentProjectError,
+    #[error("task overlaps existing tasks: {0:?}")]
+    OverlappingTaskError(Vec<String>),
+    #[error("invalid date used: {0}")]
+    InvalidDateError(String),
+    #[error("invalid time zone used: {0}")]
+    InvalidTimeZoneError(String),
+    #[error("invalid timesheet: {0}")]
+    InvalidTimesheetError(String),
+    #[error("timesheet status cannot change from {0:?} to {1:?}")]
+    InvalidTimesheetTransitionError(TimesheetStatus, TimesheetStatus),
+    #[error("task belongs to an approved timesheet")]
+    TimesheetLockedError,
+    #[error("tasks starting before {0} are locked")]
+    PeriodLockedError(String),
+    #[error("{0} is not an admin of the workspace")]
+    UnknownAdminError(String),
+    #[error("changing the lock date or admins needs an admin")]
+    AdminRequiredError,
+    #[error("{0:?} cannot review timesheets")]
+    NotAnApproverError(Option<String>),
+    #[error("webhook needs an http(s) url and at least one event")]
+    InvalidWebhookError,
+    #[error("invalid client: {0}")]
+    InvalidClientError(String),
+    #[error("invalid seed options: {0}")]
+    InvalidSeedError(String),
+    #[error("invalid import: {0}")]
+    InvalidImportError(String),
+    #[error("could not read or write file: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("invalid json: {0}")]
+    JsonError(#[from] serde_json::Error),
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
     message: String,
 }
 
@@ -32,29 +90,127 @@ pub async fn handle_rejection(err: Rejection) -> std::result::Result<Box<dyn Rep
     if err.is_not_found() {
         code = StatusCode::NOT_FOUND;
         message = "Not Found";
-    } else if let Some(_) = err.find::<warp::filters::body::BodyDeserializeError>() {
+    } else if err
+        .find::<warp::filters::body::BodyDeserializeError>()
+        .is_some()
+    {
         code = StatusCode::BAD_REQUEST;
         message = "Invalid Body";
+    } else if err.find::<warp::reject::InvalidQuery>().is_some() {
+        code = StatusCode::BAD_REQUEST;
+        message = "Invalid Query";
     } else if let Some(e) = err.find::<Error>() {
         match e {
             Error::ObjNotFound => {
                 code = StatusCode::NOT_FOUND;
                 message = "Not Found";
             }
+            Error::MissingEstimateError => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Project has no estimate";
+            }
+            Error::InvalidEstimateError(_) => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Invalid estimate";
+            }
+            Error::InvalidStatusTransitionError(_, _) => {
+                code = StatusCode::CONFLICT;
+                message = "Invalid project status transition";
+            }
+            Error::ArchivedProjectError => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Project is archived";
+            }
+            Error::ArchivedClientError => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Client is archived";
+            }
+            Error::UnknownTagError => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Unknown tag";
+            }
+            Error::DuplicateNameError => {
+                code = StatusCode::CONFLICT;
+                message = "Name already in use";
+            }
+            Error::UnknownActivityError => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Unknown activity for project";
+            }
+            Error::InvalidParentProjectError => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Invalid parent project";
+            }
+            Error::OverlappingTaskError(_) => {
+                code = StatusCode::CONFLICT;
+                message = "Task overlaps existing tasks";
+            }
+            Error::InvalidIDError(_) => {
+                code = StatusCode::BAD_REQUEST;
+                message = "Invalid id";
+            }
+            Error::InvalidDateError(_) => {
+                code = StatusCode::BAD_REQUEST;
+                message = "Invalid date";
+            }
+            Error::InvalidTimeZoneError(_) => {
+                code = StatusCode::BAD_REQUEST;
+                message = "Invalid time zone";
+            }
+            Error::InvalidTimesheetError(_) => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Invalid timesheet";
+            }
+            Error::InvalidTimesheetTransitionError(_, _) => {
+                code = StatusCode::CONFLICT;
+                message = "Invalid timesheet status transition";
+            }
+            Error::TimesheetLockedError => {
+                code = StatusCode::CONFLICT;
+                message = "Task is locked by an approved timesheet";
+            }
+            Error::PeriodLockedError(_) => {
+                code = StatusCode::CONFLICT;
+                message = "Task falls in a locked period";
+            }
+            Error::UnknownAdminError(_) => {
+                code = StatusCode::FORBIDDEN;
+                message = "Lock override by someone who is not a workspace admin";
+            }
+            Error::AdminRequiredError => {
+                code = StatusCode::FORBIDDEN;
+                message = "Changing the lock date or admins needs a workspace admin";
+            }
+            Error::NotAnApproverError(_) => {
+                code = StatusCode::FORBIDDEN;
+                message = "Only workspace admins approve, reject or reopen timesheets";
+            }
+            Error::InvalidWebhookError => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Invalid webhook";
+            }
+            Error::InvalidClientError(_) => {
+                code = StatusCode::BAD_REQUEST;
+                message = "Invalid client: check the currency, contact emails and hourly rate";
+            }
+            Error::InvalidSeedError(_) => {
+                code = StatusCode::UNPROCESSABLE_ENTITY;
+                message = "Invalid seed options";
+            }
             _ => {
                 eprintln!("unhandled application error: {:?}", err);
                 code = StatusCode::INTERNAL_SERVER_ERROR;
                 message = "Internal Server Error";
             }
         }
-    } else if let Some(_) = err.find::<CorsForbidden>() {
+    } else if err.find::<CorsForbidden>().is_some() {
         code = StatusCode::FORBIDDEN;
         message = "CORS request forbidden: header not allowed";
         // Ok(warp::reply::with_status(
         //     error.to_string(),
         //     StatusCode::FORBIDDEN,
         // ))
-    } else if let Some(_) = err.find::<warp::reject::MethodNotAllowed>() {
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
         code = StatusCode::METHOD_NOT_ALLOWED;
         message = "Method Not Allowed";
     } else {
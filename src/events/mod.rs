@@ -0,0 +1,240 @@
+use crate::db::DEFAULT_WORKSPACE;
+use crate::models::webhook::WebhookEvent;
+use futures::{future, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use utoipa::IntoParams;
+
+// Live clients lagging further behind than this skip the missed events
+const EVENT_BUFFER: usize = 256;
+// Changes remembered to drop repeats, like writes the handlers published
+// while the change stream was down and that it replays when it resumes
+const RECENT_CHANGES: usize = 1024;
+
+/// A change of a task, project or client, as pushed to live clients and
+/// posted to webhooks.
+#[derive(Clone, Debug, Serialize)]
+pub struct ChangeEvent {
+    pub event: WebhookEvent,
+    pub workspace: String,
+    pub occurred_at: String,
+    pub data: serde_json::Value,
+}
+
+impl ChangeEvent {
+    /// Events only reach their own workspace. With a `user`, task events of
+    /// other users are left out; deleted tasks only carry their id, so they
+    /// reach everyone in the workspace.
+    pub fn is_visible_to(&self, workspace: &str, user: Option<&str>) -> bool {
+        if self.workspace != workspace {
+            return false;
+        }
+
+        match (user, self.data.get("user")) {
+            (Some(user), Some(owner)) if self.event.is_task_event() => owner.as_str() == Some(user),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    // Browsers cannot send headers with `EventSource`, only used without `x-workspace`
+    pub workspace: Option<String>,
+    // Only task events of this user are sent when set
+    pub user: Option<String>,
+}
+
+impl EventsQuery {
+    /// The `x-workspace` header, else the `workspace` parameter, else `default`.
+    pub fn workspace(&self, header: Option<String>) -> String {
+        header
+            .or_else(|| self.workspace.clone())
+            .unwrap_or_else(|| DEFAULT_WORKSPACE.to_string())
+    }
+}
+
+/// In-process fan out of change events to live clients.
+#[derive(Clone, Debug)]
+pub struct EventBus {
+    sender: broadcast::Sender<ChangeEvent>,
+    // Set while changes come from a MongoDB change stream instead of the
+    // handlers that wrote them
+    streaming: Arc<AtomicBool>,
+    recent: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            sender,
+            streaming: Arc::new(AtomicBool::new(false)),
+            recent: Arc::new(Mutex::new(VecDeque::with_capacity(RECENT_CHANGES))),
+        }
+    }
+}
+
+/// Hands publishing back to the handlers when dropped.
+#[derive(Debug)]
+pub struct StreamingGuard(Arc<AtomicBool>);
+
+impl Drop for StreamingGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+impl EventBus {
+    pub fn is_streaming(&self) -> bool {
+        self.streaming.load(Ordering::Relaxed)
+    }
+
+    /// Marks changes as published by the change stream until the returned
+    /// guard is dropped, however the watcher ends.
+    pub fn start_streaming(&self) -> StreamingGuard {
+        self.streaming.store(true, Ordering::Relaxed);
+        StreamingGuard(self.streaming.clone())
+    }
+
+    /// Sends `event` to live clients, unless the write identified by
+    /// `change` was sent recently.
+    pub fn publish(&self, change: &str, event: ChangeEvent) {
+        {
+            let mut recent = self.recent.lock().unwrap();
+            if recent.iter().any(|seen| seen == change) {
+                return;
+            }
+            if recent.len() == RECENT_CHANGES {
+                recent.pop_front();
+            }
+            recent.push_back(change.to_string());
+        }
+
+        // Sending only fails when nobody is listening
+        let _ = self.sender.send(event);
+    }
+
+    /// Events visible to `workspace` (and `user`) from now on.
+    pub fn subscribe(
+        &self,
+        workspace: String,
+        user: Option<String>,
+    ) -> impl Stream<Item = ChangeEvent> + Send + 'static {
+        futures::stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+        .filter(move |event| future::ready(event.is_visible_to(&workspace, user.as_deref())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: WebhookEvent, workspace: &str, data: serde_json::Value) -> ChangeEvent {
+        ChangeEvent {
+            event,
+            workspace: workspace.to_string(),
+            occurred_at: String::new(),
+            data,
+        }
+    }
+
+    #[test]
+    fn events_are_scoped_to_workspace_and_user() {
+        let task = event(
+            WebhookEvent::TaskCreated,
+            "team",
+            serde_json::json!({ "_id": "1", "user": "ana" }),
+        );
+        let project = event(
+            WebhookEvent::ProjectUpdated,
+            "team",
+            serde_json::json!({ "_id": "2" }),
+        );
+
+        assert!(task.is_visible_to("team", None));
+        assert!(task.is_visible_to("team", Some("ana")));
+        assert!(!task.is_visible_to("team", Some("bo")));
+        assert!(!task.is_visible_to("other", None));
+        assert!(project.is_visible_to("team", Some("bo")));
+    }
+
+    #[test]
+    fn header_workspace_wins_over_the_query() {
+        let query = EventsQuery {
+            workspace: Some("team".to_string()),
+            user: None,
+        };
+
+        assert_eq!(query.workspace(Some("other".to_string())), "other");
+        assert_eq!(query.workspace(None), "team");
+        assert_eq!(
+            EventsQuery {
+                workspace: None,
+                user: None
+            }
+            .workspace(None),
+            DEFAULT_WORKSPACE
+        );
+    }
+
+    #[test]
+    fn streaming_ends_with_its_guard() {
+        let bus = EventBus::default();
+        let guard = bus.start_streaming();
+        assert!(bus.is_streaming());
+
+        drop(guard);
+        assert!(!bus.is_streaming());
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_their_events() {
+        let bus = EventBus::default();
+        let mut events = Box::pin(bus.subscribe("team".to_string(), None));
+
+        bus.publish(
+            "1",
+            event(WebhookEvent::ClientCreated, "other", serde_json::json!({})),
+        );
+        bus.publish(
+            "2",
+            event(WebhookEvent::ClientDeleted, "team", serde_json::json!({})),
+        );
+
+        let received = events.next().await.unwrap();
+        assert_eq!(received.event, WebhookEvent::ClientDeleted);
+    }
+
+    #[tokio::test]
+    async fn repeated_changes_are_sent_once() {
+        let bus = EventBus::default();
+        let mut events = Box::pin(bus.subscribe("team".to_string(), None));
+
+        for change in ["1", "1", "2"] {
+            bus.publish(
+                change,
+                event(
+                    WebhookEvent::ClientUpdated,
+                    "team",
+                    serde_json::json!({ "_id": change }),
+                ),
+            );
+        }
+
+        assert_eq!(events.next().await.unwrap().data["_id"], "1");
+        assert_eq!(events.next().await.unwrap().data["_id"], "2");
+    }
+}
@@ -0,0 +1,17 @@
+//! The time tracking API: routes, handlers and the MongoDB layer behind them.
+//! `main.rs` serves it, the tests under `tests/` drive it end to end.
+pub mod cli;
+pub mod controllers;
+pub mod db;
+pub mod error;
+pub mod events;
+pub mod models;
+pub mod notifications;
+pub mod openapi;
+pub mod routes;
+pub mod seed;
+
+use warp::Rejection;
+
+pub type Result<T> = std::result::Result<T, error::Error>;
+pub type WebResult<T> = std::result::Result<T, Rejection>;
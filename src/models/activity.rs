@@ -0,0 +1,24 @@
+use serde::{self, Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ActivityRequest {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ActivityResponse {
+    pub _id: String, //ObjectId
+    pub project: String,
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ActivitySuggestionsQuery {
+    pub project: Option<String>,
+    // Case insensitive prefix of the activity name
+    pub q: Option<String>,
+}
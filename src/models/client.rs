@@ -1,25 +1,212 @@
-use mongodb::bson::DateTime;
+use crate::error::Error::*;
 use serde::{self, Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct ClientSchema {
-    pub _id: String, //ObjectId
-    pub name: String,
-    pub created_at: DateTime,
-    pub updated_at: DateTime,
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ClientRequest {
     pub name: String,
+    #[serde(default)]
+    pub contacts: Vec<ClientContact>,
+    pub billing_address: Option<BillingAddress>,
+    pub tax_id: Option<String>,
+    // ISO 4217 code used as default on invoices, e.g. "EUR"
+    pub currency: Option<String>,
+    pub hourly_rate: Option<f64>,
+    pub notes: Option<String>,
     // pub created_at: DateTime,
     // pub updated_at: DateTime,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Partial edit of a client: fields left out (or null) keep their value.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct ClientPatch {
+    pub name: Option<String>,
+    pub contacts: Option<Vec<ClientContact>>,
+    pub billing_address: Option<BillingAddress>,
+    pub tax_id: Option<String>,
+    pub currency: Option<String>,
+    pub hourly_rate: Option<f64>,
+    pub notes: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ClientResponse {
     pub _id: String, //ObjectId
     pub name: String,
+    pub contacts: Vec<ClientContact>,
+    pub billing_address: Option<BillingAddress>,
+    pub tax_id: Option<String>,
+    pub currency: Option<String>,
+    pub hourly_rate: Option<f64>,
+    pub notes: Option<String>,
+    pub archived: bool,
     pub created_at: String,
     pub updated_at: String,
 }
+
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClientsQuery {
+    pub include_archived: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ClientContact {
+    pub name: String,
+    pub email: Option<String>,
+    pub role: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct BillingAddress {
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub postal_code: String,
+    pub region: Option<String>,
+    pub country: String,
+}
+
+// Active ISO 4217 codes, sorted for the binary search
+const CURRENCIES: [&str; 182] = [
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BOV", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD",
+    "CAD", "CDF", "CHE", "CHF", "CHW", "CLF", "CLP", "CNY", "COP", "COU", "CRC", "CUC", "CUP",
+    "CVE", "CZK", "DJF", "DKK", "DOP", "DZD", "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP",
+    "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ", "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS",
+    "INR", "IQD", "IRR", "ISK", "JMD", "JOD", "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW",
+    "KWD", "KYD", "KZT", "LAK", "LBP", "LKR", "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD",
+    "MMK", "MNT", "MOP", "MRU", "MUR", "MVR", "MWK", "MXN", "MXV", "MYR", "MZN", "NAD", "NGN",
+    "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN", "PGK", "PHP", "PKR", "PLN", "PYG", "QAR",
+    "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD", "SHP", "SLE", "SLL",
+    "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB", "TJS", "TMT", "TND", "TOP", "TRY",
+    "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "USN", "UYI", "UYU", "UYW", "UZS", "VED", "VES",
+    "VND", "VUV", "WST", "XAF", "XAG", "XAU", "XBA", "XBB", "XBC", "XBD", "XCD", "XCG", "XDR",
+    "XOF", "XPD", "XPF", "XPT", "XSU", "XTS", "XUA", "XXX", "YER", "ZAR", "ZMW", "ZWG", "ZWL",
+];
+
+// Only the shape: something before the `@` and a dotted domain after it
+fn is_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() > 1
+                && domain.split('.').all(|label| !label.is_empty())
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+fn validate_fields(
+    currency: Option<&str>,
+    contacts: Option<&[ClientContact]>,
+    hourly_rate: Option<f64>,
+) -> crate::Result<()> {
+    if let Some(currency) = currency {
+        if CURRENCIES.binary_search(&currency).is_err() {
+            return Err(InvalidClientError(format!(
+                "{} is not an ISO 4217 currency code",
+                currency
+            )));
+        }
+    }
+    for email in contacts
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|contact| contact.email.as_deref())
+    {
+        if !is_email(email) {
+            return Err(InvalidClientError(format!("{} is not an email", email)));
+        }
+    }
+    if let Some(rate) = hourly_rate {
+        if !rate.is_finite() || rate < 0.0 {
+            return Err(InvalidClientError(
+                "hourly_rate must be zero or more".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+impl ClientRequest {
+    pub fn validate(&self) -> crate::Result<()> {
+        validate_fields(
+            self.currency.as_deref(),
+            Some(&self.contacts),
+            self.hourly_rate,
+        )
+    }
+}
+
+impl ClientPatch {
+    pub fn validate(&self) -> crate::Result<()> {
+        validate_fields(
+            self.currency.as_deref(),
+            self.contacts.as_deref(),
+            self.hourly_rate,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> ClientRequest {
+        ClientRequest {
+            name: "Acme".to_string(),
+            contacts: vec![ClientContact {
+                name: "Bo".to_string(),
+                email: Some("bo@acme.test".to_string()),
+                role: None,
+            }],
+            billing_address: None,
+            tax_id: None,
+            currency: Some("EUR".to_string()),
+            hourly_rate: Some(0.0),
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn currencies_are_sorted() {
+        assert!(CURRENCIES.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn validates_currency_emails_and_rate() {
+        assert!(client().validate().is_ok());
+        assert!(ClientPatch::default().validate().is_ok());
+
+        let mut invalid = client();
+        invalid.currency = Some("eur".to_string());
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = client();
+        invalid.hourly_rate = Some(-1.0);
+        assert!(invalid.validate().is_err());
+
+        for email in [
+            "bo",
+            "bo@",
+            "@acme.test",
+            "bo@acme",
+            "bo@acme..test",
+            "b o@acme.test",
+        ] {
+            let mut invalid = client();
+            invalid.contacts[0].email = Some(email.to_string());
+            assert!(invalid.validate().is_err(), "{} passed", email);
+        }
+
+        let patch = ClientPatch {
+            currency: Some("XYZ".to_string()),
+            ..Default::default()
+        };
+        assert!(patch.validate().is_err());
+    }
+}
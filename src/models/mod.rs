@@ -1,3 +1,9 @@
+pub mod activity;
 pub mod client;
 pub mod project;
+pub mod report;
+pub mod tag;
 pub mod task;
+pub mod timesheet;
+pub mod webhook;
+pub mod workspace;
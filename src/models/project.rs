@@ -1,51 +1,409 @@
-use mongodb::bson::{oid::ObjectId, DateTime};
+use crate::error::Error::*;
+use crate::models::task::{ProjectTrackedTime, TrackedDuration};
+use crate::models::workspace::RoundingRule;
+use mongodb::bson::oid::ObjectId;
 use serde::{self, Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct ProjectSchema {
-    pub _id: String, //ObjectId
-    pub client: String,
-    pub name: String,
-    pub color: String,
-    pub estimate: String,
-    pub status: String,
-    pub created_at: DateTime,
-    pub updated_at: DateTime,
-}
-
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ProjectRequest {
+    #[schema(value_type = String)]
     pub client: ObjectId,
+    // Parent project, which must belong to the same client
+    #[schema(value_type = Option<String>)]
+    pub parent: Option<ObjectId>,
     pub name: String,
     pub color: String,
-    pub estimate: String,
-    pub status: String,
+    pub estimate: Option<Estimate>,
+    pub budget_alerts: Option<BudgetAlertSettings>,
+    // Overrides the workspace rounding rule in reports
+    pub rounding: Option<RoundingRule>,
+    #[serde(default)]
+    pub status: ProjectStatus,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ProjectResponse {
     pub _id: String, //ObjectId
     pub client: String,
+    pub parent: Option<String>, //hex
     pub name: String,
     pub color: String,
-    pub estimate: String,
-    pub status: String,
+    pub estimate: Option<Estimate>,
+    // Free text estimate of an older project that `migrate` could not read
+    pub legacy_estimate: Option<String>,
+    pub budget_alerts: Option<BudgetAlertSettings>,
+    pub rounding: Option<RoundingRule>,
+    pub status: ProjectStatus,
     pub created_at: String,
     pub updated_at: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ProjectsGroupedByClient {
     pub _id: String,
     pub projects: Vec<ProjectAfterAggregation>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ProjectAfterAggregation {
     pub _id: String,
     pub name: String,
     pub color: String,
     pub client_name: String,
-    pub estimate: String,
-    pub status: String,
+    pub parent: Option<String>,
+    pub estimate: Option<Estimate>,
+    pub status: ProjectStatus,
+    // Includes the time tracked on every subproject
+    pub budget: Option<ProjectBudget>,
+    #[schema(no_recursion)]
+    pub subprojects: Vec<ProjectAfterAggregation>,
+}
+
+impl ProjectAfterAggregation {
+    /// Nests every project under its parent. Projects whose parent is not in
+    /// the list (e.g. an archived parent) become roots.
+    pub fn into_tree(projects: Vec<ProjectAfterAggregation>) -> Vec<ProjectAfterAggregation> {
+        fn attach(
+            project: &mut ProjectAfterAggregation,
+            children: &mut HashMap<String, Vec<ProjectAfterAggregation>>,
+        ) {
+            if let Some(mut subprojects) = children.remove(&project._id) {
+                for subproject in subprojects.iter_mut() {
+                    attach(subproject, children);
+                }
+                project.subprojects = subprojects;
+            }
+        }
+
+        let ids: HashSet<String> = projects.iter().map(|project| project._id.clone()).collect();
+        let mut children: HashMap<String, Vec<ProjectAfterAggregation>> = HashMap::new();
+        let mut roots: Vec<ProjectAfterAggregation> = vec![];
+
+        for project in projects {
+            match &project.parent {
+                Some(parent) if ids.contains(parent) => {
+                    children.entry(parent.clone()).or_default().push(project)
+                }
+                _ => roots.push(project),
+            }
+        }
+
+        for root in roots.iter_mut() {
+            attach(root, &mut children);
+        }
+
+        roots
+    }
+}
+
+/// Parent links between projects, used to roll tracked time up to parents.
+#[derive(Clone, Debug, Default)]
+pub struct ProjectTree {
+    pub parents: HashMap<ObjectId, ObjectId>,
+    pub children: HashMap<ObjectId, Vec<ObjectId>>,
+}
+
+impl ProjectTree {
+    pub fn new(parents: HashMap<ObjectId, ObjectId>) -> Self {
+        let mut children: HashMap<ObjectId, Vec<ObjectId>> = HashMap::new();
+        for (project, parent) in &parents {
+            children.entry(*parent).or_default().push(*project);
+        }
+
+        Self { parents, children }
+    }
+
+    /// The project itself followed by every nested subproject.
+    pub fn with_descendants(&self, project: ObjectId) -> Vec<ObjectId> {
+        let mut visited: HashSet<ObjectId> = HashSet::new();
+        let mut pending = vec![project];
+        let mut result = vec![];
+
+        while let Some(current) = pending.pop() {
+            if !visited.insert(current) {
+                continue;
+            }
+            result.push(current);
+            if let Some(children) = self.children.get(&current) {
+                pending.extend(children);
+            }
+        }
+
+        result
+    }
+
+    /// Parent, grandparent and so on up to the root project.
+    pub fn ancestors(&self, project: ObjectId) -> Vec<ObjectId> {
+        let mut visited: HashSet<ObjectId> = HashSet::from([project]);
+        let mut result = vec![];
+        let mut current = project;
+
+        while let Some(parent) = self.parents.get(&current) {
+            if !visited.insert(*parent) {
+                break;
+            }
+            result.push(*parent);
+            current = *parent;
+        }
+
+        result
+    }
+
+    pub fn rolled_up_time(
+        &self,
+        project: ObjectId,
+        tracked: &HashMap<ObjectId, ProjectTrackedTime>,
+    ) -> ProjectTrackedTime {
+        let mut total = ProjectTrackedTime::default();
+        for id in self.with_descendants(project) {
+            if let Some(time) = tracked.get(&id) {
+                total.total_seconds += time.total_seconds;
+                total.month_seconds += time.month_seconds;
+            }
+        }
+
+        total
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProjectStatus {
+    #[default]
+    Active,
+    OnHold,
+    Completed,
+    Archived,
+}
+
+impl ProjectStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectStatus::Active => "active",
+            ProjectStatus::OnHold => "on-hold",
+            ProjectStatus::Completed => "completed",
+            ProjectStatus::Archived => "archived",
+        }
+    }
+
+    pub fn can_transition_to(&self, next: ProjectStatus) -> bool {
+        use ProjectStatus::*;
+
+        matches!(
+            (self, next),
+            (Active, OnHold | Completed | Archived)
+                | (OnHold, Active | Completed | Archived)
+                | (Completed, Active | Archived)
+                | (Archived, Active)
+        )
+    }
+}
+
+impl FromStr for ProjectStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "active" => Ok(ProjectStatus::Active),
+            "on-hold" => Ok(ProjectStatus::OnHold),
+            "completed" => Ok(ProjectStatus::Completed),
+            "archived" => Ok(ProjectStatus::Archived),
+            _ => Err(format!("unknown project status: {}", status)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ProjectStatusRequest {
+    pub status: ProjectStatus,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ProjectParentRequest {
+    // `null` moves the project to the top level
+    #[schema(value_type = Option<String>)]
+    pub parent: Option<ObjectId>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ProjectsQuery {
+    // Archived projects are only listed when explicitly asked for
+    pub status: Option<ProjectStatus>,
+    // Also list projects of archived clients
+    pub include_archived: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateKind {
+    Hours,
+    Money,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateRecurrence {
+    Total,
+    Monthly,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct Estimate {
+    pub kind: EstimateKind,
+    pub amount: f64,
+    pub recurrence: EstimateRecurrence,
+    // Only used by `Money` estimates to price the tracked hours
+    pub hourly_rate: Option<f64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ProjectBudget {
+    pub estimate: Estimate,
+    pub tracked: TrackedDuration,
+    pub consumed: f64,
+    pub remaining: f64,
+    pub percentage: f64,
+}
+
+impl Estimate {
+    /// `Money` estimates price the tracked hours, so they need a rate.
+    pub fn validate(&self) -> crate::Result<()> {
+        if !self.amount.is_finite() || self.amount < 0.0 {
+            return Err(InvalidEstimateError(
+                "amount must be zero or more".to_string(),
+            ));
+        }
+        match (self.kind, self.hourly_rate) {
+            (EstimateKind::Money, None) => Err(InvalidEstimateError(
+                "money estimates need an hourly_rate".to_string(),
+            )),
+            (_, Some(rate)) if !rate.is_finite() || rate <= 0.0 => Err(InvalidEstimateError(
+                "hourly_rate must be more than zero".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Budget alerts fire once per period: once for `Total` estimates and
+    /// every calendar month (`YYYY-MM`) for `Monthly` ones.
+    pub fn alert_period(&self, now: chrono::DateTime<chrono::Utc>) -> String {
+        match self.recurrence {
+            EstimateRecurrence::Total => "total".to_string(),
+            EstimateRecurrence::Monthly => now.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// Compares tracked time against the estimate. `total_seconds` is every
+    /// task of the project, `month_seconds` only the current calendar month
+    /// (used by monthly recurring estimates).
+    pub fn budget(&self, total_seconds: i64, month_seconds: i64) -> ProjectBudget {
+        let tracked_seconds = match self.recurrence {
+            EstimateRecurrence::Total => total_seconds,
+            EstimateRecurrence::Monthly => month_seconds,
+        };
+        let hours = tracked_seconds as f64 / 3600.0;
+        let consumed = match self.kind {
+            EstimateKind::Hours => hours,
+            EstimateKind::Money => hours * self.hourly_rate.unwrap_or(0.0),
+        };
+        let percentage = if self.amount > 0.0 {
+            consumed / self.amount * 100.0
+        } else {
+            0.0
+        };
+
+        ProjectBudget {
+            estimate: self.clone(),
+            tracked: tracked_seconds.into(),
+            consumed,
+            remaining: self.amount - consumed,
+            percentage,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct BudgetAlertSettings {
+    // Percentages of the estimate, e.g. [50, 80, 100]
+    pub thresholds: Vec<u32>,
+    pub webhook_url: Option<String>,
+    pub email: Option<String>,
+}
+
+impl BudgetAlertSettings {
+    pub fn channels(&self) -> Vec<BudgetAlertChannel> {
+        let webhook = self.webhook_url.clone().map(BudgetAlertChannel::Webhook);
+        let email = self.email.clone().map(BudgetAlertChannel::Email);
+        webhook.into_iter().chain(email).collect()
+    }
+}
+
+/// Where an alert is sent, each channel is claimed and delivered on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BudgetAlertChannel {
+    Webhook(String),
+    Email(String),
+}
+
+impl BudgetAlertChannel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BudgetAlertChannel::Webhook(_) => "webhook",
+            BudgetAlertChannel::Email(_) => "email",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BudgetAlert {
+    pub project: String, //hex
+    pub project_name: String,
+    pub threshold: u32,
+    pub percentage: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn money_estimates_need_an_hourly_rate() {
+        let mut estimate = Estimate {
+            kind: EstimateKind::Money,
+            amount: 5000.0,
+            recurrence: EstimateRecurrence::Total,
+            hourly_rate: None,
+        };
+        assert!(estimate.validate().is_err());
+
+        estimate.hourly_rate = Some(80.0);
+        assert!(estimate.validate().is_ok());
+
+        estimate.kind = EstimateKind::Hours;
+        estimate.hourly_rate = None;
+        assert!(estimate.validate().is_ok());
+    }
+
+    #[test]
+    fn alerts_go_to_each_configured_channel() {
+        let mut settings = BudgetAlertSettings {
+            thresholds: vec![80],
+            webhook_url: Some("https://hooks.example.com".to_string()),
+            email: None,
+        };
+        assert_eq!(
+            settings.channels(),
+            vec![BudgetAlertChannel::Webhook(
+                "https://hooks.example.com".to_string()
+            )]
+        );
+
+        settings.email = Some("pm@example.com".to_string());
+        let names: Vec<_> = settings.channels().iter().map(|c| c.name()).collect();
+        assert_eq!(names, ["webhook", "email"]);
+    }
 }
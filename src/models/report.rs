@@ -0,0 +1,57 @@
+use crate::models::task::TrackedDuration;
+use serde::{self, Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportQuery {
+    // Inclusive days, formatted as YYYY-MM-DD
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub tag: Option<String>,
+    // IANA time zone of the days, defaults to UTC
+    pub tz: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct SummaryReport {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub total: TrackedDuration,
+    pub rounded_total: TrackedDuration,
+    pub projects: Vec<ProjectSummary>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ProjectSummary {
+    pub project: Option<String>, //hex
+    pub parent: Option<String>,  //hex
+    pub project_name: Option<String>,
+    pub client_name: Option<String>,
+    pub total: TrackedDuration,
+    // Billable time after the project (or workspace) rounding rule
+    pub rounded: TrackedDuration,
+    // Own time plus the time of every nested subproject
+    pub total_with_subprojects: TrackedDuration,
+    pub rounded_with_subprojects: TrackedDuration,
+    pub task_count: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ActivityReport {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub total: TrackedDuration,
+    pub rounded_total: TrackedDuration,
+    pub activities: Vec<ActivitySummary>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ActivitySummary {
+    pub activity: Option<String>, //hex
+    pub activity_name: Option<String>,
+    pub project_name: Option<String>,
+    pub total: TrackedDuration,
+    pub rounded: TrackedDuration,
+    pub task_count: i64,
+}
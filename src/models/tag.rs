@@ -0,0 +1,22 @@
+use serde::{self, Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TagRequest {
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TagResponse {
+    pub _id: String, //ObjectId
+    pub name: String,
+    pub workspace: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TagMergeRequest {
+    // Tag that replaces the merged one on every task
+    pub into: String,
+}
@@ -1,50 +1,54 @@
+use chrono::{Duration, NaiveDate, TimeZone};
+use chrono_tz::Tz;
 use mongodb::bson::oid::ObjectId;
-use mongodb::bson::serde_helpers::bson_datetime_as_rfc3339_string;
-use mongodb::bson::DateTime;
 use serde::{self, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct TaskSchema {
-    pub _id: ObjectId,
-    pub name: String,
-    pub project: Option<ObjectId>,
-    #[serde(with = "bson_datetime_as_rfc3339_string")]
-    pub initial_time: DateTime,
-    #[serde(with = "bson_datetime_as_rfc3339_string")]
-    pub end_time: DateTime,
-    #[serde(with = "bson_datetime_as_rfc3339_string")]
-    pub created_at: DateTime,
-    #[serde(with = "bson_datetime_as_rfc3339_string")]
-    pub updated_at: DateTime,
-}
+type UtcDateTime = chrono::DateTime<chrono::Utc>;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct TaskRequest {
     pub name: String,
+    // Overlapping entries are only detected between tasks of the same user
+    pub user: Option<String>,
     pub initial_time: String,
     pub end_time: String,
+    #[schema(value_type = Option<String>)]
     pub project: Option<ObjectId>,
+    // Must be one of the activities of `project`
+    #[schema(value_type = Option<String>)]
+    pub activity: Option<ObjectId>,
+    pub description: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Vec<String>)]
+    pub tags: Vec<ObjectId>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct TaskResponse {
     pub _id: String,
     pub name: String,
+    pub user: Option<String>,
     pub initial_time: String,
     pub end_time: String,
-    pub project: Option<String>, //hex
+    pub project: Option<String>,  //hex
+    pub activity: Option<String>, //hex
+    pub description: Option<String>,
+    pub tags: Vec<String>, //hex
+    pub duration: TrackedDuration,
     pub created_at: String,
     pub updated_at: String,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct TasksGroupedByDate {
     pub _id: String,
     pub tasks: Vec<TaskAfterGrouped>,
-    pub total_time: i32,
+    pub total_time: TrackedDuration,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct TaskAfterGrouped {
     pub _id: String,
     pub name: String,
@@ -53,4 +57,389 @@ pub struct TaskAfterGrouped {
     pub project: Option<String>,
     pub project_color: Option<String>,
     pub client: Option<String>,
+    pub activity: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    // Only the part of the task falling on the day it is listed under
+    pub duration: TrackedDuration,
+}
+
+/// Length of time as returned by the API: whole seconds plus `HH:MM:SS`.
+/// Hours are not wrapped, so long totals read like `31:05:00`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct TrackedDuration {
+    pub seconds: i64,
+    pub formatted: String,
+}
+
+impl From<i64> for TrackedDuration {
+    fn from(seconds: i64) -> Self {
+        let abs = seconds.abs();
+        let sign = if seconds < 0 { "-" } else { "" };
+
+        TrackedDuration {
+            seconds,
+            formatted: format!(
+                "{}{:02}:{:02}:{:02}",
+                sign,
+                abs / 3600,
+                abs % 3600 / 60,
+                abs % 60
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TasksQuery {
+    pub tag: Option<String>,
+    pub user: Option<String>,
+    // Inclusive days (YYYY-MM-DD) the tasks overlap. The grouped view lists
+    // the last 30 by default, the plain list is unbounded.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    // IANA time zone used to split tasks into days, defaults to UTC
+    pub tz: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProjectTrackedTime {
+    pub total_seconds: i64,
+    pub month_seconds: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TaskWriteResponse {
+    pub _id: String,
+    // Only filled when the workspace overlap policy is `warn`
+    pub overlapping_tasks: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct OverlapsQuery {
+    pub user: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TaskOverlap {
+    pub user: String,
+    pub task: String,
+    pub overlaps_with: String,
+    pub overlap_seconds: i64,
+}
+
+/// A stored task as overlap detection sees it: id, user and time range.
+pub type OverlapEntry = (String, Option<String>, UtcDateTime, UtcDateTime);
+
+/// Every pair of tasks of the same user covering the same minutes, out of
+/// tasks sorted by user and start. Tasks without a user never overlap.
+pub fn find_overlapping_pairs(tasks: &[OverlapEntry]) -> Vec<TaskOverlap> {
+    let mut overlaps: Vec<TaskOverlap> = vec![];
+    let mut current_user: Option<&str> = None;
+    // Earlier tasks of the current user that are still running
+    let mut open: Vec<(&str, UtcDateTime)> = vec![];
+
+    for (id, user, initial_time, end_time) in tasks {
+        let user = match user {
+            Some(user) => user.as_str(),
+            None => continue,
+        };
+
+        if Some(user) != current_user {
+            current_user = Some(user);
+            open.clear();
+        }
+        open.retain(|(_, open_end)| open_end > initial_time);
+
+        for (open_id, open_end) in &open {
+            let overlap_end = std::cmp::min(open_end, end_time);
+            overlaps.push(TaskOverlap {
+                user: user.to_string(),
+                task: id.clone(),
+                overlaps_with: open_id.to_string(),
+                overlap_seconds: (*overlap_end - *initial_time).num_seconds(),
+            });
+        }
+        open.push((id, *end_time));
+    }
+
+    overlaps
+}
+
+/// Shortens `start..end` so it no longer intersects any of `others`, moving
+/// the start past entries covering it and the end before entries covering it.
+/// Returns `None` when an entry sits strictly inside the range or nothing is
+/// left after trimming.
+pub fn trim_overlaps(
+    start: UtcDateTime,
+    end: UtcDateTime,
+    others: &[(UtcDateTime, UtcDateTime)],
+) -> Option<(UtcDateTime, UtcDateTime)> {
+    let mut start = start;
+    let mut end = end;
+    let mut changed = true;
+
+    while changed {
+        changed = false;
+        for (other_start, other_end) in others {
+            if *other_start >= end || *other_end <= start {
+                continue;
+            }
+            if *other_start <= start {
+                start = *other_end;
+            } else if *other_end >= end {
+                end = *other_start;
+            } else {
+                return None;
+            }
+            if start >= end {
+                return None;
+            }
+            changed = true;
+        }
+    }
+
+    Some((start, end))
+}
+
+/// First instant of `day` in `tz`.
+pub fn start_of_day(day: NaiveDate, tz: Tz) -> UtcDateTime {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap();
+    // Some zones skip midnight on DST changes, the day then starts an hour later
+    tz.from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(midnight + Duration::hours(1)))
+                .earliest()
+        })
+        .unwrap()
+        .with_timezone(&chrono::Utc)
+}
+
+/// Seconds of `start..end` falling on each local day of `tz`. Empty ranges
+/// still belong to the day they start on.
+pub fn split_by_day(start: UtcDateTime, end: UtcDateTime, tz: Tz) -> Vec<(NaiveDate, i64)> {
+    let mut days = vec![];
+    let mut cursor = start;
+
+    loop {
+        let day = cursor.with_timezone(&tz).date_naive();
+        let next_midnight = start_of_day(day.succ_opt().unwrap(), tz);
+
+        let segment_end = std::cmp::min(end, next_midnight);
+        days.push((
+            day,
+            std::cmp::max(segment_end - cursor, Duration::zero()).num_seconds(),
+        ));
+
+        if segment_end >= end {
+            break;
+        }
+        cursor = segment_end;
+    }
+
+    days
+}
+
+/// Lists tasks under every local day of `tz` they span, most recent day
+/// first. Each listing only carries the part of the task on that day, so
+/// the total of a day is always the sum of its tasks.
+pub fn group_by_day(
+    tasks: Vec<(UtcDateTime, UtcDateTime, TaskAfterGrouped)>,
+    tz: Tz,
+) -> Vec<TasksGroupedByDate> {
+    let mut days: BTreeMap<NaiveDate, Vec<TaskAfterGrouped>> = BTreeMap::new();
+
+    for (start, end, task) in tasks {
+        for (day, seconds) in split_by_day(start, end, tz) {
+            days.entry(day).or_default().push(TaskAfterGrouped {
+                duration: seconds.into(),
+                ..task.clone()
+            });
+        }
+    }
+
+    days.into_iter()
+        .rev()
+        .map(|(day, tasks)| TasksGroupedByDate {
+            _id: day.format("%Y-%m-%d").to_string(),
+            total_time: tasks
+                .iter()
+                .map(|task| task.duration.seconds)
+                .sum::<i64>()
+                .into(),
+            tasks,
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TaskSplitRequest {
+    // Instant inside the task, formatted as RFC 3339
+    pub at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TaskSplitResponse {
+    pub first: String,
+    pub second: String,
+    // Only filled when the workspace overlap policy is `warn`
+    pub overlapping_tasks: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> UtcDateTime {
+        time.parse().unwrap()
+    }
+
+    fn task(id: &str) -> TaskAfterGrouped {
+        TaskAfterGrouped {
+            _id: id.to_string(),
+            name: id.to_string(),
+            initial_time: String::new(),
+            end_time: String::new(),
+            project: None,
+            project_color: None,
+            client: None,
+            activity: None,
+            description: None,
+            tags: vec![],
+            duration: TrackedDuration::default(),
+        }
+    }
+
+    #[test]
+    fn finds_overlaps_per_user() {
+        let entry = |id: &str, user: Option<&str>, start: &str, end: &str| {
+            (id.to_string(), user.map(str::to_string), at(start), at(end))
+        };
+        let tasks = vec![
+            entry(
+                "a",
+                Some("ana"),
+                "2021-10-18T09:00:00Z",
+                "2021-10-18T10:00:00Z",
+            ),
+            entry(
+                "b",
+                Some("ana"),
+                "2021-10-18T09:30:00Z",
+                "2021-10-18T11:00:00Z",
+            ),
+            entry(
+                "c",
+                Some("ana"),
+                "2021-10-18T11:00:00Z",
+                "2021-10-18T12:00:00Z",
+            ),
+            entry(
+                "d",
+                Some("bo"),
+                "2021-10-18T09:00:00Z",
+                "2021-10-18T10:00:00Z",
+            ),
+        ];
+
+        let overlaps = find_overlapping_pairs(&tasks);
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].user, "ana");
+        assert_eq!(overlaps[0].task, "b");
+        assert_eq!(overlaps[0].overlaps_with, "a");
+        assert_eq!(overlaps[0].overlap_seconds, 1800);
+    }
+
+    #[test]
+    fn tasks_without_a_user_never_overlap() {
+        let entry = |id: &str, user: Option<&str>| {
+            (
+                id.to_string(),
+                user.map(str::to_string),
+                at("2021-10-18T09:00:00Z"),
+                at("2021-10-18T10:00:00Z"),
+            )
+        };
+        let tasks = vec![entry("a", None), entry("b", None), entry("c", Some("ana"))];
+
+        assert!(find_overlapping_pairs(&tasks).is_empty());
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(TrackedDuration::from(0).formatted, "00:00:00");
+        assert_eq!(TrackedDuration::from(3725).formatted, "01:02:05");
+        assert_eq!(TrackedDuration::from(111_900).formatted, "31:05:00");
+        assert_eq!(TrackedDuration::from(-90).formatted, "-00:01:30");
+    }
+
+    #[test]
+    fn day_totals_match_the_sum_of_entries() {
+        let tasks = vec![
+            (
+                at("2021-10-19T09:00:00Z"),
+                at("2021-10-19T10:30:00Z"),
+                task("a"),
+            ),
+            (
+                at("2021-10-19T11:00:00Z"),
+                at("2021-10-19T11:00:45Z"),
+                task("b"),
+            ),
+            (
+                at("2021-10-18T08:15:00Z"),
+                at("2021-10-18T12:00:00Z"),
+                task("c"),
+            ),
+        ];
+
+        let days = group_by_day(tasks, Tz::UTC);
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0]._id, "2021-10-19");
+        assert_eq!(days[0].total_time, TrackedDuration::from(5445));
+        assert_eq!(days[1]._id, "2021-10-18");
+        assert_eq!(days[1].total_time.formatted, "03:45:00");
+        for day in &days {
+            let sum: i64 = day.tasks.iter().map(|task| task.duration.seconds).sum();
+            assert_eq!(day.total_time.seconds, sum);
+        }
+    }
+
+    #[test]
+    fn tasks_crossing_midnight_count_on_each_day() {
+        let tasks = vec![(
+            at("2021-10-18T22:00:00Z"),
+            at("2021-10-19T01:30:00Z"),
+            task("night"),
+        )];
+
+        let days = group_by_day(tasks, Tz::UTC);
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].total_time.seconds, 5400);
+        assert_eq!(days[1].total_time.seconds, 7200);
+        assert_eq!(
+            days.iter().map(|day| day.total_time.seconds).sum::<i64>(),
+            (at("2021-10-19T01:30:00Z") - at("2021-10-18T22:00:00Z")).num_seconds()
+        );
+    }
+
+    #[test]
+    fn days_follow_the_requested_time_zone() {
+        let tasks = vec![(
+            at("2021-10-18T22:00:00Z"),
+            at("2021-10-18T23:00:00Z"),
+            task("late"),
+        )];
+
+        let days = group_by_day(tasks, "Europe/Berlin".parse().unwrap());
+
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0]._id, "2021-10-19");
+        assert_eq!(days[0].total_time.seconds, 3600);
+    }
 }
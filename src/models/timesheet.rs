@@ -0,0 +1,640 @@
+use crate::models::task::{split_by_day, start_of_day, TrackedDuration};
+use crate::{error::Error::*, Result};
+use chrono::{Duration, NaiveDate};
+use chrono_tz::Tz;
+use mongodb::bson::oid::ObjectId;
+use serde::{self, Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
+
+type UtcDateTime = chrono::DateTime<chrono::Utc>;
+
+pub const WEEK_DAYS: usize = 7;
+// Hour of the day new timesheet tasks start at when the day is still empty
+const WORKDAY_START_HOUR: i64 = 9;
+const DEFAULT_TASK_NAME: &str = "Timesheet entry";
+
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WeekQuery {
+    // First day of the week, formatted as YYYY-MM-DD
+    pub start: String,
+    pub user: Option<String>,
+    // IANA time zone of the days, defaults to UTC
+    pub tz: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct WeekTimesheet {
+    pub start: String,
+    pub user: Option<String>,
+    pub status: TimesheetStatus,
+    pub days: Vec<String>,
+    pub rows: Vec<TimesheetRow>,
+    pub day_totals: Vec<TrackedDuration>,
+    pub total: TrackedDuration,
+    // Only filled by a PUT when the workspace overlap policy is `warn`
+    pub overlapping_tasks: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TimesheetRow {
+    pub project: Option<String>,  //hex
+    pub activity: Option<String>, //hex
+    pub project_name: Option<String>,
+    pub activity_name: Option<String>,
+    // One entry per day of the week
+    pub days: Vec<TrackedDuration>,
+    pub total: TrackedDuration,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct WeekTimesheetRequest {
+    pub start: String,
+    pub user: Option<String>,
+    pub tz: Option<String>,
+    pub rows: Vec<TimesheetRowRequest>,
+}
+
+/// Rows left out of the request keep their tasks untouched, a day set to `0`
+/// removes the tasks of that cell.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TimesheetRowRequest {
+    #[schema(value_type = Option<String>)]
+    pub project: Option<ObjectId>,
+    #[schema(value_type = Option<String>)]
+    pub activity: Option<ObjectId>,
+    // Name of the tasks created for this row
+    pub name: Option<String>,
+    // Seconds for each day of the week
+    pub days: Vec<i64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimesheetStatus {
+    #[default]
+    Draft,
+    Submitted,
+    Approved,
+    Rejected,
+}
+
+impl TimesheetStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimesheetStatus::Draft => "draft",
+            TimesheetStatus::Submitted => "submitted",
+            TimesheetStatus::Approved => "approved",
+            TimesheetStatus::Rejected => "rejected",
+        }
+    }
+
+    /// Submitted timesheets can be withdrawn back to draft, approved ones
+    /// only by reopening them as a draft.
+    pub fn can_transition_to(&self, next: TimesheetStatus) -> bool {
+        use TimesheetStatus::*;
+
+        matches!(
+            (self, next),
+            (Draft, Submitted)
+                | (Submitted, Approved | Rejected | Draft)
+                | (Rejected, Submitted | Draft)
+                | (Approved, Draft)
+        )
+    }
+}
+
+impl TimesheetStatus {
+    /// Approving, rejecting and reopening an approved week are reviews, only
+    /// workspace admins do them.
+    pub fn is_review(&self, next: TimesheetStatus) -> bool {
+        use TimesheetStatus::*;
+
+        matches!(next, Approved | Rejected) || *self == Approved
+    }
+}
+
+impl FromStr for TimesheetStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> std::result::Result<Self, Self::Err> {
+        match status {
+            "draft" => Ok(TimesheetStatus::Draft),
+            "submitted" => Ok(TimesheetStatus::Submitted),
+            "approved" => Ok(TimesheetStatus::Approved),
+            "rejected" => Ok(TimesheetStatus::Rejected),
+            _ => Err(format!("unknown timesheet status: {}", status)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TimesheetStatusRequest {
+    pub start: String,
+    pub user: Option<String>,
+    pub tz: Option<String>,
+    pub status: TimesheetStatus,
+    // Who submits, approves or rejects the timesheet. Approving, rejecting
+    // and reopening an approved week need one of the workspace `admins`.
+    pub author: Option<String>,
+    // Required when rejecting
+    pub comment: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TimesheetComment {
+    pub author: Option<String>,
+    // Status the timesheet moved to with this comment
+    pub status: TimesheetStatus,
+    pub text: String,
+    pub created_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct TimesheetResponse {
+    pub _id: String,
+    pub user: Option<String>,
+    pub start: String,
+    pub tz: Option<String>,
+    pub status: TimesheetStatus,
+    pub comments: Vec<TimesheetComment>,
+    // Tasks locked by the approval
+    pub tasks: Vec<String>, //hex
+    pub submitted_at: Option<String>,
+    // Admin who approved the week, while it is approved
+    pub approved_by: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A task of the timesheet user, as far as the grid is concerned.
+#[derive(Clone, Debug)]
+pub struct WeekEntry {
+    // `None` for tasks planned but not written yet
+    pub id: Option<ObjectId>,
+    pub project: Option<ObjectId>,
+    pub activity: Option<ObjectId>,
+    pub project_name: Option<String>,
+    pub activity_name: Option<String>,
+    pub start: UtcDateTime,
+    pub end: UtcDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimesheetChange {
+    Create {
+        name: String,
+        project: Option<ObjectId>,
+        activity: Option<ObjectId>,
+        start: UtcDateTime,
+        end: UtcDateTime,
+    },
+    Resize {
+        id: ObjectId,
+        end: UtcDateTime,
+    },
+    Delete {
+        id: ObjectId,
+    },
+}
+
+pub fn parse_week_start(start: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(start, "%Y-%m-%d").map_err(|_| InvalidDateError(start.to_owned()))
+}
+
+/// First instant of each day of the week plus the end of the last one.
+pub fn week_bounds(start: NaiveDate, tz: Tz) -> Vec<UtcDateTime> {
+    (0..=WEEK_DAYS as i64)
+        .map(|day| start_of_day(start + Duration::days(day), tz))
+        .collect()
+}
+
+/// Rows of the week grid, one per project and activity, ordered by name.
+/// Tasks crossing midnight count on each day they span.
+pub fn build_week(
+    start: NaiveDate,
+    tz: Tz,
+    user: Option<String>,
+    entries: &[WeekEntry],
+) -> WeekTimesheet {
+    let mut rows: Vec<(WeekEntry, Vec<i64>)> = vec![];
+
+    for entry in entries {
+        let index = match rows
+            .iter()
+            .position(|(row, _)| row.project == entry.project && row.activity == entry.activity)
+        {
+            Some(index) => index,
+            None => {
+                rows.push((entry.clone(), vec![0; WEEK_DAYS]));
+                rows.len() - 1
+            }
+        };
+
+        for (day, seconds) in split_by_day(entry.start, entry.end, tz) {
+            let offset = (day - start).num_days();
+            if (0..WEEK_DAYS as i64).contains(&offset) {
+                rows[index].1[offset as usize] += seconds;
+            }
+        }
+    }
+
+    rows.sort_by(|(a, _), (b, _)| {
+        (&a.project_name, &a.activity_name).cmp(&(&b.project_name, &b.activity_name))
+    });
+
+    let mut day_totals = vec![0; WEEK_DAYS];
+    for (_, days) in &rows {
+        for (total, seconds) in day_totals.iter_mut().zip(days) {
+            *total += seconds;
+        }
+    }
+
+    WeekTimesheet {
+        start: start.format("%Y-%m-%d").to_string(),
+        user,
+        status: TimesheetStatus::default(),
+        days: (0..WEEK_DAYS as i64)
+            .map(|day| (start + Duration::days(day)).format("%Y-%m-%d").to_string())
+            .collect(),
+        rows: rows
+            .into_iter()
+            .map(|(row, days)| TimesheetRow {
+                project: row.project.map(|id| id.to_hex()),
+                activity: row.activity.map(|id| id.to_hex()),
+                project_name: row.project_name,
+                activity_name: row.activity_name,
+                total: days.iter().sum::<i64>().into(),
+                days: days.into_iter().map(TrackedDuration::from).collect(),
+            })
+            .collect(),
+        total: day_totals.iter().sum::<i64>().into(),
+        day_totals: day_totals.into_iter().map(TrackedDuration::from).collect(),
+        overlapping_tasks: vec![],
+    }
+}
+
+fn seconds_within(entry: &WeekEntry, from: UtcDateTime, to: UtcDateTime) -> i64 {
+    let start = std::cmp::max(entry.start, from);
+    let end = std::cmp::min(entry.end, to);
+    std::cmp::max(end - start, Duration::zero()).num_seconds()
+}
+
+/// Works out the task writes turning the current `entries` of the user into
+/// the requested grid. Cells that already match are left alone; shorter
+/// cells shrink or remove their latest tasks and longer ones grow the latest
+/// task when it has room, otherwise a new task is appended to the day.
+pub fn plan_week(
+    start: NaiveDate,
+    tz: Tz,
+    entries: &[WeekEntry],
+    rows: &[TimesheetRowRequest],
+) -> Result<Vec<TimesheetChange>> {
+    let bounds = week_bounds(start, tz);
+    let mut entries = entries.to_vec();
+    let mut changes: Vec<TimesheetChange> = vec![];
+
+    for (index, row) in rows.iter().enumerate() {
+        if row.days.len() != WEEK_DAYS {
+            return Err(InvalidTimesheetError(format!(
+                "row {} must have {} days",
+                index, WEEK_DAYS
+            )));
+        }
+        if rows[..index]
+            .iter()
+            .any(|other| other.project == row.project && other.activity == row.activity)
+        {
+            return Err(InvalidTimesheetError(format!("row {} is repeated", index)));
+        }
+
+        for (day, desired) in row.days.iter().enumerate() {
+            let (day_start, day_end) = (bounds[day], bounds[day + 1]);
+            if *desired < 0 || *desired > (day_end - day_start).num_seconds() {
+                return Err(InvalidTimesheetError(format!(
+                    "row {} day {} has an invalid duration",
+                    index, day
+                )));
+            }
+
+            let in_row =
+                |entry: &WeekEntry| entry.project == row.project && entry.activity == row.activity;
+            let existing: i64 = entries
+                .iter()
+                .filter(|entry| in_row(entry))
+                .map(|entry| seconds_within(entry, day_start, day_end))
+                .sum();
+
+            // Only stored tasks lying inside the day are changed, each cell is
+            // visited once so tasks planned here are never part of it
+            let mut cell: Vec<usize> = (0..entries.len())
+                .filter(|i| {
+                    entries[*i].id.is_some()
+                        && in_row(&entries[*i])
+                        && entries[*i].start >= day_start
+                        && entries[*i].end <= day_end
+                })
+                .collect();
+            cell.sort_by_key(|i| entries[*i].start);
+
+            if *desired < existing {
+                let mut shrink = existing - desired;
+                let mut removed: Vec<usize> = vec![];
+                for i in cell.iter().rev() {
+                    let seconds = (entries[*i].end - entries[*i].start).num_seconds();
+                    if seconds <= shrink {
+                        shrink -= seconds;
+                        removed.push(*i);
+                        if let Some(id) = entries[*i].id {
+                            changes.push(TimesheetChange::Delete { id });
+                        }
+                    } else {
+                        entries[*i].end -= Duration::seconds(shrink);
+                        shrink = 0;
+                        if let Some(id) = entries[*i].id {
+                            changes.push(TimesheetChange::Resize {
+                                id,
+                                end: entries[*i].end,
+                            });
+                        }
+                    }
+                    if shrink == 0 {
+                        break;
+                    }
+                }
+                if shrink > 0 {
+                    return Err(InvalidTimesheetError(format!(
+                        "row {} day {} comes from tasks crossing midnight",
+                        index, day
+                    )));
+                }
+                removed.sort_unstable();
+                for i in removed.into_iter().rev() {
+                    entries.remove(i);
+                }
+            } else if *desired > existing {
+                let grow = Duration::seconds(desired - existing);
+
+                // The latest task of the cell grows when nothing else starts after it
+                if let Some(last) = cell.last().copied() {
+                    let new_end = entries[last].end + grow;
+                    let blocked = entries
+                        .iter()
+                        .any(|entry| entry.start >= entries[last].end && entry.start < new_end);
+                    if !blocked && new_end <= day_end {
+                        entries[last].end = new_end;
+                        if let Some(id) = entries[last].id {
+                            changes.push(TimesheetChange::Resize { id, end: new_end });
+                        }
+                        continue;
+                    }
+                }
+
+                let new_start = entries
+                    .iter()
+                    .filter(|entry| entry.end > day_start && entry.end <= day_end)
+                    .map(|entry| entry.end)
+                    .fold(
+                        day_start + Duration::hours(WORKDAY_START_HOUR),
+                        std::cmp::max,
+                    );
+                let new_end = new_start + grow;
+                let overlaps = entries
+                    .iter()
+                    .any(|entry| entry.start < new_end && entry.end > new_start);
+                if new_end > day_end || overlaps {
+                    return Err(InvalidTimesheetError(format!(
+                        "row {} day {} does not fit in the day",
+                        index, day
+                    )));
+                }
+
+                entries.push(WeekEntry {
+                    id: None,
+                    project: row.project,
+                    activity: row.activity,
+                    project_name: None,
+                    activity_name: None,
+                    start: new_start,
+                    end: new_end,
+                });
+                changes.push(TimesheetChange::Create {
+                    name: row
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| DEFAULT_TASK_NAME.to_string()),
+                    project: row.project,
+                    activity: row.activity,
+                    start: new_start,
+                    end: new_end,
+                });
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timesheets_follow_the_approval_workflow() {
+        use TimesheetStatus::*;
+
+        assert!(Draft.can_transition_to(Submitted));
+        assert!(Submitted.can_transition_to(Approved));
+        assert!(Submitted.can_transition_to(Rejected));
+        assert!(Rejected.can_transition_to(Submitted));
+        assert!(Approved.can_transition_to(Draft));
+        assert!(!Draft.can_transition_to(Approved));
+        assert!(!Rejected.can_transition_to(Approved));
+        assert!(!Approved.can_transition_to(Rejected));
+    }
+
+    #[test]
+    fn only_reviews_need_an_admin() {
+        use TimesheetStatus::*;
+
+        assert!(Submitted.is_review(Approved));
+        assert!(Submitted.is_review(Rejected));
+        assert!(Approved.is_review(Draft));
+        assert!(!Draft.is_review(Submitted));
+        assert!(!Submitted.is_review(Draft));
+        assert!(!Rejected.is_review(Submitted));
+    }
+
+    fn at(time: &str) -> UtcDateTime {
+        time.parse().unwrap()
+    }
+
+    fn monday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2021, 10, 18).unwrap()
+    }
+
+    fn entry(id: ObjectId, project: ObjectId, start: &str, end: &str) -> WeekEntry {
+        WeekEntry {
+            id: Some(id),
+            project: Some(project),
+            activity: None,
+            project_name: None,
+            activity_name: None,
+            start: at(start),
+            end: at(end),
+        }
+    }
+
+    fn row(project: ObjectId, days: [i64; WEEK_DAYS]) -> TimesheetRowRequest {
+        TimesheetRowRequest {
+            project: Some(project),
+            activity: None,
+            name: None,
+            days: days.to_vec(),
+        }
+    }
+
+    #[test]
+    fn builds_one_row_per_project_and_activity() {
+        let project = ObjectId::new();
+        let entries = vec![
+            entry(
+                ObjectId::new(),
+                project,
+                "2021-10-18T09:00:00Z",
+                "2021-10-18T11:00:00Z",
+            ),
+            entry(
+                ObjectId::new(),
+                project,
+                "2021-10-19T23:00:00Z",
+                "2021-10-20T01:00:00Z",
+            ),
+        ];
+
+        let week = build_week(monday(), Tz::UTC, None, &entries);
+
+        assert_eq!(week.rows.len(), 1);
+        assert_eq!(week.days[0], "2021-10-18");
+        let days: Vec<i64> = week.rows[0].days.iter().map(|d| d.seconds).collect();
+        assert_eq!(days, vec![7200, 3600, 3600, 0, 0, 0, 0]);
+        assert_eq!(week.total.seconds, 14400);
+    }
+
+    #[test]
+    fn matching_grid_changes_nothing() {
+        let project = ObjectId::new();
+        let entries = vec![entry(
+            ObjectId::new(),
+            project,
+            "2021-10-18T09:00:00Z",
+            "2021-10-18T11:00:00Z",
+        )];
+
+        let changes = plan_week(
+            monday(),
+            Tz::UTC,
+            &entries,
+            &[row(project, [7200, 0, 0, 0, 0, 0, 0])],
+        )
+        .unwrap();
+
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn plans_creates_resizes_and_deletes() {
+        let project = ObjectId::new();
+        let (first, second) = (ObjectId::new(), ObjectId::new());
+        let entries = vec![
+            entry(
+                first,
+                project,
+                "2021-10-18T09:00:00Z",
+                "2021-10-18T11:00:00Z",
+            ),
+            entry(
+                second,
+                project,
+                "2021-10-19T09:00:00Z",
+                "2021-10-19T10:00:00Z",
+            ),
+        ];
+
+        let changes = plan_week(
+            monday(),
+            Tz::UTC,
+            &entries,
+            &[row(project, [9000, 0, 1800, 0, 0, 0, 0])],
+        )
+        .unwrap();
+
+        assert_eq!(
+            changes,
+            vec![
+                TimesheetChange::Resize {
+                    id: first,
+                    end: at("2021-10-18T11:30:00Z"),
+                },
+                TimesheetChange::Delete { id: second },
+                TimesheetChange::Create {
+                    name: DEFAULT_TASK_NAME.to_string(),
+                    project: Some(project),
+                    activity: None,
+                    start: at("2021-10-20T09:00:00Z"),
+                    end: at("2021-10-20T09:30:00Z"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn new_tasks_do_not_overlap_other_rows() {
+        let (project, other) = (ObjectId::new(), ObjectId::new());
+        let entries = vec![entry(
+            ObjectId::new(),
+            other,
+            "2021-10-18T09:00:00Z",
+            "2021-10-18T12:00:00Z",
+        )];
+
+        let changes = plan_week(
+            monday(),
+            Tz::UTC,
+            &entries,
+            &[row(project, [3600, 0, 0, 0, 0, 0, 0])],
+        )
+        .unwrap();
+
+        assert!(matches!(
+            changes[0],
+            TimesheetChange::Create { start, .. } if start == at("2021-10-18T12:00:00Z")
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_rows() {
+        let project = ObjectId::new();
+        let short = TimesheetRowRequest {
+            days: vec![0; 3],
+            ..row(project, [0; WEEK_DAYS])
+        };
+
+        assert!(plan_week(monday(), Tz::UTC, &[], &[short]).is_err());
+        assert!(plan_week(
+            monday(),
+            Tz::UTC,
+            &[],
+            &[row(project, [-1, 0, 0, 0, 0, 0, 0])]
+        )
+        .is_err());
+        assert!(plan_week(
+            monday(),
+            Tz::UTC,
+            &[],
+            &[row(project, [0; WEEK_DAYS]), row(project, [0; WEEK_DAYS])]
+        )
+        .is_err());
+    }
+}
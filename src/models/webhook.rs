@@ -0,0 +1,175 @@
+use serde::{self, Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+// Deliveries are given up after this many failed attempts
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+pub enum WebhookEvent {
+    #[serde(rename = "task.created")]
+    TaskCreated,
+    #[serde(rename = "task.updated")]
+    TaskUpdated,
+    #[serde(rename = "task.deleted")]
+    TaskDeleted,
+    #[serde(rename = "project.created")]
+    ProjectCreated,
+    #[serde(rename = "project.updated")]
+    ProjectUpdated,
+    #[serde(rename = "project.deleted")]
+    ProjectDeleted,
+    #[serde(rename = "client.created")]
+    ClientCreated,
+    #[serde(rename = "client.updated")]
+    ClientUpdated,
+    #[serde(rename = "client.deleted")]
+    ClientDeleted,
+}
+
+impl WebhookEvent {
+    pub fn is_task_event(&self) -> bool {
+        matches!(
+            self,
+            WebhookEvent::TaskCreated | WebhookEvent::TaskUpdated | WebhookEvent::TaskDeleted
+        )
+    }
+
+    pub fn is_delete_event(&self) -> bool {
+        matches!(
+            self,
+            WebhookEvent::TaskDeleted | WebhookEvent::ProjectDeleted | WebhookEvent::ClientDeleted
+        )
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::TaskCreated => "task.created",
+            WebhookEvent::TaskUpdated => "task.updated",
+            WebhookEvent::TaskDeleted => "task.deleted",
+            WebhookEvent::ProjectCreated => "project.created",
+            WebhookEvent::ProjectUpdated => "project.updated",
+            WebhookEvent::ProjectDeleted => "project.deleted",
+            WebhookEvent::ClientCreated => "client.created",
+            WebhookEvent::ClientUpdated => "client.updated",
+            WebhookEvent::ClientDeleted => "client.deleted",
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = String;
+
+    fn from_str(event: &str) -> Result<Self, Self::Err> {
+        match event {
+            "task.created" => Ok(WebhookEvent::TaskCreated),
+            "task.updated" => Ok(WebhookEvent::TaskUpdated),
+            "task.deleted" => Ok(WebhookEvent::TaskDeleted),
+            "project.created" => Ok(WebhookEvent::ProjectCreated),
+            "project.updated" => Ok(WebhookEvent::ProjectUpdated),
+            "project.deleted" => Ok(WebhookEvent::ProjectDeleted),
+            "client.created" => Ok(WebhookEvent::ClientCreated),
+            "client.updated" => Ok(WebhookEvent::ClientUpdated),
+            "client.deleted" => Ok(WebhookEvent::ClientDeleted),
+            _ => Err(format!("unknown webhook event: {}", event)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct WebhookRequest {
+    pub url: String,
+    // Key of the HMAC-SHA256 signature sent in `x-webhook-signature`
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+}
+
+/// The secret is never sent back.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct WebhookResponse {
+    pub _id: String,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_at: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeliveryStatus {
+    #[default]
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        match status {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "delivered" => Ok(DeliveryStatus::Delivered),
+            "failed" => Ok(DeliveryStatus::Failed),
+            _ => Err(format!("unknown delivery status: {}", status)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct WebhookDeliveryResponse {
+    pub _id: String,
+    pub webhook: String, //hex
+    pub event: String,
+    // JSON body sent to the webhook
+    pub payload: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub response_status: Option<i32>,
+    pub next_attempt_at: Option<String>,
+    pub delivered_at: Option<String>,
+    pub created_at: String,
+}
+
+/// Wait before the next attempt once `attempts` deliveries failed: 30 seconds
+/// doubling each time, at most an hour.
+pub fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.clamp(1, 8) as u32 - 1;
+    std::cmp::min(
+        Duration::from_secs(30 * 2u64.pow(exponent)),
+        Duration::from_secs(3600),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_up_to_an_hour() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(5), Duration::from_secs(480));
+        assert_eq!(retry_delay(20), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn events_use_dotted_names() {
+        for event in [WebhookEvent::TaskCreated, WebhookEvent::ProjectDeleted] {
+            let json = serde_json::to_string(&event).unwrap();
+            assert_eq!(json, format!("\"{}\"", event.as_str()));
+            assert_eq!(event.as_str().parse::<WebhookEvent>(), Ok(event));
+        }
+    }
+}
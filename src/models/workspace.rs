@@ -0,0 +1,225 @@
+use crate::models::task::split_by_day;
+use crate::{error::Error::*, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{self, Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// What happens when a task covers minutes already tracked by the same user.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverlapPolicy {
+    Reject,
+    // Accept the task and report the conflicting ids
+    #[default]
+    Warn,
+    // Shorten the task so it no longer overlaps
+    AutoTrim,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct WorkspaceSettings {
+    #[serde(default)]
+    pub overlap_policy: OverlapPolicy,
+    // Used by reports for projects without their own rule
+    pub rounding: Option<RoundingRule>,
+    // YYYY-MM-DD, tasks starting before this day (midnight UTC, whatever
+    // the time zone of the user) can no longer change
+    pub lock_date: Option<String>,
+    // Names accepted in `x-lock-override`, also needed to change `lock_date`
+    // or `admins`
+    #[serde(default)]
+    pub admins: Vec<String>,
+}
+
+impl WorkspaceSettings {
+    /// First instant still open for changes, if the workspace has a lock date.
+    pub fn locked_until(&self) -> Result<Option<DateTime<Utc>>> {
+        match &self.lock_date {
+            Some(lock_date) => {
+                let day = NaiveDate::parse_from_str(lock_date, "%Y-%m-%d")
+                    .map_err(|_| InvalidDateError(lock_date.to_owned()))?;
+                Ok(Some(day.and_hms_opt(0, 0, 0).unwrap().and_utc()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn is_admin(&self, name: &str) -> bool {
+        self.admins.iter().any(|admin| admin == name)
+    }
+
+    /// The admin-only settings `next` changes: `lock-date` and `admins`.
+    pub fn guarded_changes(&self, next: &WorkspaceSettings) -> Vec<&'static str> {
+        let mut changes = vec![];
+        if self.lock_date != next.lock_date {
+            changes.push("lock-date");
+        }
+        if self.admins != next.admins {
+            changes.push("admins");
+        }
+
+        changes
+    }
+
+    /// The admin changing guarded settings: one of the current admins, or
+    /// while there are none, one of the new ones.
+    pub fn change_admin<'a>(
+        &self,
+        next: &WorkspaceSettings,
+        lock_override: Option<&'a str>,
+    ) -> Result<&'a str> {
+        match lock_override {
+            None => Err(AdminRequiredError),
+            Some(admin) if self.is_admin(admin) => Ok(admin),
+            Some(admin) if self.admins.is_empty() && next.is_admin(admin) => Ok(admin),
+            Some(admin) => Err(UnknownAdminError(admin.to_string())),
+        }
+    }
+}
+
+/// A task write let through a locked period by an admin, or a change of the
+/// lock date or admins.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct LockOverrideResponse {
+    pub _id: String,
+    // Only for task writes
+    pub task: Option<String>, //hex
+    // `create`, `edit`, `split` or `delete` a task, or set the `lock-date` or
+    // `admins`
+    pub action: String,
+    // Value of the `x-lock-override` header
+    pub admin: String,
+    // Lock date in force, the new one for settings changes
+    pub lock_date: String,
+    // The new admins, only for settings changes
+    pub admins: Option<Vec<String>>,
+    pub created_at: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingMode {
+    Up,
+    Down,
+    Nearest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingScope {
+    // Every time entry is rounded on its own
+    PerEntry,
+    // The time of each day is summed first and then rounded
+    PerDay,
+}
+
+/// Billable time rounding, applied when reporting. Stored task times are
+/// never changed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ToSchema)]
+pub struct RoundingRule {
+    pub mode: RoundingMode,
+    // E.g. 6 or 15
+    pub increment_minutes: u32,
+    pub scope: RoundingScope,
+}
+
+impl RoundingRule {
+    pub fn round(&self, seconds: i64) -> i64 {
+        let increment = self.increment_minutes as i64 * 60;
+        if increment == 0 {
+            return seconds;
+        }
+
+        let increments = match self.mode {
+            RoundingMode::Up => (seconds + increment - 1).div_euclid(increment),
+            RoundingMode::Down => seconds.div_euclid(increment),
+            RoundingMode::Nearest => (seconds + increment / 2).div_euclid(increment),
+        };
+
+        increments * increment
+    }
+
+    /// Rounded seconds of a set of time entries, days taken in `tz`.
+    pub fn apply(&self, entries: &[(DateTime<Utc>, DateTime<Utc>)], tz: Tz) -> i64 {
+        match self.scope {
+            RoundingScope::PerEntry => entries
+                .iter()
+                .map(|(start, end)| self.round((*end - *start).num_seconds()))
+                .sum(),
+            RoundingScope::PerDay => {
+                let mut days: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+                for (start, end) in entries {
+                    for (day, seconds) in split_by_day(*start, *end, tz) {
+                        *days.entry(day).or_default() += seconds;
+                    }
+                }
+                days.values().map(|seconds| self.round(*seconds)).sum()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_date_starts_at_utc_midnight() {
+        let settings = WorkspaceSettings {
+            lock_date: Some("2021-11-01".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            settings.locked_until().unwrap(),
+            Some("2021-11-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(WorkspaceSettings::default().locked_until().unwrap(), None);
+        assert!(WorkspaceSettings {
+            lock_date: Some("November".to_string()),
+            ..Default::default()
+        }
+        .locked_until()
+        .is_err());
+    }
+
+    #[test]
+    fn only_admins_change_the_lock() {
+        let open = WorkspaceSettings::default();
+        let bootstrap = WorkspaceSettings {
+            admins: vec!["ana".to_string()],
+            ..Default::default()
+        };
+        let locked = WorkspaceSettings {
+            lock_date: Some("2021-11-01".to_string()),
+            ..bootstrap.clone()
+        };
+
+        assert!(open.guarded_changes(&open).is_empty());
+        assert_eq!(open.guarded_changes(&bootstrap), vec!["admins"]);
+        assert_eq!(open.guarded_changes(&locked), vec!["lock-date", "admins"]);
+
+        // The first admins come from anyone naming themselves
+        assert_eq!(open.change_admin(&bootstrap, Some("ana")).unwrap(), "ana");
+        assert!(open.change_admin(&bootstrap, Some("bo")).is_err());
+        assert!(open.change_admin(&bootstrap, None).is_err());
+        // Then only from them
+        assert_eq!(bootstrap.change_admin(&locked, Some("ana")).unwrap(), "ana");
+        assert!(bootstrap.change_admin(&open, Some("bo")).is_err());
+        assert!(locked.change_admin(&open, None).is_err());
+    }
+
+    #[test]
+    fn only_listed_admins_override_the_lock() {
+        let settings = WorkspaceSettings {
+            admins: vec!["ana".to_string()],
+            ..Default::default()
+        };
+
+        assert!(settings.is_admin("ana"));
+        assert!(!settings.is_admin("Ana"));
+        assert!(!WorkspaceSettings::default().is_admin("ana"));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::models::project::{BudgetAlert, BudgetAlertChannel};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use sha2::Sha256;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Anything able to deliver a plain text email. The default transport only
+/// logs the message so the service runs without a mail server.
+#[async_trait]
+pub trait MailTransport: Debug + Send + Sync {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String>;
+}
+
+#[derive(Debug)]
+pub struct LogMailTransport;
+
+#[async_trait]
+impl MailTransport for LogMailTransport {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String> {
+        eprintln!("email to {}: {}\n{}", to, subject, body);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SmtpMailTransport {
+    from: String,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailTransport {
+    pub fn new(host: &str, port: u16, from: &str) -> Self {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+            .port(port)
+            .build();
+
+        Self {
+            from: from.to_string(),
+            transport,
+        }
+    }
+}
+
+#[async_trait]
+impl MailTransport for SmtpMailTransport {
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), String> {
+        let message = Message::builder()
+            .from(self.from.parse().map_err(|e| format!("{}", e))?)
+            .to(to.parse().map_err(|e| format!("{}", e))?)
+            .subject(subject)
+            .body(body.to_string())
+            .map_err(|e| e.to_string())?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of `payload`, sent as `sha256=<hex>` so receivers
+/// can check a webhook came from us.
+pub fn sign_payload(secret: &str, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key length");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Outcome of one webhook delivery attempt.
+#[derive(Clone, Debug, Default)]
+pub struct WebhookAttempt {
+    pub response_status: Option<u16>,
+    // `None` when the receiver answered with a 2xx status
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    pub mailer: Arc<dyn MailTransport>,
+    pub http: reqwest::Client,
+}
+
+impl Notifier {
+    /// Uses SMTP when `SMTP_HOST` is set, otherwise falls back to logging.
+    pub fn from_env() -> Self {
+        let mailer: Arc<dyn MailTransport> = match std::env::var("SMTP_HOST") {
+            Ok(host) => {
+                let port = std::env::var("SMTP_PORT")
+                    .ok()
+                    .and_then(|port| port.parse().ok())
+                    .unwrap_or(25);
+                let from = std::env::var("SMTP_FROM")
+                    .unwrap_or_else(|_| "time-tracker@localhost".to_string());
+                Arc::new(SmtpMailTransport::new(&host, port, &from))
+            }
+            Err(_) => Arc::new(LogMailTransport),
+        };
+
+        Self {
+            mailer,
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("could not build http client"),
+        }
+    }
+
+    /// Sends the alert to one channel of the project's alert settings.
+    pub async fn send_budget_alert(
+        &self,
+        channel: &BudgetAlertChannel,
+        alert: &BudgetAlert,
+    ) -> Result<(), String> {
+        match channel {
+            BudgetAlertChannel::Webhook(url) => self
+                .http
+                .post(url)
+                .json(alert)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map(|_| ())
+                .map_err(|e| format!("webhook to {}: {}", url, e)),
+            BudgetAlertChannel::Email(email) => {
+                let subject = format!(
+                    "Budget alert: {} reached {}%",
+                    alert.project_name, alert.threshold
+                );
+                let body = format!(
+                    "Project {} has consumed {:.1}% of its estimate.",
+                    alert.project_name, alert.percentage
+                );
+                self.mailer
+                    .send(email, &subject, &body)
+                    .await
+                    .map_err(|e| format!("email to {}: {}", email, e))
+            }
+        }
+    }
+
+    pub async fn send_webhook(
+        &self,
+        url: &str,
+        secret: &str,
+        event: &str,
+        delivery: &str,
+        payload: &str,
+    ) -> WebhookAttempt {
+        let response = self
+            .http
+            .post(url)
+            .header("content-type", "application/json")
+            .header("x-webhook-event", event)
+            .header("x-webhook-delivery", delivery)
+            .header(
+                "x-webhook-signature",
+                format!("sha256={}", sign_payload(secret, payload)),
+            )
+            .body(payload.to_string())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) => {
+                let status = response.status();
+                WebhookAttempt {
+                    response_status: Some(status.as_u16()),
+                    error: (!status.is_success()).then(|| format!("receiver answered {}", status)),
+                }
+            }
+            Err(e) => WebhookAttempt {
+                response_status: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::sync::mpsc;
+    use warp::Filter;
+
+    // Local stand-in for a webhook receiver, answering with `status`
+    async fn receiver(
+        status: u16,
+    ) -> (
+        SocketAddr,
+        mpsc::UnboundedReceiver<(Option<String>, String)>,
+    ) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let route = warp::post()
+            .and(warp::header::optional::<String>("x-webhook-signature"))
+            .and(warp::body::bytes())
+            .map(
+                move |signature: Option<String>, body: warp::hyper::body::Bytes| {
+                    tx.send((signature, String::from_utf8_lossy(&body).to_string()))
+                        .unwrap();
+                    warp::http::StatusCode::from_u16(status).unwrap()
+                },
+            );
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr, rx)
+    }
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign_payload("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_payloads() {
+        let (addr, mut requests) = receiver(200).await;
+        let notifier = Notifier::from_env();
+        let payload = r#"{"event":"task.created"}"#;
+
+        let attempt = notifier
+            .send_webhook(
+                &format!("http://{}/hook", addr),
+                "secret",
+                "task.created",
+                "delivery",
+                payload,
+            )
+            .await;
+
+        assert_eq!(attempt.response_status, Some(200));
+        assert!(attempt.error.is_none());
+        let (signature, body) = requests.recv().await.unwrap();
+        assert_eq!(body, payload);
+        assert_eq!(
+            signature,
+            Some(format!("sha256={}", sign_payload("secret", payload)))
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_failed_deliveries() {
+        let (addr, _requests) = receiver(500).await;
+        let notifier = Notifier::from_env();
+
+        let attempt = notifier
+            .send_webhook(
+                &format!("http://{}/hook", addr),
+                "secret",
+                "task.created",
+                "d",
+                "{}",
+            )
+            .await;
+
+        assert_eq!(attempt.response_status, Some(500));
+        assert!(attempt.error.is_some());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::controllers::{
+    activities, clients, live, projects, reports, seed, tags, tasks, timesheets, webhooks,
+    workspaces,
+};
+use utoipa::OpenApi;
+
+/// OpenAPI document of every route. Schemas are collected from the request
+/// and response types of the handlers.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "rust-mongodb-warp",
+        description = "Time tracking API. Write routes take the workspace from the `x-workspace` header."
+    ),
+    servers((url = "/api/v1")),
+    paths(
+        tasks::fetch_all_tasks_handler,
+        tasks::fetch_tasks_grouped_by_date,
+        tasks::fetch_task_handler,
+        tasks::fetch_task_overlaps_handler,
+        tasks::create_task_handler,
+        tasks::delete_all_tasks_handler,
+        tasks::edit_task_handler,
+        tasks::split_task_handler,
+        tasks::delete_task_handler,
+        projects::fetch_all_projects_handler,
+        projects::fetch_project_handler,
+        projects::fetch_project_budget_handler,
+        projects::create_project_handler,
+        projects::update_project_status_handler,
+        projects::update_project_parent_handler,
+        projects::delete_all_projects_handler,
+        projects::delete_project_handler,
+        activities::fetch_project_activities_handler,
+        activities::create_activity_handler,
+        activities::fetch_activity_suggestions_handler,
+        activities::delete_activity_handler,
+        clients::fetch_all_clients_handler,
+        clients::fetch_client_handler,
+        clients::create_client_handler,
+        clients::edit_client_handler,
+        clients::patch_client_handler,
+        clients::archive_client_handler,
+        clients::unarchive_client_handler,
+        clients::delete_client_handler,
+        tags::fetch_all_tags_handler,
+        tags::create_tag_handler,
+        tags::rename_tag_handler,
+        tags::merge_tag_handler,
+        tags::delete_tag_handler,
+        reports::fetch_summary_report_handler,
+        reports::fetch_activity_report_handler,
+        timesheets::fetch_week_timesheet_handler,
+        timesheets::update_week_timesheet_handler,
+        timesheets::update_timesheet_status_handler,
+        timesheets::fetch_pending_timesheets_handler,
+        workspaces::fetch_workspace_settings_handler,
+        workspaces::update_workspace_settings_handler,
+        workspaces::fetch_lock_overrides_handler,
+        webhooks::fetch_webhooks_handler,
+        webhooks::create_webhook_handler,
+        webhooks::delete_webhook_handler,
+        webhooks::fetch_webhook_deliveries_handler,
+        live::events_handler,
+        live::events_ws_handler,
+        seed::seed_clients,
+        seed::seed_projects,
+        seed::seed_tasks,
+        seed::seed_all_data,
+        seed::remove_all_data,
+    )
+)]
+pub struct ApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DB;
+    use crate::events::EventBus;
+    use crate::notifications::Notifier;
+    use crate::routes;
+    use mongodb::options::ClientOptions;
+    use mongodb::Client;
+    use std::collections::HashSet;
+    use std::time::Duration;
+    use warp::reject::MethodNotAllowed;
+
+    // A client for a server that is not there: the mounted handlers answer
+    // with an error right away instead of touching a real database.
+    async fn unreachable_db() -> DB {
+        let mut options = ClientOptions::parse("mongodb://127.0.0.1:9").await.unwrap();
+        options.server_selection_timeout = Some(Duration::from_millis(50));
+        options.connect_timeout = Some(Duration::from_millis(50));
+
+        DB {
+            client: Client::with_options(options).unwrap(),
+            notifier: Notifier::from_env(),
+            events: EventBus::default(),
+            name: "openapi-routes".to_string(),
+        }
+    }
+
+    // Every operation of the spec as (operation id, method, path), path
+    // parameters filled in
+    fn described_operations() -> Vec<(String, &'static str, String)> {
+        let id = mongodb::bson::oid::ObjectId::new().to_hex();
+
+        ApiDoc::openapi()
+            .paths
+            .paths
+            .iter()
+            .flat_map(|(path, item)| {
+                let path = path
+                    .split('/')
+                    .map(|segment| {
+                        if segment.starts_with('{') {
+                            id.as_str()
+                        } else {
+                            segment
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                [
+                    ("GET", &item.get),
+                    ("PUT", &item.put),
+                    ("POST", &item.post),
+                    ("DELETE", &item.delete),
+                    ("PATCH", &item.patch),
+                ]
+                .into_iter()
+                .filter_map(|(method, operation)| {
+                    let operation = operation.as_ref()?.operation_id.clone()?;
+                    Some((operation, method))
+                })
+                .map(move |(operation, method)| (operation, method, path.clone()))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn every_described_operation_is_served_by_its_route() {
+        let routes = routes::v1_routes(unreachable_db().await, true);
+        let operations = described_operations();
+
+        assert!(!operations.is_empty());
+        let mut missing = vec![];
+        for (operation, method, path) in operations {
+            let route = match routes.iter().find(|route| route.operation == operation) {
+                Some(route) => route,
+                None => {
+                    missing.push(format!("{} is not mounted", operation));
+                    continue;
+                }
+            };
+
+            // Only the route of the operation is asked, so no other route can
+            // answer in its place. The event streams never end, answering at
+            // all is enough.
+            let request = warp::test::request()
+                .method(method)
+                .path(&path)
+                .filter(&route.filter);
+            let rejection = match tokio::time::timeout(Duration::from_secs(2), request).await {
+                Ok(Err(rejection)) => rejection,
+                Ok(Ok(_)) | Err(_) => continue,
+            };
+            // Other rejections (a missing body or query, the handler failing
+            // without a database) come after the path and method matched
+            if rejection.is_not_found() || rejection.find::<MethodNotAllowed>().is_some() {
+                missing.push(format!("{} does not serve {} {}", operation, method, path));
+            }
+        }
+        assert!(
+            missing.is_empty(),
+            "spec operations not served as described: {:?}",
+            missing
+        );
+    }
+
+    #[tokio::test]
+    async fn every_mounted_route_is_described() {
+        let operation_ids: HashSet<String> = described_operations()
+            .into_iter()
+            .map(|(operation, _, _)| operation)
+            .collect();
+        let routes = routes::v1_routes(unreachable_db().await, true);
+
+        let mut mounted = HashSet::new();
+        for route in &routes {
+            assert!(
+                mounted.insert(route.operation),
+                "{} is mounted twice",
+                route.operation
+            );
+        }
+        let missing: Vec<&str> = mounted
+            .into_iter()
+            .filter(|operation| !operation_ids.contains(*operation))
+            .collect();
+        assert!(
+            missing.is_empty(),
+            "routes missing from the spec: {:?}",
+            missing
+        );
+    }
+
+    #[test]
+    fn spec_paths_use_route_parameters() {
+        let spec = ApiDoc::openapi();
+
+        assert!(spec.paths.paths.contains_key("/tasks/{id}/split"));
+        assert!(spec.components.unwrap().schemas.contains_key("TaskRequest"));
+    }
+}
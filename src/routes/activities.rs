@@ -0,0 +1,30 @@
+use super::{with_db, Route};
+use crate::controllers::activities;
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let activities = warp::path("activities");
+
+    vec![
+        Route::new(
+            "fetch_activity_suggestions_handler",
+            activities
+                .and(warp::get())
+                .and(warp::path("suggestions"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_db(db.clone()))
+                .and_then(activities::fetch_activity_suggestions_handler),
+        ),
+        Route::new(
+            "delete_activity_handler",
+            activities
+                .and(warp::delete())
+                .and(warp::path::param())
+                .and(warp::path::end())
+                .and(with_db(db.clone()))
+                .and_then(activities::delete_activity_handler),
+        ),
+    ]
+}
@@ -0,0 +1,90 @@
+use super::{with_db, with_workspace, Route};
+use crate::controllers::clients;
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let clients = warp::path("clients");
+
+    vec![
+        Route::new(
+            "fetch_all_clients_handler",
+            clients
+                .and(warp::get())
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_db(db.clone()))
+                .and_then(clients::fetch_all_clients_handler),
+        ),
+        Route::new(
+            "archive_client_handler",
+            clients
+                .and(warp::post())
+                .and(warp::path::param())
+                .and(warp::path("archive"))
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(clients::archive_client_handler),
+        ),
+        Route::new(
+            "unarchive_client_handler",
+            clients
+                .and(warp::post())
+                .and(warp::path::param())
+                .and(warp::path("unarchive"))
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(clients::unarchive_client_handler),
+        ),
+        Route::new(
+            "fetch_client_handler",
+            clients
+                .and(warp::get())
+                .and(warp::path::param())
+                .and(with_db(db.clone()))
+                .and_then(clients::fetch_client_handler),
+        ),
+        Route::new(
+            "create_client_handler",
+            clients
+                .and(warp::post())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(clients::create_client_handler),
+        ),
+        Route::new(
+            "edit_client_handler",
+            clients
+                .and(warp::put())
+                .and(warp::path::param())
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(clients::edit_client_handler),
+        ),
+        Route::new(
+            "patch_client_handler",
+            clients
+                .and(warp::patch())
+                .and(warp::path::param())
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(clients::patch_client_handler),
+        ),
+        Route::new(
+            "delete_client_handler",
+            clients
+                .and(warp::delete())
+                .and(warp::path::param())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(clients::delete_client_handler),
+        ),
+    ]
+}
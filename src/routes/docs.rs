@@ -0,0 +1,17 @@
+use crate::controllers::docs;
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+pub fn routes() -> BoxedFilter<(Response,)> {
+    warp::get()
+        .and(warp::path("openapi.json"))
+        .and(warp::path::end())
+        .and_then(docs::docs_openapi_handler)
+        .or(warp::get()
+            .and(warp::path("docs"))
+            .and(warp::path::end())
+            .and_then(docs::docs_ui_handler))
+        .map(Reply::into_response)
+        .boxed()
+}
@@ -0,0 +1,33 @@
+use super::{with_db, Route};
+use crate::controllers::live;
+use crate::db::DB;
+use crate::events::EventsQuery;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let events = warp::path("events");
+
+    vec![
+        Route::new(
+            "events_handler",
+            events
+                .and(warp::get())
+                .and(warp::path::end())
+                .and(warp::query::<EventsQuery>())
+                .and(warp::header::optional::<String>("x-workspace"))
+                .and(with_db(db.clone()))
+                .and_then(live::events_handler),
+        ),
+        Route::new(
+            "events_ws_handler",
+            events
+                .and(warp::path("ws"))
+                .and(warp::path::end())
+                .and(warp::ws())
+                .and(warp::query::<EventsQuery>())
+                .and(warp::header::optional::<String>("x-workspace"))
+                .and(with_db(db.clone()))
+                .and_then(live::events_ws_handler),
+        ),
+    ]
+}
@@ -0,0 +1,194 @@
+pub mod activities;
+pub mod clients;
+pub mod docs;
+pub mod events;
+pub mod projects;
+pub mod reports;
+pub mod seed;
+pub mod tags;
+pub mod tasks;
+pub mod timesheets;
+pub mod webhooks;
+pub mod workspaces;
+
+use crate::db::{DB, DEFAULT_WORKSPACE};
+use crate::error;
+use std::convert::Infallible;
+use warp::filters::{path::FullPath, BoxedFilter};
+use warp::http::HeaderValue;
+use warp::reply::Response;
+use warp::{hyper::Method, Filter, Rejection, Reply};
+
+// Unversioned paths keep answering until then, see `deprecated`
+const LEGACY_API_SUNSET: &str = "Fri, 30 Apr 2027 00:00:00 GMT";
+
+/// Every route of the service, as served by `main`. The `/seed` routes replace
+/// or delete every document, so they are only mounted when `seed_routes` is set.
+pub fn api(
+    db: DB,
+    seed_routes: bool,
+) -> impl Filter<Extract = (impl Reply,), Error = Infallible> + Clone {
+    let cors = warp::cors()
+        .allow_any_origin()
+        .allow_headers(vec!["content-type", "x-workspace", "x-lock-override"])
+        .allow_methods(&[
+            Method::GET,
+            Method::POST,
+            Method::PATCH,
+            Method::PUT,
+            Method::DELETE,
+        ])
+        // Lets browsers read the deprecation notice of legacy paths
+        .expose_headers(vec!["deprecation", "sunset", "link"]);
+
+    let api_v1 = v1(db, seed_routes);
+
+    versioned("v1", api_v1.clone())
+        .or(deprecated(api_v1))
+        .or(docs::routes())
+        .with(cors)
+        .recover(error::handle_rejection)
+}
+
+/// One mounted route, named after the handler it ends in. The name is the
+/// operation id of the handler in the OpenAPI document.
+pub struct Route {
+    pub operation: &'static str,
+    pub filter: BoxedFilter<(Response,)>,
+}
+
+impl Route {
+    pub fn new<F, R>(operation: &'static str, filter: F) -> Route
+    where
+        F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+        R: Reply + 'static,
+    {
+        Route {
+            operation,
+            filter: filter.map(Reply::into_response).boxed(),
+        }
+    }
+}
+
+/// Tries `routes` in order, like chaining them with `or`.
+pub fn mount(routes: Vec<Route>) -> BoxedFilter<(Response,)> {
+    routes
+        .into_iter()
+        .map(|route| route.filter)
+        .reduce(|mounted, filter| mounted.or(filter).unify().boxed())
+        .expect("nothing to mount")
+}
+
+/// Routes of the first API version, in the order they are tried.
+pub fn v1_routes(db: DB, seed_routes: bool) -> Vec<Route> {
+    let mut routes = tasks::routes(db.clone());
+    routes.extend(projects::routes(db.clone()));
+    routes.extend(clients::routes(db.clone()));
+    routes.extend(tags::routes(db.clone()));
+    routes.extend(activities::routes(db.clone()));
+    routes.extend(workspaces::routes(db.clone()));
+    routes.extend(reports::routes(db.clone()));
+    routes.extend(timesheets::routes(db.clone()));
+    routes.extend(webhooks::routes(db.clone()));
+    routes.extend(events::routes(db.clone()));
+    if seed_routes {
+        routes.extend(seed::routes(db));
+    }
+
+    routes
+}
+
+/// Routes of the first API version, without the `/api/v1` prefix.
+pub fn v1(db: DB, seed_routes: bool) -> BoxedFilter<(Response,)> {
+    mount(v1_routes(db, seed_routes))
+}
+
+/// Mounts `routes` under `/api/{version}`. A later version lists the routes it
+/// changes first and falls back to the previous one for the rest, e.g.
+/// `versioned("v2", v2_routes.or(v1(db, seed_routes)).unify().boxed())`.
+pub fn versioned(
+    version: &'static str,
+    routes: BoxedFilter<(Response,)>,
+) -> BoxedFilter<(Response,)> {
+    warp::path("api")
+        .and(warp::path(version))
+        .and(routes)
+        .boxed()
+}
+
+// Legacy unversioned paths, flagged with `Deprecation`, `Sunset` and a link to
+// their `/api/v1` successor
+pub fn deprecated(routes: BoxedFilter<(Response,)>) -> BoxedFilter<(Response,)> {
+    warp::path::full()
+        .and(routes)
+        .map(|path: FullPath, mut response: Response| {
+            let headers = response.headers_mut();
+            headers.insert("deprecation", HeaderValue::from_static("true"));
+            headers.insert("sunset", HeaderValue::from_static(LEGACY_API_SUNSET));
+            let successor = format!("</api/v1{}>; rel=\"successor-version\"", path.as_str());
+            if let Ok(link) = HeaderValue::from_str(&successor) {
+                headers.insert("link", link);
+            }
+            response
+        })
+        .boxed()
+}
+
+fn with_db(db: DB) -> impl Filter<Extract = (DB,), Error = Infallible> + Clone {
+    warp::any().map(move || db.clone())
+}
+
+fn with_workspace() -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-workspace")
+        .map(|workspace: Option<String>| workspace.unwrap_or_else(|| DEFAULT_WORKSPACE.to_string()))
+}
+
+// Admins send their name in `x-lock-override` to write tasks in a locked period
+fn with_lock_override() -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-lock-override")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping() -> BoxedFilter<(Response,)> {
+        warp::path("ping")
+            .and(warp::path::end())
+            .map(|| "pong".into_response())
+            .boxed()
+    }
+
+    #[tokio::test]
+    async fn routes_are_mounted_under_their_version() {
+        let routes = versioned("v1", ping()).or(deprecated(ping()));
+
+        let current = warp::test::request()
+            .path("/api/v1/ping")
+            .reply(&routes)
+            .await;
+        assert_eq!(current.status(), 200);
+        assert!(current.headers().get("deprecation").is_none());
+
+        let legacy = warp::test::request().path("/ping").reply(&routes).await;
+        assert_eq!(legacy.status(), 200);
+        assert_eq!(legacy.headers()["deprecation"], "true");
+        assert_eq!(legacy.headers()["sunset"], LEGACY_API_SUNSET);
+        assert_eq!(
+            legacy.headers()["link"],
+            "</api/v1/ping>; rel=\"successor-version\""
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_the_openapi_document() {
+        let reply = warp::test::request()
+            .path("/openapi.json")
+            .reply(&docs::routes())
+            .await;
+
+        assert_eq!(reply.status(), 200);
+        let spec: serde_json::Value = serde_json::from_slice(reply.body()).unwrap();
+        assert!(spec["paths"]["/tasks"].is_object());
+    }
+}
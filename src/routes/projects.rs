@@ -1 +1,102 @@
+use super::{with_db, with_workspace, Route};
+use crate::controllers::{activities, projects};
+use crate::db::DB;
+use warp::Filter;
 
+pub fn routes(db: DB) -> Vec<Route> {
+    let projects = warp::path("projects");
+
+    vec![
+        Route::new(
+            "create_project_handler",
+            projects
+                .and(warp::post())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(projects::create_project_handler),
+        ),
+        Route::new(
+            "fetch_all_projects_handler",
+            projects
+                .and(warp::get())
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_db(db.clone()))
+                .and_then(projects::fetch_all_projects_handler),
+        ),
+        Route::new(
+            "fetch_project_budget_handler",
+            projects
+                .and(warp::get())
+                .and(warp::path::param())
+                .and(warp::path("budget"))
+                .and(warp::path::end())
+                .and(with_db(db.clone()))
+                .and_then(projects::fetch_project_budget_handler),
+        ),
+        Route::new(
+            "fetch_project_activities_handler",
+            projects
+                .and(warp::get())
+                .and(warp::path::param())
+                .and(warp::path("activities"))
+                .and(warp::path::end())
+                .and(with_db(db.clone()))
+                .and_then(activities::fetch_project_activities_handler),
+        ),
+        Route::new(
+            "create_activity_handler",
+            projects
+                .and(warp::post())
+                .and(warp::path::param())
+                .and(warp::path("activities"))
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_db(db.clone()))
+                .and_then(activities::create_activity_handler),
+        ),
+        Route::new(
+            "fetch_project_handler",
+            projects
+                .and(warp::get())
+                .and(warp::path::param())
+                .and(warp::path::end())
+                .and(with_db(db.clone()))
+                .and_then(projects::fetch_project_handler),
+        ),
+        Route::new(
+            "update_project_status_handler",
+            projects
+                .and(warp::put())
+                .and(warp::path::param())
+                .and(warp::path("status"))
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(projects::update_project_status_handler),
+        ),
+        Route::new(
+            "update_project_parent_handler",
+            projects
+                .and(warp::put())
+                .and(warp::path::param())
+                .and(warp::path("parent"))
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(projects::update_project_parent_handler),
+        ),
+        Route::new(
+            "delete_project_handler",
+            projects
+                .and(warp::delete())
+                .and(warp::path::param())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(projects::delete_project_handler),
+        ),
+    ]
+}
@@ -0,0 +1,33 @@
+use super::{with_db, with_workspace, Route};
+use crate::controllers::reports;
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let reports = warp::path("reports");
+
+    vec![
+        Route::new(
+            "fetch_summary_report_handler",
+            reports
+                .and(warp::get())
+                .and(warp::path("summary"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(reports::fetch_summary_report_handler),
+        ),
+        Route::new(
+            "fetch_activity_report_handler",
+            reports
+                .and(warp::get())
+                .and(warp::path("activities"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(reports::fetch_activity_report_handler),
+        ),
+    ]
+}
@@ -0,0 +1,69 @@
+use super::{with_db, Route};
+use crate::controllers::{projects, seed, tasks};
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let seed = warp::path("seed").and(warp::post());
+
+    vec![
+        Route::new(
+            "seed_clients",
+            seed.and(warp::path("clients"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_db(db.clone()))
+                .and_then(seed::seed_clients),
+        ),
+        Route::new(
+            "seed_projects",
+            seed.and(warp::path("projects"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_db(db.clone()))
+                .and_then(seed::seed_projects),
+        ),
+        Route::new(
+            "seed_tasks",
+            seed.and(warp::path("tasks"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_db(db.clone()))
+                .and_then(seed::seed_tasks),
+        ),
+        Route::new(
+            "seed_all_data",
+            seed.and(warp::path("all"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_db(db.clone()))
+                .and_then(seed::seed_all_data),
+        ),
+        Route::new(
+            "remove_all_data",
+            seed.and(warp::path("remove"))
+                .and(warp::path::end())
+                .and(with_db(db.clone()))
+                .and_then(seed::remove_all_data),
+        ),
+        // As destructive as seeding, so only served along with it
+        Route::new(
+            "delete_all_tasks_handler",
+            warp::path("tasks")
+                .and(warp::delete())
+                .and(warp::path("dangerously-delete-all-tasks"))
+                .and(warp::path::end())
+                .and(with_db(db.clone()))
+                .and_then(tasks::delete_all_tasks_handler),
+        ),
+        Route::new(
+            "delete_all_projects_handler",
+            warp::path("projects")
+                .and(warp::delete())
+                .and(warp::path("dangerously-delete-all-projects"))
+                .and(warp::path::end())
+                .and(with_db(db.clone()))
+                .and_then(projects::delete_all_projects_handler),
+        ),
+    ]
+}
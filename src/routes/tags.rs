@@ -0,0 +1,58 @@
+use super::{with_db, with_workspace, Route};
+use crate::controllers::tags;
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let tags = warp::path("tags");
+
+    vec![
+        Route::new(
+            "fetch_all_tags_handler",
+            tags.and(warp::get())
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tags::fetch_all_tags_handler),
+        ),
+        Route::new(
+            "create_tag_handler",
+            tags.and(warp::post())
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tags::create_tag_handler),
+        ),
+        Route::new(
+            "merge_tag_handler",
+            tags.and(warp::post())
+                .and(warp::path::param())
+                .and(warp::path("merge"))
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tags::merge_tag_handler),
+        ),
+        Route::new(
+            "rename_tag_handler",
+            tags.and(warp::put())
+                .and(warp::path::param())
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tags::rename_tag_handler),
+        ),
+        Route::new(
+            "delete_tag_handler",
+            tags.and(warp::delete())
+                .and(warp::path::param())
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tags::delete_tag_handler),
+        ),
+    ]
+}
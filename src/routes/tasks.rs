@@ -0,0 +1,95 @@
+use super::{with_db, with_lock_override, with_workspace, Route};
+use crate::controllers::tasks;
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let tasks = warp::path("tasks");
+
+    vec![
+        Route::new(
+            "create_task_handler",
+            tasks
+                .and(warp::post())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_lock_override())
+                .and(with_db(db.clone()))
+                .and_then(tasks::create_task_handler),
+        ),
+        Route::new(
+            "split_task_handler",
+            tasks
+                .and(warp::post())
+                .and(warp::path::param())
+                .and(warp::path("split"))
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_lock_override())
+                .and(with_db(db.clone()))
+                .and_then(tasks::split_task_handler),
+        ),
+        Route::new(
+            "fetch_task_overlaps_handler",
+            tasks
+                .and(warp::get())
+                .and(warp::path("overlaps"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tasks::fetch_task_overlaps_handler),
+        ),
+        Route::new(
+            "fetch_all_tasks_handler",
+            tasks
+                .and(warp::get())
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tasks::fetch_all_tasks_handler),
+        ),
+        Route::new(
+            "fetch_task_handler",
+            tasks
+                .and(warp::get())
+                .and(warp::path::param())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tasks::fetch_task_handler),
+        ),
+        Route::new(
+            "fetch_tasks_grouped_by_date",
+            tasks
+                .and(warp::get())
+                .and(warp::path("group"))
+                .and(warp::query())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(tasks::fetch_tasks_grouped_by_date),
+        ),
+        Route::new(
+            "edit_task_handler",
+            tasks
+                .and(warp::put())
+                .and(warp::path::param())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_lock_override())
+                .and(with_db(db.clone()))
+                .and_then(tasks::edit_task_handler),
+        ),
+        Route::new(
+            "delete_task_handler",
+            tasks
+                .and(warp::delete())
+                .and(warp::path::param())
+                .and(with_workspace())
+                .and(with_lock_override())
+                .and(with_db(db.clone()))
+                .and_then(tasks::delete_task_handler),
+        ),
+    ]
+}
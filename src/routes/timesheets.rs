@@ -0,0 +1,55 @@
+use super::{with_db, with_workspace, Route};
+use crate::controllers::timesheets;
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let timesheets = warp::path("timesheets");
+
+    vec![
+        Route::new(
+            "fetch_week_timesheet_handler",
+            timesheets
+                .and(warp::get())
+                .and(warp::path("week"))
+                .and(warp::path::end())
+                .and(warp::query())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(timesheets::fetch_week_timesheet_handler),
+        ),
+        Route::new(
+            "update_week_timesheet_handler",
+            timesheets
+                .and(warp::put())
+                .and(warp::path("week"))
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(timesheets::update_week_timesheet_handler),
+        ),
+        Route::new(
+            "update_timesheet_status_handler",
+            timesheets
+                .and(warp::put())
+                .and(warp::path("week"))
+                .and(warp::path("status"))
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(timesheets::update_timesheet_status_handler),
+        ),
+        Route::new(
+            "fetch_pending_timesheets_handler",
+            timesheets
+                .and(warp::get())
+                .and(warp::path("pending"))
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(timesheets::fetch_pending_timesheets_handler),
+        ),
+    ]
+}
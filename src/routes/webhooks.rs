@@ -0,0 +1,51 @@
+use super::{with_db, with_workspace, Route};
+use crate::controllers::webhooks;
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let webhooks = warp::path("webhooks");
+
+    vec![
+        Route::new(
+            "fetch_webhooks_handler",
+            webhooks
+                .and(warp::get())
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(webhooks::fetch_webhooks_handler),
+        ),
+        Route::new(
+            "create_webhook_handler",
+            webhooks
+                .and(warp::post())
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(webhooks::create_webhook_handler),
+        ),
+        Route::new(
+            "fetch_webhook_deliveries_handler",
+            webhooks
+                .and(warp::get())
+                .and(warp::path::param())
+                .and(warp::path("deliveries"))
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(webhooks::fetch_webhook_deliveries_handler),
+        ),
+        Route::new(
+            "delete_webhook_handler",
+            webhooks
+                .and(warp::delete())
+                .and(warp::path::param())
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(webhooks::delete_webhook_handler),
+        ),
+    ]
+}
@@ -0,0 +1,43 @@
+use super::{with_db, with_lock_override, with_workspace, Route};
+use crate::controllers::workspaces;
+use crate::db::DB;
+use warp::Filter;
+
+pub fn routes(db: DB) -> Vec<Route> {
+    let workspace = warp::path("workspace");
+
+    vec![
+        Route::new(
+            "fetch_workspace_settings_handler",
+            workspace
+                .and(warp::get())
+                .and(warp::path("settings"))
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(workspaces::fetch_workspace_settings_handler),
+        ),
+        Route::new(
+            "update_workspace_settings_handler",
+            workspace
+                .and(warp::put())
+                .and(warp::path("settings"))
+                .and(warp::path::end())
+                .and(warp::body::json())
+                .and(with_workspace())
+                .and(with_lock_override())
+                .and(with_db(db.clone()))
+                .and_then(workspaces::update_workspace_settings_handler),
+        ),
+        Route::new(
+            "fetch_lock_overrides_handler",
+            workspace
+                .and(warp::get())
+                .and(warp::path("lock-overrides"))
+                .and(warp::path::end())
+                .and(with_workspace())
+                .and(with_db(db.clone()))
+                .and_then(workspaces::fetch_lock_overrides_handler),
+        ),
+    ]
+}
@@ -0,0 +1,421 @@
+use crate::db::DEFAULT_WORKSPACE;
+use crate::models::project::{Estimate, EstimateKind, EstimateRecurrence, ProjectStatus};
+use crate::{error::Error::*, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use fake::faker::company::en::{Bs, CompanyName};
+use fake::faker::internet::en::SafeEmail;
+use fake::faker::job::en::Title;
+use fake::faker::name::en::Name;
+use fake::Fake;
+use mongodb::bson::{self, doc, oid::ObjectId, Document};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{self, Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const PROJECT_COLORS: [&str; 10] = [
+    "#61e294ff",
+    "#7bcdbaff",
+    "#9799caff",
+    "#bd93d8ff",
+    "#b47aeaff",
+    "#d3d5d4ff",
+    "#a2c5acff",
+    "#9db5b2ff",
+    "#878e99ff",
+    "#7f6a93ff",
+];
+
+// Seeded tasks belong to one of these users
+const SEED_USERS: [&str; 4] = ["ana", "bruno", "carla", "diego"];
+
+// Upper bounds of the counts, so a typo can not fill the database
+const MAX_CLIENTS: usize = 1_000;
+const MAX_PROJECTS: usize = 10_000;
+const MAX_TASKS: usize = 100_000;
+const MAX_DAYS: u32 = 3_650;
+
+// Tasks start between 9:00 and 17:00 (UTC) and end by 18:00
+const WORKDAY_START_MINUTES: u32 = 9 * 60;
+const WORKDAY_END_MINUTES: u32 = 18 * 60;
+const LATEST_START_MINUTES: u32 = 17 * 60;
+// Tasks of a user on a day, one every quarter hour from 9:00 to 17:00
+const TASKS_PER_DAY: usize = ((LATEST_START_MINUTES - WORKDAY_START_MINUTES) / 15 + 1) as usize;
+
+/// What to generate. The same options always give the same data, as long as
+/// `until` is set (it defaults to today).
+#[derive(Clone, Debug, Deserialize, Serialize, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+#[serde(default)]
+pub struct SeedOptions {
+    pub seed: u64,
+    pub clients: usize,
+    pub projects: usize,
+    pub tasks: usize,
+    // Tasks are spread over the weekdays of this many days, ending `until`
+    pub days: u32,
+    // YYYY-MM-DD
+    pub until: Option<String>,
+}
+
+impl Default for SeedOptions {
+    fn default() -> Self {
+        SeedOptions {
+            seed: 42,
+            clients: 5,
+            projects: 10,
+            tasks: 200,
+            days: 30,
+            until: None,
+        }
+    }
+}
+
+impl SeedOptions {
+    pub fn validate(&self) -> Result<()> {
+        if self.clients > MAX_CLIENTS || self.projects > MAX_PROJECTS || self.tasks > MAX_TASKS {
+            return Err(InvalidSeedError(format!(
+                "at most {} clients, {} projects and {} tasks",
+                MAX_CLIENTS, MAX_PROJECTS, MAX_TASKS
+            )));
+        }
+        if self.days == 0 || self.days > MAX_DAYS {
+            return Err(InvalidSeedError(format!(
+                "days must be between 1 and {}",
+                MAX_DAYS
+            )));
+        }
+        self.last_day()?;
+
+        Ok(())
+    }
+
+    fn last_day(&self) -> Result<NaiveDate> {
+        match &self.until {
+            Some(until) => NaiveDate::parse_from_str(until, "%Y-%m-%d")
+                .map_err(|_| InvalidDateError(until.to_owned())),
+            None => Ok(Utc::now().date_naive()),
+        }
+    }
+
+    fn first_day(&self) -> Result<NaiveDate> {
+        Ok(self.last_day()? - Duration::days(self.days as i64 - 1))
+    }
+
+    // Every collection draws from a stream of its own, so seeding one of them
+    // alone gives the same documents as seeding everything
+    fn rng(&self, stream: u64) -> StdRng {
+        StdRng::seed_from_u64(self.seed.wrapping_mul(3).wrapping_add(stream))
+    }
+}
+
+/// Number of documents added by a seed.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct SeedSummary {
+    pub clients: usize,
+    pub projects: usize,
+    pub tasks: usize,
+}
+
+fn object_id(rng: &mut StdRng) -> ObjectId {
+    ObjectId::from_bytes(rng.gen())
+}
+
+fn midnight(day: NaiveDate) -> DateTime<Utc> {
+    day.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+pub fn generate_clients(options: &SeedOptions) -> Result<Vec<Document>> {
+    let mut rng = options.rng(0);
+    let created_at = midnight(options.first_day()?);
+
+    let clients = (0..options.clients)
+        .map(|_| {
+            doc! {
+                "_id": object_id(&mut rng),
+                "workspace": DEFAULT_WORKSPACE,
+                "name": CompanyName().fake_with_rng::<String, _>(&mut rng),
+                "contacts": [{
+                    "name": Name().fake_with_rng::<String, _>(&mut rng),
+                    "email": SafeEmail().fake_with_rng::<String, _>(&mut rng),
+                    "role": Title().fake_with_rng::<String, _>(&mut rng),
+                }],
+                "currency": "EUR",
+                "hourly_rate": rng.gen_range(40..=120) as f64,
+                "archived": false,
+                "created_at": created_at,
+                "updated_at": created_at,
+            }
+        })
+        .collect();
+
+    Ok(clients)
+}
+
+/// Projects of random clients among `client_ids`.
+pub fn generate_projects(options: &SeedOptions, client_ids: &[ObjectId]) -> Result<Vec<Document>> {
+    if options.projects > 0 && client_ids.is_empty() {
+        return Err(InvalidSeedError(
+            "projects need at least one client".to_string(),
+        ));
+    }
+
+    let mut rng = options.rng(1);
+    let created_at = midnight(options.first_day()?);
+
+    let projects = (0..options.projects)
+        .map(|_| {
+            doc! {
+                "_id": object_id(&mut rng),
+                "workspace": DEFAULT_WORKSPACE,
+                "client": client_ids.choose(&mut rng),
+                "name": CompanyName().fake_with_rng::<String, _>(&mut rng),
+                "color": PROJECT_COLORS.choose(&mut rng),
+                "estimate": bson::to_bson(&Estimate {
+                    kind: EstimateKind::Hours,
+                    amount: rng.gen_range(10..=200) as f64,
+                    recurrence: EstimateRecurrence::Total,
+                    hourly_rate: None,
+                })
+                .unwrap(),
+                "status": ProjectStatus::Active.as_str(),
+                "created_at": created_at,
+                "updated_at": created_at,
+            }
+        })
+        .collect();
+
+    Ok(projects)
+}
+
+/// Tasks of random projects among `project_ids` and users among `SEED_USERS`,
+/// on the weekdays of the span and within working hours, lasting 15 minutes
+/// to 3 hours. The tasks of a user on a day follow one another, never
+/// overlapping.
+pub fn generate_tasks(options: &SeedOptions, project_ids: &[ObjectId]) -> Result<Vec<Document>> {
+    if options.tasks > 0 && project_ids.is_empty() {
+        return Err(InvalidSeedError(
+            "tasks need at least one project".to_string(),
+        ));
+    }
+
+    let mut rng = options.rng(2);
+    let first_day = options.first_day()?;
+    let weekdays: Vec<NaiveDate> = first_day
+        .iter_days()
+        .take(options.days as usize)
+        .filter(|day| !matches!(day.weekday(), Weekday::Sat | Weekday::Sun))
+        .collect();
+    // A span made only of a weekend still gets its tasks
+    let days = if weekdays.is_empty() {
+        vec![first_day]
+    } else {
+        weekdays
+    };
+
+    // How many tasks each user gets on each day, at most one per quarter hour
+    let slots: Vec<(NaiveDate, &str)> = days
+        .iter()
+        .flat_map(|day| SEED_USERS.iter().map(move |user| (*day, *user)))
+        .collect();
+    if options.tasks > slots.len() * TASKS_PER_DAY {
+        return Err(InvalidSeedError(format!(
+            "{} tasks do not fit in {} working days of {} users",
+            options.tasks,
+            days.len(),
+            SEED_USERS.len()
+        )));
+    }
+    let mut counts = vec![0; slots.len()];
+    let mut open: Vec<usize> = (0..slots.len()).collect();
+    for _ in 0..options.tasks {
+        let index = rng.gen_range(0..open.len());
+        counts[open[index]] += 1;
+        if counts[open[index]] == TASKS_PER_DAY {
+            open.swap_remove(index);
+        }
+    }
+
+    let mut tasks = Vec::with_capacity(options.tasks);
+    for ((day, user), count) in slots.into_iter().zip(counts) {
+        let day = midnight(day);
+        let mut free_from = WORKDAY_START_MINUTES;
+
+        for left in (0..count as u32).rev() {
+            // Leaves a quarter hour, starting by 17:00, to each task still to come
+            let latest_start = LATEST_START_MINUTES - left * 15;
+            let latest_end = match left {
+                0 => WORKDAY_END_MINUTES,
+                _ => latest_start + 15,
+            };
+
+            // Quarter hours, after a break of up to an hour
+            let earliest_start = free_from.div_ceil(15) * 15;
+            let breaks = rng.gen_range(0..=std::cmp::min(4, (latest_start - earliest_start) / 15));
+            let start = earliest_start + breaks * 15;
+            let end = std::cmp::min(start + rng.gen_range(3..=36) * 5, latest_end);
+            free_from = end;
+
+            let initial_time = day + Duration::minutes(start as i64);
+            let end_time = day + Duration::minutes(end as i64);
+            tasks.push(doc! {
+                "_id": object_id(&mut rng),
+                "workspace": DEFAULT_WORKSPACE,
+                "name": Bs().fake_with_rng::<String, _>(&mut rng),
+                "initial_time": initial_time,
+                "end_time": end_time,
+                "project": project_ids.choose(&mut rng),
+                "user": user,
+                "tags": [],
+                "created_at": end_time,
+                "updated_at": end_time,
+            });
+        }
+    }
+
+    Ok(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> SeedOptions {
+        SeedOptions {
+            until: Some("2021-10-31".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_data() {
+        let options = options();
+        let clients = generate_clients(&options).unwrap();
+        assert_eq!(clients, generate_clients(&options).unwrap());
+        assert_eq!(clients.len(), options.clients);
+
+        let other = SeedOptions { seed: 7, ..options };
+        assert_ne!(clients, generate_clients(&other).unwrap());
+    }
+
+    #[test]
+    fn picks_every_client_even_a_single_one() {
+        let options = SeedOptions {
+            projects: 50,
+            ..options()
+        };
+        let clients: Vec<ObjectId> = (1..=3u8).map(|n| ObjectId::from_bytes([n; 12])).collect();
+
+        let projects = generate_projects(&options, &clients).unwrap();
+        assert_eq!(projects.len(), 50);
+        for client in &clients {
+            assert!(projects
+                .iter()
+                .any(|project| project.get_object_id("client") == Ok(*client)));
+        }
+
+        let projects = generate_projects(&options, &clients[..1]).unwrap();
+        assert_eq!(projects[0].get_object_id("client"), Ok(clients[0]));
+        assert!(generate_projects(&options, &[]).is_err());
+    }
+
+    #[test]
+    fn tasks_fall_on_working_hours_of_the_span() {
+        let options = options();
+        let projects = [ObjectId::from_bytes([1; 12])];
+        let first_day = midnight(options.first_day().unwrap());
+        let after_last_day = midnight(options.last_day().unwrap()) + Duration::days(1);
+
+        let tasks = generate_tasks(&options, &projects).unwrap();
+        assert_eq!(tasks.len(), options.tasks);
+        for task in &tasks {
+            let initial_time = task.get_datetime("initial_time").unwrap().to_chrono();
+            let end_time = task.get_datetime("end_time").unwrap().to_chrono();
+
+            assert!(SEED_USERS.contains(&task.get_str("user").unwrap()));
+            assert!(initial_time >= first_day && end_time < after_last_day);
+            assert!(!matches!(
+                initial_time.weekday(),
+                Weekday::Sat | Weekday::Sun
+            ));
+            assert!(end_time > initial_time);
+            assert!(end_time - initial_time <= Duration::hours(3));
+            let start = initial_time
+                .time()
+                .signed_duration_since(chrono::NaiveTime::MIN);
+            let end = end_time
+                .time()
+                .signed_duration_since(chrono::NaiveTime::MIN);
+            assert!(start >= Duration::hours(9) && end <= Duration::hours(18));
+        }
+    }
+
+    #[test]
+    fn tasks_of_a_user_never_overlap() {
+        let projects = [ObjectId::from_bytes([1; 12])];
+        // Every quarter hour of the day taken, and a busy month
+        for options in [
+            SeedOptions {
+                days: 1,
+                until: Some("2021-10-29".to_string()),
+                tasks: TASKS_PER_DAY * SEED_USERS.len(),
+                ..options()
+            },
+            SeedOptions {
+                tasks: 2_000,
+                ..options()
+            },
+        ] {
+            let mut tasks: Vec<_> = generate_tasks(&options, &projects)
+                .unwrap()
+                .iter()
+                .map(|task| {
+                    (
+                        task.get_str("user").unwrap().to_string(),
+                        task.get_datetime("initial_time").unwrap().to_chrono(),
+                        task.get_datetime("end_time").unwrap().to_chrono(),
+                    )
+                })
+                .collect();
+            assert_eq!(tasks.len(), options.tasks);
+            tasks.sort();
+            for pair in tasks.windows(2) {
+                let ((user, start, end), (next_user, next_start, _)) = (&pair[0], &pair[1]);
+                assert!(start < end);
+                if user == next_user {
+                    assert!(end <= next_start, "{} has overlapping tasks", user);
+                }
+            }
+        }
+
+        let crowded = SeedOptions {
+            days: 1,
+            until: Some("2021-10-29".to_string()),
+            tasks: TASKS_PER_DAY * SEED_USERS.len() + 1,
+            ..options()
+        };
+        assert!(generate_tasks(&crowded, &projects).is_err());
+    }
+
+    #[test]
+    fn rejects_unreasonable_options() {
+        assert!(options().validate().is_ok());
+        for options in [
+            SeedOptions {
+                days: 0,
+                ..options()
+            },
+            SeedOptions {
+                tasks: MAX_TASKS + 1,
+                ..options()
+            },
+            SeedOptions {
+                until: Some("31/10/2021".to_string()),
+                ..options()
+            },
+        ] {
+            assert!(options.validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use super::{TestApp, FIXTURE_USER};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn creates_and_suggests_activities() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+    let path = format!("/api/v1/projects/{}/activities", fixtures.project);
+
+    let (status, meetings) = app.post(&path, json!({ "name": "Meetings" })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let meetings = meetings.as_str().unwrap().to_string();
+    let (status, _) = app.post(&path, json!({ "name": "Meetings" })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (_, activities) = app.get(&path).await;
+    assert_eq!(activities.as_array().unwrap().len(), 1);
+    assert_eq!(activities[0]["name"], "Meetings");
+
+    let (status, _) = app
+        .post(
+            "/api/v1/tasks",
+            json!({
+                "name": "Weekly",
+                "user": FIXTURE_USER,
+                "initial_time": "2021-10-20T09:00:00Z",
+                "end_time": "2021-10-20T09:30:00Z",
+                "project": fixtures.project,
+                "activity": meetings,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    // Activities only go with their own project
+    let (status, _) = app
+        .post(
+            "/api/v1/tasks",
+            json!({
+                "name": "Weekly",
+                "initial_time": "2021-10-21T09:00:00Z",
+                "end_time": "2021-10-21T09:30:00Z",
+                "activity": meetings,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, suggestions) = app.get("/api/v1/activities/suggestions?q=mee").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(suggestions[0]["_id"], meetings.as_str());
+    let (_, suggestions) = app.get("/api/v1/activities/suggestions?q=design").await;
+    assert!(suggestions.as_array().unwrap().is_empty());
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn deleting_an_activity_keeps_its_tasks() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let path = format!("/api/v1/projects/{}/activities", fixtures.project);
+    let (_, meetings) = app.post(&path, json!({ "name": "Meetings" })).await;
+    let (_, task) = app
+        .post(
+            "/api/v1/tasks",
+            json!({
+                "name": "Weekly",
+                "initial_time": "2021-10-20T09:00:00Z",
+                "end_time": "2021-10-20T09:30:00Z",
+                "project": fixtures.project,
+                "activity": meetings,
+            }),
+        )
+        .await;
+
+    let activity = format!("/api/v1/activities/{}", meetings.as_str().unwrap());
+    let (status, _) = app.delete(&activity).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, activities) = app.get(&path).await;
+    assert!(activities.as_array().unwrap().is_empty());
+    let (status, task) = app
+        .get(&format!("/api/v1/tasks/{}", task["_id"].as_str().unwrap()))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(task["activity"], serde_json::Value::Null);
+
+    app.stop().await;
+}
@@ -0,0 +1,110 @@
+use super::{TestApp, FIXTURE_USER};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn creates_and_edits_clients() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+    let path = format!("/api/v1/clients/{}", fixtures.client);
+
+    let (status, client) = app.get(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(client["name"], "Acme");
+    assert_eq!(client["currency"], "EUR");
+
+    let (status, _) = app
+        .put(
+            &path,
+            json!({
+                "name": "Acme Corp",
+                "contacts": [{ "name": "Bo", "email": "bo@acme.test" }],
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, client) = app.get(&path).await;
+    assert_eq!(client["name"], "Acme Corp");
+    assert_eq!(client["contacts"][0]["email"], "bo@acme.test");
+
+    // Only the fields sent change
+    let (status, _) = app
+        .request("PATCH", &path, Some(json!({ "currency": "USD" })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, client) = app.get(&path).await;
+    assert_eq!(client["currency"], "USD");
+    assert_eq!(client["contacts"][0]["email"], "bo@acme.test");
+
+    for invalid in [
+        json!({ "currency": "Euro" }),
+        json!({ "hourly_rate": -10.0 }),
+        json!({ "contacts": [{ "name": "Bo", "email": "bo at acme" }] }),
+    ] {
+        let (status, _) = app.request("PATCH", &path, Some(invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let (status, _) = app
+        .post(
+            "/api/v1/clients",
+            json!({ "name": "Globex", "currency": "euros" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn archived_clients_are_hidden() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+    let path = format!("/api/v1/clients/{}", fixtures.client);
+
+    let (status, _) = app.post(&format!("{}/archive", path), json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, clients) = app.get("/api/v1/clients").await;
+    assert!(clients.as_array().unwrap().is_empty());
+    let (_, clients) = app.get("/api/v1/clients?include_archived=true").await;
+    assert_eq!(clients.as_array().unwrap().len(), 1);
+    // Projects of archived clients are hidden too
+    let (_, groups) = app.get("/api/v1/projects").await;
+    assert!(groups.as_array().unwrap().is_empty());
+    // And take no new tasks
+    let task = json!({
+        "name": "Support",
+        "user": FIXTURE_USER,
+        "initial_time": "2021-10-20T09:00:00Z",
+        "end_time": "2021-10-20T10:00:00Z",
+        "project": fixtures.project,
+    });
+    let (status, _) = app.post("/api/v1/tasks", task.clone()).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, _) = app.post(&format!("{}/unarchive", path), json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, clients) = app.get("/api/v1/clients").await;
+    assert_eq!(clients.as_array().unwrap().len(), 1);
+    let (status, _) = app.post("/api/v1/tasks", task).await;
+    assert_eq!(status, StatusCode::OK);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn deletes_clients() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+    let path = format!("/api/v1/clients/{}", fixtures.client);
+
+    let (status, deleted) = app.delete(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deleted, fixtures.client.as_str());
+    let (status, _) = app.delete(&path).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    app.stop().await;
+}
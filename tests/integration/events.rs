@@ -0,0 +1,72 @@
+use super::{api, TestApp, FIXTURE_USER};
+use serde_json::{json, Value};
+use std::time::Duration;
+use warp::http::StatusCode;
+use warp::Reply;
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn streams_server_sent_events() {
+    let app = TestApp::start().await;
+
+    // The stream never ends, so only the head of the reply is checked
+    let reply = warp::test::request()
+        .path("/api/v1/events?workspace=default")
+        .filter(&api(app.db.clone(), true))
+        .await
+        .unwrap()
+        .into_response();
+    assert_eq!(reply.status(), StatusCode::OK);
+    assert_eq!(reply.headers()["content-type"], "text/event-stream");
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn pushes_changes_over_websockets() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let path = format!("/api/v1/events/ws?user={}", FIXTURE_USER);
+    let mut socket = warp::test::ws()
+        .path(&path)
+        .handshake(api(app.db.clone(), true))
+        .await
+        .unwrap();
+
+    // Tasks of other users are left out
+    app.post(
+        "/api/v1/tasks",
+        json!({
+            "name": "Other",
+            "user": "bo",
+            "initial_time": "2021-10-20T09:00:00Z",
+            "end_time": "2021-10-20T10:00:00Z",
+        }),
+    )
+    .await;
+    let (_, task) = app
+        .post(
+            "/api/v1/tasks",
+            json!({
+                "name": "Support",
+                "user": FIXTURE_USER,
+                "initial_time": "2021-10-20T09:00:00Z",
+                "end_time": "2021-10-20T10:00:00Z",
+                "project": fixtures.project,
+            }),
+        )
+        .await;
+
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.recv())
+        .await
+        .expect("no event within 5 seconds")
+        .unwrap();
+    let change: Value = serde_json::from_str(message.to_str().unwrap()).unwrap();
+    assert_eq!(change["event"], "task.created");
+    assert_eq!(change["workspace"], "default");
+    assert_eq!(change["data"]["_id"], task["_id"]);
+
+    app.stop().await;
+}
@@ -0,0 +1,156 @@
+// End-to-end tests driving `routes::api` with `warp::test`. Each test gets a
+// database of its own on `TEST_MONGODB_URI` (the local `mongod` by default),
+// dropped once it passes. They need a replica set for transactions, so they
+// are ignored by default: `cargo test -- --ignored` runs them, and they fail
+// when no server answers.
+mod activities;
+mod clients;
+mod events;
+mod projects;
+mod reports;
+mod seed;
+mod tags;
+mod tasks;
+mod timesheets;
+mod webhooks;
+mod workspaces;
+
+use mongodb::bson::oid::ObjectId;
+use rust_mongodb_warp::db::DB;
+use rust_mongodb_warp::routes::api;
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+use warp::test::RequestBuilder;
+
+const DEFAULT_TEST_MONGODB_URI: &str = "mongodb://127.0.0.1:27017/?serverSelectionTimeoutMS=2000";
+
+// Fixture tasks of `FIXTURE_USER`: name, start and end
+pub const FIXTURE_TASKS: [(&str, &str, &str); 3] = [
+    ("Design", "2021-10-18T09:00:00Z", "2021-10-18T10:30:00Z"),
+    ("Review", "2021-10-18T11:00:00Z", "2021-10-18T11:45:00Z"),
+    ("Deploy", "2021-10-19T14:00:00Z", "2021-10-19T16:00:00Z"),
+];
+pub const FIXTURE_USER: &str = "ana";
+
+pub struct Fixtures {
+    pub client: String,
+    pub project: String,
+    // In the order of `FIXTURE_TASKS`
+    pub tasks: Vec<String>,
+}
+
+pub struct TestApp {
+    pub db: DB,
+}
+
+impl TestApp {
+    pub async fn start() -> TestApp {
+        let uri = std::env::var("TEST_MONGODB_URI")
+            .unwrap_or_else(|_| DEFAULT_TEST_MONGODB_URI.to_string());
+        let name = format!("test-{}", ObjectId::new().to_hex());
+
+        match DB::connect(&uri, &name).await {
+            Ok(db) => TestApp { db },
+            Err(e) => panic!("no MongoDB at {}: {}", uri, e),
+        }
+    }
+
+    pub async fn stop(self) {
+        self.db.database().drop(None).await.unwrap();
+    }
+
+    /// Status and JSON body of a request, `Value::Null` for empty bodies.
+    pub async fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = warp::test::request().method(method).path(path);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+
+        self.send(request).await
+    }
+
+    /// Like `request`, for requests that need headers of their own.
+    pub async fn send(&self, request: RequestBuilder) -> (StatusCode, Value) {
+        let reply = request.reply(&api(self.db.clone(), true)).await;
+        let body = serde_json::from_slice(reply.body()).unwrap_or(Value::Null);
+
+        (reply.status(), body)
+    }
+
+    pub async fn get(&self, path: &str) -> (StatusCode, Value) {
+        self.request("GET", path, None).await
+    }
+
+    pub async fn post(&self, path: &str, body: Value) -> (StatusCode, Value) {
+        self.request("POST", path, Some(body)).await
+    }
+
+    pub async fn put(&self, path: &str, body: Value) -> (StatusCode, Value) {
+        self.request("PUT", path, Some(body)).await
+    }
+
+    pub async fn delete(&self, path: &str) -> (StatusCode, Value) {
+        self.request("DELETE", path, None).await
+    }
+
+    /// Client "Acme", its project "Website" with a 10 hour estimate and the
+    /// `FIXTURE_TASKS` on it.
+    pub async fn seed_fixtures(&self) -> Fixtures {
+        let (status, _) = self
+            .post(
+                "/api/v1/clients",
+                json!({ "name": "Acme", "currency": "EUR", "hourly_rate": 80.0 }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (_, clients) = self.get("/api/v1/clients").await;
+        let client = clients[0]["_id"].as_str().unwrap().to_string();
+
+        let (status, _) = self
+            .post(
+                "/api/v1/projects",
+                json!({
+                    "client": client,
+                    "name": "Website",
+                    "color": "#61e294ff",
+                    "estimate": { "kind": "hours", "amount": 10.0, "recurrence": "total" },
+                }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (_, groups) = self.get("/api/v1/projects").await;
+        let project = groups[0]["projects"][0]["_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let mut tasks = vec![];
+        for (name, start, end) in FIXTURE_TASKS {
+            let (status, task) = self
+                .post(
+                    "/api/v1/tasks",
+                    json!({
+                        "name": name,
+                        "user": FIXTURE_USER,
+                        "initial_time": start,
+                        "end_time": end,
+                        "project": project,
+                    }),
+                )
+                .await;
+            assert_eq!(status, StatusCode::OK);
+            tasks.push(task["_id"].as_str().unwrap().to_string());
+        }
+
+        Fixtures {
+            client,
+            project,
+            tasks,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use super::TestApp;
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn lists_projects_grouped_by_client() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let (status, groups) = app.get("/api/v1/projects").await;
+    assert_eq!(status, StatusCode::OK);
+    let groups = groups.as_array().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["_id"], "Acme");
+    assert_eq!(groups[0]["projects"][0]["name"], "Website");
+
+    let path = format!("/api/v1/projects/{}", fixtures.project);
+    let (status, project) = app.get(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(project["client"], fixtures.client.as_str());
+    assert_eq!(project["status"], "active");
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn reports_budget_consumption() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let path = format!("/api/v1/projects/{}/budget", fixtures.project);
+    let (status, budget) = app.get(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    // 1:30 + 0:45 + 2:00 of a 10 hour estimate
+    assert_eq!(budget["tracked"]["seconds"], 15300);
+    assert_eq!(budget["percentage"], 42.5);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn archived_projects_reject_new_tasks() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let path = format!("/api/v1/projects/{}/status", fixtures.project);
+    let (status, _) = app.put(&path, json!({ "status": "archived" })).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.put(&path, json!({ "status": "on-hold" })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = app
+        .post(
+            "/api/v1/tasks",
+            json!({
+                "name": "Late",
+                "initial_time": "2021-10-20T09:00:00Z",
+                "end_time": "2021-10-20T10:00:00Z",
+                "project": fixtures.project,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (_, groups) = app.get("/api/v1/projects").await;
+    assert!(groups.as_array().unwrap().is_empty());
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn deletes_projects() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let path = format!("/api/v1/projects/{}", fixtures.project);
+    let (status, _) = app.delete(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get(&path).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app.delete(&path).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    app.stop().await;
+}
+
+// Id of the project called `name`, looked up among the nested subprojects too
+async fn project_id(app: &TestApp, name: &str) -> String {
+    let (_, groups) = app.get("/api/v1/projects").await;
+    let mut projects = groups[0]["projects"].as_array().unwrap().clone();
+    while let Some(project) = projects.pop() {
+        if project["name"] == name {
+            return project["_id"].as_str().unwrap().to_string();
+        }
+        projects.extend(
+            project["subprojects"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default(),
+        );
+    }
+    panic!("no project named {}", name);
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn deleting_a_project_moves_its_subprojects_up() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let subproject = |name: &str, parent: &str| json!({ "name": name, "color": "#00f", "client": fixtures.client, "parent": parent });
+    let (status, _) = app
+        .post("/api/v1/projects", subproject("Backend", &fixtures.project))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let backend = project_id(&app, "Backend").await;
+    app.post("/api/v1/projects", subproject("Api", &backend))
+        .await;
+    let api = project_id(&app, "Api").await;
+
+    let (status, _) = app.delete(&format!("/api/v1/projects/{}", backend)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, project) = app.get(&format!("/api/v1/projects/{}", api)).await;
+    assert_eq!(project["parent"], fixtures.project.as_str());
+
+    app.stop().await;
+}
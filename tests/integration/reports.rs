@@ -0,0 +1,90 @@
+use super::TestApp;
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn sums_time_per_project() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let (status, report) = app
+        .get("/api/v1/reports/summary?from=2021-10-18&to=2021-10-18&tz=UTC")
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    // Design and Review, Deploy is on the next day
+    assert_eq!(report["total"]["seconds"], 8100);
+    assert_eq!(report["rounded_total"]["formatted"], "02:15:00");
+    assert_eq!(report["projects"][0]["project"], fixtures.project.as_str());
+    assert_eq!(report["projects"][0]["client_name"], "Acme");
+    assert_eq!(report["projects"][0]["task_count"], 2);
+
+    let (status, _) = app.get("/api/v1/reports/summary?from=18/10/2021").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Other workspaces see none of it
+    let request = warp::test::request()
+        .method("GET")
+        .path("/api/v1/reports/summary")
+        .header("x-workspace", "other");
+    let (_, report) = app.send(request).await;
+    assert_eq!(report["total"]["seconds"], 0);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn rounds_with_the_workspace_rule() {
+    let app = TestApp::start().await;
+    app.seed_fixtures().await;
+
+    let rounding = json!({ "mode": "up", "increment_minutes": 60, "scope": "per-entry" });
+    app.put(
+        "/api/v1/workspace/settings",
+        json!({ "rounding": rounding }),
+    )
+    .await;
+
+    let (_, report) = app.get("/api/v1/reports/summary").await;
+    assert_eq!(report["total"]["seconds"], 15300);
+    // 1:30, 0:45 and 2:00 rounded up to whole hours
+    assert_eq!(report["rounded_total"]["seconds"], 18000);
+    assert_eq!(report["projects"][0]["rounded"]["formatted"], "05:00:00");
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn sums_time_per_activity() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let path = format!("/api/v1/projects/{}/activities", fixtures.project);
+    let (_, meetings) = app.post(&path, json!({ "name": "Meetings" })).await;
+    app.post(
+        "/api/v1/tasks",
+        json!({
+            "name": "Weekly",
+            "initial_time": "2021-10-20T09:00:00Z",
+            "end_time": "2021-10-20T09:30:00Z",
+            "project": fixtures.project,
+            "activity": meetings,
+        }),
+    )
+    .await;
+
+    let (status, report) = app
+        .get("/api/v1/reports/activities?from=2021-10-20&to=2021-10-20")
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["total"]["seconds"], 1800);
+    let activities = report["activities"].as_array().unwrap();
+    assert_eq!(activities.len(), 1);
+    assert_eq!(activities[0]["activity"], meetings);
+    assert_eq!(activities[0]["activity_name"], "Meetings");
+    assert_eq!(activities[0]["project_name"], "Website");
+
+    app.stop().await;
+}
@@ -0,0 +1,66 @@
+use super::TestApp;
+use warp::http::StatusCode;
+
+const SEED: &str = "seed=7&clients=3&projects=4&tasks=25&days=14&until=2021-10-31";
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn seeds_and_removes_data() {
+    let app = TestApp::start().await;
+
+    let (status, summary) = app
+        .request("POST", &format!("/api/v1/seed/all?{}", SEED), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(summary["tasks"], 25);
+    let (_, clients) = app.get("/api/v1/clients").await;
+    let (_, groups) = app.get("/api/v1/projects").await;
+    let (_, tasks) = app.get("/api/v1/tasks").await;
+    assert_eq!(clients.as_array().unwrap().len(), 3);
+    assert!(!groups.as_array().unwrap().is_empty());
+    assert_eq!(tasks.as_array().unwrap().len(), 25);
+
+    let (status, _) = app.request("POST", "/api/v1/seed/remove", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, clients) = app.get("/api/v1/clients").await;
+    let (_, tasks) = app.get("/api/v1/tasks").await;
+    assert!(clients.as_array().unwrap().is_empty());
+    assert!(tasks.as_array().unwrap().is_empty());
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn seeding_each_collection_gives_the_same_data() {
+    let app = TestApp::start().await;
+
+    app.request("POST", &format!("/api/v1/seed/all?{}", SEED), None)
+        .await;
+    let (_, all) = app.get("/api/v1/tasks").await;
+
+    for collection in ["clients", "projects", "tasks"] {
+        let path = format!("/api/v1/seed/{}?{}", collection, SEED);
+        let (status, _) = app.request("POST", &path, None).await;
+        assert_eq!(status, StatusCode::OK, "{}", path);
+    }
+    let (_, tasks) = app.get("/api/v1/tasks").await;
+    assert_eq!(tasks, all);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn rejects_invalid_seeds() {
+    let app = TestApp::start().await;
+
+    let (status, _) = app.request("POST", "/api/v1/seed/all?days=0", None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = app.request("POST", "/api/v1/seed/tasks", None).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = app.get("/api/v1/seed/all").await;
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+
+    app.stop().await;
+}
@@ -0,0 +1,135 @@
+use super::{TestApp, FIXTURE_USER};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn creates_renames_and_filters_by_tags() {
+    let app = TestApp::start().await;
+    app.seed_fixtures().await;
+
+    let (status, billable) = app
+        .post("/api/v1/tags", json!({ "name": "billable" }))
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let billable = billable.as_str().unwrap().to_string();
+    let (status, _) = app
+        .post("/api/v1/tags", json!({ "name": "billable" }))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let task = json!({
+        "name": "Support",
+        "user": FIXTURE_USER,
+        "initial_time": "2021-10-20T09:00:00Z",
+        "end_time": "2021-10-20T10:00:00Z",
+        "tags": [billable],
+    });
+    let (status, _) = app.post("/api/v1/tasks", task).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, tasks) = app.get(&format!("/api/v1/tasks?tag={}", billable)).await;
+    assert_eq!(tasks.as_array().unwrap().len(), 1);
+    assert_eq!(tasks[0]["tags"][0], billable.as_str());
+
+    let path = format!("/api/v1/tags/{}", billable);
+    let (status, _) = app.put(&path, json!({ "name": "invoiced" })).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, tags) = app.get("/api/v1/tags").await;
+    assert_eq!(tags[0]["name"], "invoiced");
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn merges_and_deletes_tags() {
+    let app = TestApp::start().await;
+
+    let (_, billable) = app
+        .post("/api/v1/tags", json!({ "name": "billable" }))
+        .await;
+    let (_, invoiced) = app
+        .post("/api/v1/tags", json!({ "name": "invoiced" }))
+        .await;
+    let (billable, invoiced) = (billable.as_str().unwrap(), invoiced.as_str().unwrap());
+    let (_, task) = app
+        .post(
+            "/api/v1/tasks",
+            json!({
+                "name": "Support",
+                "initial_time": "2021-10-20T09:00:00Z",
+                "end_time": "2021-10-20T10:00:00Z",
+                "tags": [billable],
+            }),
+        )
+        .await;
+    let task = format!("/api/v1/tasks/{}", task["_id"].as_str().unwrap());
+
+    let path = format!("/api/v1/tags/{}/merge", billable);
+    let (status, _) = app.post(&path, json!({ "into": invoiced })).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, tags) = app.get("/api/v1/tags").await;
+    assert_eq!(tags.as_array().unwrap().len(), 1);
+    let (_, merged) = app.get(&task).await;
+    assert_eq!(merged["tags"], json!([invoiced]));
+
+    let (status, _) = app.delete(&format!("/api/v1/tags/{}", invoiced)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, untagged) = app.get(&task).await;
+    assert_eq!(untagged["tags"], json!([]));
+
+    // Tags that do not exist (anymore) are refused
+    let (status, _) = app
+        .post(
+            "/api/v1/tasks",
+            json!({
+                "name": "Support",
+                "initial_time": "2021-10-21T09:00:00Z",
+                "end_time": "2021-10-21T10:00:00Z",
+                "tags": [invoiced],
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn tags_belong_to_their_workspace() {
+    let app = TestApp::start().await;
+
+    let (_, billable) = app
+        .post("/api/v1/tags", json!({ "name": "billable" }))
+        .await;
+    let request = warp::test::request()
+        .method("GET")
+        .path("/api/v1/tags")
+        .header("x-workspace", "other");
+    let (_, tags) = app.send(request).await;
+    assert!(tags.as_array().unwrap().is_empty());
+
+    // The same name is free in another workspace, the tag itself is not usable
+    let request = warp::test::request()
+        .method("POST")
+        .path("/api/v1/tags")
+        .header("x-workspace", "other")
+        .json(&json!({ "name": "billable" }));
+    let (status, _) = app.send(request).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let request = warp::test::request()
+        .method("POST")
+        .path("/api/v1/tasks")
+        .header("x-workspace", "other")
+        .json(&json!({
+            "name": "Support",
+            "initial_time": "2021-10-20T09:00:00Z",
+            "end_time": "2021-10-20T10:00:00Z",
+            "tags": [billable],
+        }));
+    let (status, _) = app.send(request).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    app.stop().await;
+}
@@ -0,0 +1,185 @@
+use super::{TestApp, FIXTURE_USER};
+use serde_json::json;
+use warp::http::StatusCode;
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn creates_and_lists_tasks() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+
+    let (status, tasks) = app.get("/api/v1/tasks").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(tasks.as_array().unwrap().len(), 3);
+
+    let path = format!("/api/v1/tasks?user={}&from=2021-10-19", FIXTURE_USER);
+    let (_, tasks) = app.get(&path).await;
+    assert_eq!(tasks.as_array().unwrap().len(), 1);
+    assert_eq!(tasks[0]["name"], "Deploy");
+    let (_, tasks) = app.get("/api/v1/tasks?user=bo").await;
+    assert!(tasks.as_array().unwrap().is_empty());
+
+    let path = format!("/api/v1/tasks/{}", fixtures.tasks[0]);
+    let (status, task) = app.get(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(task["name"], "Design");
+    assert_eq!(task["project"], fixtures.project.as_str());
+    assert_eq!(task["duration"]["formatted"], "01:30:00");
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn edits_splits_and_deletes_tasks() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+    let path = format!("/api/v1/tasks/{}", fixtures.tasks[0]);
+
+    let (status, _) = app
+        .put(
+            &path,
+            json!({
+                "name": "Design review",
+                "user": FIXTURE_USER,
+                "initial_time": "2021-10-18T08:00:00Z",
+                "end_time": "2021-10-18T10:30:00Z",
+                "project": fixtures.project,
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, task) = app.get(&path).await;
+    assert_eq!(task["name"], "Design review");
+    assert_eq!(task["duration"]["seconds"], 9000);
+
+    let (status, halves) = app
+        .post(
+            &format!("{}/split", path),
+            json!({ "at": "2021-10-18T09:00:00Z" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(halves["first"], fixtures.tasks[0].as_str());
+    let (_, tasks) = app.get("/api/v1/tasks").await;
+    assert_eq!(tasks.as_array().unwrap().len(), 4);
+
+    let (status, _) = app.delete(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get(&path).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn rejects_invalid_tasks() {
+    let app = TestApp::start().await;
+
+    let (status, _) = app.get("/api/v1/tasks/not-an-id").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = app
+        .post("/api/v1/tasks", json!({ "name": "No times" }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn groups_tasks_by_day() {
+    let app = TestApp::start().await;
+    app.seed_fixtures().await;
+
+    let (status, days) = app
+        .get("/api/v1/tasks/group?tz=UTC&from=2021-10-01&to=2021-10-31")
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let days = days.as_array().unwrap();
+    assert_eq!(days.len(), 2);
+    assert_eq!(days[0]["_id"], "2021-10-19");
+    assert_eq!(days[0]["total_time"]["seconds"], 7200);
+    assert_eq!(days[1]["_id"], "2021-10-18");
+    assert_eq!(days[1]["total_time"]["formatted"], "02:15:00");
+    assert_eq!(days[1]["tasks"].as_array().unwrap().len(), 2);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn legacy_paths_are_deprecated() {
+    let app = TestApp::start().await;
+
+    let reply = warp::test::request()
+        .path("/tasks")
+        .reply(&super::api(app.db.clone(), true))
+        .await;
+    assert_eq!(reply.status(), StatusCode::OK);
+    assert_eq!(reply.headers()["deprecation"], "true");
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn tasks_belong_to_their_workspace() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+    let path = format!("/api/v1/tasks/{}", fixtures.tasks[0]);
+
+    let request = warp::test::request()
+        .method("GET")
+        .path(&path)
+        .header("x-workspace", "other");
+    let (status, _) = app.send(request).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    for list in [
+        "/api/v1/tasks",
+        "/api/v1/tasks/group?from=2021-10-18&to=2021-10-19",
+    ] {
+        let request = warp::test::request()
+            .method("GET")
+            .path(list)
+            .header("x-workspace", "other");
+        let (_, tasks) = app.send(request).await;
+        assert!(tasks.as_array().unwrap().is_empty());
+    }
+    let request = warp::test::request()
+        .method("DELETE")
+        .path(&path)
+        .header("x-workspace", "other");
+    let (status, _) = app.send(request).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Editing without the header keeps the task in its workspace
+    let request = warp::test::request()
+        .method("POST")
+        .path("/api/v1/tasks")
+        .header("x-workspace", "team")
+        .json(&json!({
+            "name": "Support",
+            "initial_time": "2021-10-20T09:00:00Z",
+            "end_time": "2021-10-20T10:00:00Z",
+        }));
+    let (_, task) = app.send(request).await;
+    let path = format!("/api/v1/tasks/{}", task["_id"].as_str().unwrap());
+    let edit = json!({
+        "name": "Support call",
+        "initial_time": "2021-10-20T09:00:00Z",
+        "end_time": "2021-10-20T10:00:00Z",
+    });
+    let (status, _) = app.put(&path, edit).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let request = warp::test::request()
+        .method("GET")
+        .path(&path)
+        .header("x-workspace", "team");
+    let (_, task) = app.send(request).await;
+    assert_eq!(task["name"], "Support");
+
+    app.stop().await;
+}
@@ -0,0 +1,138 @@
+use super::{TestApp, FIXTURE_USER};
+use serde_json::json;
+use warp::http::StatusCode;
+
+// Monday of the week of the fixture tasks
+const WEEK: &str = "2021-10-18";
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn fills_in_the_week_grid() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+    let path = format!(
+        "/api/v1/timesheets/week?start={}&user={}",
+        WEEK, FIXTURE_USER
+    );
+
+    let (status, week) = app.get(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(week["status"], "draft");
+    assert_eq!(week["rows"].as_array().unwrap().len(), 1);
+    assert_eq!(week["day_totals"][0]["seconds"], 8100);
+    assert_eq!(week["day_totals"][1]["seconds"], 7200);
+    assert_eq!(week["total"]["seconds"], 15300);
+
+    // An hour on Wednesday, the other days as they are
+    let (status, week) = app
+        .put(
+            "/api/v1/timesheets/week",
+            json!({
+                "start": WEEK,
+                "user": FIXTURE_USER,
+                "rows": [{
+                    "project": fixtures.project,
+                    "days": [8100, 7200, 3600, 0, 0, 0, 0],
+                }],
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(week["day_totals"][2]["seconds"], 3600);
+    assert_eq!(week["total"]["seconds"], 18900);
+    let (_, tasks) = app.get("/api/v1/tasks").await;
+    assert_eq!(tasks.as_array().unwrap().len(), 4);
+
+    let (status, _) = app
+        .put(
+            "/api/v1/timesheets/week",
+            json!({
+                "start": WEEK,
+                "user": FIXTURE_USER,
+                "rows": [{ "project": fixtures.project, "days": [3600] }],
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn approved_weeks_are_locked() {
+    let app = TestApp::start().await;
+    let fixtures = app.seed_fixtures().await;
+    let status_path = "/api/v1/timesheets/week/status";
+    let status_change = |status: &str| {
+        json!({
+            "start": WEEK,
+            "user": FIXTURE_USER,
+            "status": status,
+            "author": "boss",
+        })
+    };
+
+    let admins = warp::test::request()
+        .method("PUT")
+        .path("/api/v1/workspace/settings")
+        .header("x-lock-override", "boss")
+        .json(&json!({ "admins": ["boss"] }));
+    app.send(admins).await;
+
+    let (status, timesheet) = app.put(status_path, status_change("submitted")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(timesheet["status"], "submitted");
+    // Only admins review
+    let mut approval = status_change("approved");
+    approval["author"] = json!("intern");
+    let (status, _) = app.put(status_path, approval).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, pending) = app.get("/api/v1/timesheets/pending").await;
+    assert_eq!(pending.as_array().unwrap().len(), 1);
+    // Rejections need a comment
+    let (status, _) = app.put(status_path, status_change("rejected")).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (status, timesheet) = app.put(status_path, status_change("approved")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(timesheet["tasks"].as_array().unwrap().len(), 3);
+    assert_eq!(timesheet["approved_by"], "boss");
+    let (_, pending) = app.get("/api/v1/timesheets/pending").await;
+    assert!(pending.as_array().unwrap().is_empty());
+    let (status, _) = app.put(status_path, status_change("submitted")).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let task = json!({
+        "name": "Late",
+        "user": FIXTURE_USER,
+        "initial_time": "2021-10-20T09:00:00Z",
+        "end_time": "2021-10-20T10:00:00Z",
+        "project": fixtures.project,
+    });
+    let (status, _) = app.post("/api/v1/tasks", task.clone()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let path = format!("/api/v1/tasks/{}", fixtures.tasks[0]);
+    let (status, _) = app.delete(&path).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    // Nor through the grid of a week starting on another day
+    let (status, _) = app
+        .put(
+            "/api/v1/timesheets/week",
+            json!({
+                "start": "2021-10-13",
+                "user": FIXTURE_USER,
+                "rows": [{ "project": fixtures.project, "days": [0, 0, 0, 0, 0, 0, 0] }],
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Reopening unlocks the week
+    let (status, _) = app.put(status_path, status_change("draft")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.post("/api/v1/tasks", task).await;
+    assert_eq!(status, StatusCode::OK);
+
+    app.stop().await;
+}
@@ -0,0 +1,97 @@
+use super::{TestApp, FIXTURE_USER};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+
+// Nothing listens there, deliveries stay pending
+const HOOK_URL: &str = "http://127.0.0.1:9/hook";
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn queues_deliveries_for_subscribed_events() {
+    let app = TestApp::start().await;
+
+    let (status, webhook) = app
+        .post(
+            "/api/v1/webhooks",
+            json!({ "url": HOOK_URL, "secret": "s3cret", "events": ["task.created"] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let webhook = webhook.as_str().unwrap().to_string();
+    let (_, webhooks) = app.get("/api/v1/webhooks").await;
+    assert_eq!(webhooks[0]["url"], HOOK_URL);
+    assert_eq!(webhooks[0]["secret"], Value::Null);
+
+    // One created task, the project and client events are not subscribed
+    let fixtures = app.seed_fixtures().await;
+    let path = format!("/api/v1/webhooks/{}/deliveries", webhook);
+    let (status, deliveries) = app.get(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    let deliveries = deliveries.as_array().unwrap();
+    assert_eq!(deliveries.len(), fixtures.tasks.len());
+    assert!(deliveries
+        .iter()
+        .all(|delivery| delivery["event"] == "task.created" && delivery["status"] == "pending"));
+    let payload: Value = serde_json::from_str(deliveries[0]["payload"].as_str().unwrap()).unwrap();
+    assert_eq!(payload["data"]["user"], FIXTURE_USER);
+
+    let path = format!("/api/v1/webhooks/{}", webhook);
+    let (status, _) = app.delete(&path).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.delete(&path).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn webhooks_belong_to_their_workspace() {
+    let app = TestApp::start().await;
+
+    let (status, _) = app
+        .post(
+            "/api/v1/webhooks",
+            json!({ "url": "ftp://example.com", "secret": "s3cret", "events": ["task.created"] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = app
+        .post(
+            "/api/v1/webhooks",
+            json!({ "url": HOOK_URL, "secret": "s3cret", "events": [] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (_, webhook) = app
+        .post(
+            "/api/v1/webhooks",
+            json!({ "url": HOOK_URL, "secret": "s3cret", "events": ["task.created"] }),
+        )
+        .await;
+    let request = warp::test::request()
+        .method("GET")
+        .path("/api/v1/webhooks")
+        .header("x-workspace", "other");
+    let (_, webhooks) = app.send(request).await;
+    assert!(webhooks.as_array().unwrap().is_empty());
+
+    // Tasks of another workspace are not delivered
+    let request = warp::test::request()
+        .method("POST")
+        .path("/api/v1/tasks")
+        .header("x-workspace", "other")
+        .json(&json!({
+            "name": "Support",
+            "initial_time": "2021-10-20T09:00:00Z",
+            "end_time": "2021-10-20T10:00:00Z",
+        }));
+    let (status, _) = app.send(request).await;
+    assert_eq!(status, StatusCode::OK);
+    let path = format!("/api/v1/webhooks/{}/deliveries", webhook.as_str().unwrap());
+    let (_, deliveries) = app.get(&path).await;
+    assert!(deliveries.as_array().unwrap().is_empty());
+
+    app.stop().await;
+}
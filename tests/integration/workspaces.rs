@@ -0,0 +1,171 @@
+use super::{TestApp, FIXTURE_USER};
+use serde_json::{json, Value};
+use warp::http::StatusCode;
+use warp::test::RequestBuilder;
+
+// Settings changed by `admin`
+fn settings_as(admin: &str, settings: Value) -> RequestBuilder {
+    warp::test::request()
+        .method("PUT")
+        .path("/api/v1/workspace/settings")
+        .header("x-lock-override", admin)
+        .json(&settings)
+}
+
+fn task(start: &str, end: &str) -> Value {
+    json!({
+        "name": "Support",
+        "user": FIXTURE_USER,
+        "initial_time": start,
+        "end_time": end,
+    })
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn settings_belong_to_their_workspace() {
+    let app = TestApp::start().await;
+
+    let (status, settings) = app.get("/api/v1/workspace/settings").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(settings["overlap_policy"], "warn");
+
+    let (status, _) = app
+        .put(
+            "/api/v1/workspace/settings",
+            json!({ "overlap_policy": "reject", "lock_date": "2021-10-19" }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let settings = json!({
+        "overlap_policy": "reject",
+        "lock_date": "2021-10-19",
+        "admins": ["boss"],
+    });
+    let (status, _) = app.send(settings_as("boss", settings)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, settings) = app.get("/api/v1/workspace/settings").await;
+    assert_eq!(settings["overlap_policy"], "reject");
+    assert_eq!(settings["lock_date"], "2021-10-19");
+
+    let request = warp::test::request()
+        .method("GET")
+        .path("/api/v1/workspace/settings")
+        .header("x-workspace", "other");
+    let (_, settings) = app.send(request).await;
+    assert_eq!(settings["overlap_policy"], "warn");
+    assert_eq!(settings["lock_date"], Value::Null);
+
+    let (status, _) = app
+        .send(settings_as(
+            "boss",
+            json!({ "lock_date": "19/10/2021", "admins": ["boss"] }),
+        ))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn applies_the_overlap_policy() {
+    let app = TestApp::start().await;
+    app.seed_fixtures().await;
+    // Deploy runs from 14:00 to 16:00
+    let (status, written) = app
+        .post(
+            "/api/v1/tasks",
+            task("2021-10-19T15:00:00Z", "2021-10-19T17:00:00Z"),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(written["overlapping_tasks"].as_array().unwrap().len(), 1);
+
+    let overlapping = task("2021-10-19T16:30:00Z", "2021-10-19T18:00:00Z");
+    app.put(
+        "/api/v1/workspace/settings",
+        json!({ "overlap_policy": "reject" }),
+    )
+    .await;
+    let (status, _) = app.post("/api/v1/tasks", overlapping.clone()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    app.put(
+        "/api/v1/workspace/settings",
+        json!({ "overlap_policy": "auto-trim" }),
+    )
+    .await;
+    let (status, written) = app.post("/api/v1/tasks", overlapping).await;
+    assert_eq!(status, StatusCode::OK);
+    let path = format!("/api/v1/tasks/{}", written["_id"].as_str().unwrap());
+    let (_, trimmed) = app.get(&path).await;
+    assert_eq!(trimmed["initial_time"], "2021-10-19T17:00:00Z");
+    assert_eq!(trimmed["end_time"], "2021-10-19T18:00:00Z");
+
+    app.stop().await;
+}
+
+#[tokio::test]
+#[ignore = "needs MongoDB, run with --ignored"]
+async fn admins_write_inside_the_locked_period() {
+    let app = TestApp::start().await;
+    app.send(settings_as(
+        "boss",
+        json!({ "lock_date": "2021-10-19", "admins": ["boss"] }),
+    ))
+    .await;
+    let locked = task("2021-10-18T09:00:00Z", "2021-10-18T10:00:00Z");
+
+    let (status, _) = app.post("/api/v1/tasks", locked.clone()).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = app
+        .post(
+            "/api/v1/tasks",
+            task("2021-10-19T09:00:00Z", "2021-10-19T10:00:00Z"),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let as_admin = |admin: &str| {
+        warp::test::request()
+            .method("POST")
+            .path("/api/v1/tasks")
+            .header("x-lock-override", admin)
+            .json(&locked)
+    };
+    let (status, _) = app.send(as_admin("intern")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, written) = app.send(as_admin("boss")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Only admins move the lock date
+    let (status, _) = app
+        .send(settings_as("intern", json!({ "admins": ["boss"] })))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .send(settings_as("boss", json!({ "admins": ["boss"] })))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, overrides) = app.get("/api/v1/workspace/lock-overrides").await;
+    let mut actions: Vec<&str> = overrides
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|record| record["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions[..2], ["lock-date", "create"]);
+    // Both written by the first change
+    actions[2..].sort();
+    assert_eq!(actions[2..], ["admins", "lock-date"]);
+    assert_eq!(overrides[1]["task"], written["_id"]);
+    assert!(overrides
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|record| record["admin"] == "boss"));
+
+    app.stop().await;
+}